@@ -0,0 +1,13 @@
+ALTER TABLE messages ADD COLUMN parent_id INTEGER REFERENCES messages(id);
+ALTER TABLE conversations ADD COLUMN active_leaf INTEGER;
+
+-- Existing histories are linear: each message follows the one before it
+UPDATE messages SET parent_id = (
+    SELECT MAX(earlier.id) FROM messages earlier
+    WHERE earlier.conversation_id = messages.conversation_id AND earlier.id < messages.id
+);
+UPDATE conversations SET active_leaf = (
+    SELECT MAX(id) FROM messages WHERE messages.conversation_id = conversations.id
+);
+
+CREATE INDEX IF NOT EXISTS idx_messages_parent ON messages(parent_id);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS personas (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    model TEXT NOT NULL,
+    system_prompt TEXT,
+    voice TEXT NOT NULL,
+    vault_folders TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_personas_user ON personas(user_id, name);
+
+ALTER TABLE conversations ADD COLUMN persona_id TEXT REFERENCES personas(id) ON DELETE SET NULL;
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS model_votes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    team TEXT NOT NULL,
+    category TEXT NOT NULL,
+    conversation_id TEXT REFERENCES conversations(id) ON DELETE SET NULL,
+    prompt TEXT,
+    model_a TEXT NOT NULL,
+    response_a TEXT NOT NULL,
+    model_b TEXT NOT NULL,
+    response_b TEXT NOT NULL,
+    outcome TEXT NOT NULL CHECK (outcome IN ('a', 'b', 'tie')),
+    tags TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_model_votes_team ON model_votes(team, category, id);
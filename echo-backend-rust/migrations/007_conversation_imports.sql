@@ -0,0 +1,6 @@
+-- Where an imported conversation came from, so re-importing the same export is a no-op
+ALTER TABLE conversations ADD COLUMN source TEXT;
+ALTER TABLE conversations ADD COLUMN source_id TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_conversations_source
+    ON conversations(user_id, source, source_id) WHERE source_id IS NOT NULL;
@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS batches (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    status TEXT NOT NULL, -- queued, running, completed or cancelled
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_batches_user ON batches(user_id, created_at);
+
+CREATE TABLE IF NOT EXISTS batch_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    batch_id TEXT NOT NULL REFERENCES batches(id) ON DELETE CASCADE,
+    line INTEGER NOT NULL,
+    custom_id TEXT,
+    request TEXT NOT NULL,
+    status TEXT NOT NULL, -- pending, succeeded, failed or cancelled
+    response TEXT,
+    error TEXT,
+    completed_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_batch_items_batch ON batch_items(batch_id, status, id);
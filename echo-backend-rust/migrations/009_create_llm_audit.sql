@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS llm_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,          -- the model spec that was routed, e.g. "claude-3-opus" or "proxy:name:model"
+    request TEXT NOT NULL,        -- messages and generation params, enough to replay the call
+    payload TEXT NOT NULL,        -- the provider request body as sent
+    response TEXT,                -- the raw provider reply
+    error TEXT,
+    latency_ms INTEGER NOT NULL,
+    input_tokens INTEGER,
+    output_tokens INTEGER,
+    redacted INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_llm_audit_created ON llm_audit(created_at);
+CREATE INDEX IF NOT EXISTS idx_llm_audit_user ON llm_audit(user_id, created_at);
@@ -0,0 +1,21 @@
+-- Refresh tokens are stored as SHA-256 hashes; the plaintext only ever goes to the client
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL,
+    revoked_at INTEGER,
+    replaced_by TEXT -- id of the token issued when this one was rotated
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_user ON refresh_tokens(user_id);
+
+-- Access tokens revoked before they expire, keyed by their jti claim
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti TEXT PRIMARY KEY,
+    expires_at INTEGER NOT NULL
+);
+
+-- Access tokens issued at or before this time are rejected
+ALTER TABLE users ADD COLUMN sessions_revoked_at INTEGER;
@@ -0,0 +1,8 @@
+-- Cookie sessions created by passphrase unlock, so an unlock survives restarts
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    data TEXT NOT NULL,        -- JSON map of session values
+    expires_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_expires_at ON sessions(expires_at);
@@ -0,0 +1,2 @@
+-- Disabled accounts can't log in or refresh; set and cleared by admins
+ALTER TABLE users ADD COLUMN disabled_at INTEGER;
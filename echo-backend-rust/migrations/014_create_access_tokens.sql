@@ -0,0 +1,17 @@
+-- Long-lived personal access tokens for scripts; stored as SHA-256 hashes
+CREATE TABLE IF NOT EXISTS access_tokens (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    prefix TEXT NOT NULL,          -- first characters of the token, to tell tokens apart in listings
+    features TEXT NOT NULL,        -- JSON array; a subset of the owner's features
+    allowed_ips TEXT,              -- JSON array of CIDR ranges, NULL for anywhere
+    expires_at INTEGER,            -- NULL for no expiry
+    created_at INTEGER NOT NULL,
+    last_used_at INTEGER,
+    last_used_ip TEXT,
+    revoked_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_access_tokens_user ON access_tokens(user_id);
@@ -0,0 +1,17 @@
+-- Provider API keys and other credentials, encrypted with a key derived from the vault passphrase
+CREATE TABLE IF NOT EXISTS secrets (
+    name TEXT PRIMARY KEY,
+    nonce BLOB NOT NULL,
+    ciphertext BLOB NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+-- The salt the secrets key is derived with, and a known value sealed under it
+-- so a wrong key is caught before anything is decrypted
+CREATE TABLE IF NOT EXISTS secrets_key (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    salt BLOB NOT NULL,
+    check_nonce BLOB NOT NULL,
+    check_ciphertext BLOB NOT NULL
+);
@@ -0,0 +1,3 @@
+-- sessions_revoked_at moves from seconds to microseconds, so a login in the same
+-- second as a revoke-all is no longer rejected along with the tokens it replaced
+UPDATE users SET sessions_revoked_at = sessions_revoked_at * 1000000 WHERE sessions_revoked_at IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Passphrase failures now count per address only; drop the old shared count
+-- so an existing lockout on it doesn't linger
+DELETE FROM login_failures WHERE key = 'passphrase';
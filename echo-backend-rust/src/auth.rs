@@ -1,38 +1,105 @@
 // auth.rs - Enhanced authentication module
 
 use axum::{
-    extract::Request,
-    http::{header, StatusCode},
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Request},
+    http::{header, request::Parts, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
-    Extension,
 };
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use tower_sessions::Session;
+use crate::db::access_tokens::{self, TOKEN_PREFIX};
+use crate::models::ip_range::IpRange;
+
+/// Access tokens are short-lived; clients renew them with a refresh token
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+
+/// `jti` prefix marking claims that came from a personal access token
+pub const ACCESS_TOKEN_JTI_PREFIX: &str = "pat:";
+
+/// Identity of a cookie session unlocked with the vault passphrase. Whoever
+/// holds the passphrase owns the installation, so the session gets every feature.
+pub const SESSION_USER: &str = "owner";
+
+/// Owner of data created by requests that carry no identity
+pub const ANONYMOUS_USER: &str = "anonymous";
+
+/// Data is keyed by `Claims::sub`, so an account named after one of the
+/// built-in identities would share its data. Usernames are case-insensitive.
+pub fn is_reserved_username(username: &str) -> bool {
+    [SESSION_USER, ANONYMOUS_USER].iter().any(|reserved| reserved.eq_ignore_ascii_case(username))
+}
 
 // Claims attached to JWT tokens
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Claims {
     pub sub: String,           // user email
     pub exp: usize,            // expiration timestamp
+    #[serde(default)]
+    pub iat: usize,            // issued-at timestamp
+    #[serde(default)]
+    pub iat_micros: i64,       // issued-at in microseconds, to order against a revoke-all
+    #[serde(default)]
+    pub jti: String,           // token id, used to revoke a single token
     pub is_dev: bool,          // development access flag
     pub features: Vec<String>, // feature flags (e.g., ["chat", "streaming", "premium"])
 }
 
 impl Claims {
+    /// Whether these claims came from a personal access token rather than a login
+    pub fn is_access_token(&self) -> bool {
+        self.jti.starts_with(ACCESS_TOKEN_JTI_PREFIX)
+    }
+
+    /// Claims for a fresh access token
+    pub fn new(sub: String, is_dev: bool, features: Vec<String>) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            sub,
+            exp: (now + chrono::Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).timestamp() as usize,
+            iat: now.timestamp() as usize,
+            iat_micros: now.timestamp_micros(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            is_dev,
+            features,
+        }
+    }
+
+    /// Issue time for revocation checks; tokens from before `iat_micros`
+    /// existed only have whole seconds
+    pub fn issued_at_micros(&self) -> i64 {
+        if self.iat_micros > 0 {
+            self.iat_micros
+        } else {
+            self.iat as i64 * 1_000_000
+        }
+    }
+
     /// Check if user has a specific feature enabled
     pub fn has_feature(&self, feature: &str) -> bool {
         self.is_dev || self.features.contains(&"*".to_string()) || self.features.contains(&feature.to_string())
     }
 }
 
+/// The user id data is stored under for a request that may carry claims
+pub fn user_id(claims: &Option<axum::Extension<Claims>>) -> String {
+    claims.as_ref()
+        .map(|axum::Extension(c)| c.sub.clone())
+        .unwrap_or_else(|| ANONYMOUS_USER.to_string())
+}
+
 // Error responses for auth failures
 #[derive(Debug)]
 pub enum AuthError {
     MissingToken,
     InvalidToken,
     ExpiredToken,
+    RevokedToken,
     InsufficientPermissions,
 }
 
@@ -42,6 +109,7 @@ impl IntoResponse for AuthError {
             AuthError::MissingToken => (StatusCode::UNAUTHORIZED, "Missing authorization token"),
             AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid authorization token"),
             AuthError::ExpiredToken => (StatusCode::UNAUTHORIZED, "Token has expired"),
+            AuthError::RevokedToken => (StatusCode::UNAUTHORIZED, "Token has been revoked"),
             AuthError::InsufficientPermissions => (StatusCode::FORBIDDEN, "Insufficient permissions"),
         };
         
@@ -81,21 +149,122 @@ pub fn decode_token(token: &str, secret: &[u8]) -> Result<Claims, AuthError> {
     })
 }
 
-/// Middleware that requires valid authentication
-pub async fn require_auth(
-    Extension(jwt_secret): Extension<Arc<Vec<u8>>>,
-    mut req: Request,
-    next: Next,
-) -> Result<Response, AuthError> {
-    // Extract token from Authorization header
-    let token = extract_token(req.headers())?;
-    
-    // Decode and validate token
-    let claims = decode_token(&token, &jwt_secret)?;
-    
+/// The caller's identity, from a bearer JWT, a bearer personal access token or
+/// an unlocked passphrase session. A bearer token wins when both are present,
+/// and an invalid one is rejected rather than falling back to the session.
+#[derive(Debug, Clone)]
+pub struct Identity(pub Claims);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Identity
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Already resolved by require_auth further out
+        if let Some(claims) = parts.extensions.get::<Claims>() {
+            return Ok(Identity(claims.clone()));
+        }
+
+        if parts.headers.contains_key(header::AUTHORIZATION) {
+            let jwt_secret = parts.extensions.get::<Arc<Vec<u8>>>().ok_or(AuthError::InvalidToken)?;
+            let pool = parts.extensions.get::<SqlitePool>().ok_or(AuthError::InvalidToken)?;
+            let token = extract_token(&parts.headers)?;
+            if token.starts_with(TOKEN_PREFIX) {
+                let client_ip = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+                return access_token_claims(pool, &token, client_ip).await.map(Identity);
+            }
+            let claims = decode_token(&token, jwt_secret)?;
+
+            // Reject tokens revoked by logout or by an admin; fail closed if the check can't run
+            let revoked = crate::db::tokens::is_access_revoked(pool, &claims.jti, &claims.sub, claims.issued_at_micros())
+                .await
+                .unwrap_or(true);
+            if revoked {
+                return Err(AuthError::RevokedToken);
+            }
+            return Ok(Identity(claims));
+        }
+
+        let session = parts.extensions.get::<Session>().ok_or(AuthError::MissingToken)?;
+        session_claims(session).await.map(Identity).ok_or(AuthError::MissingToken)
+    }
+}
+
+/// Claims for a personal access token. The token only carries the features it
+/// was created with that its owner still has, and never dev access.
+async fn access_token_claims(pool: &SqlitePool, plaintext: &str, client_ip: Option<IpAddr>) -> Result<Claims, AuthError> {
+    let (token, user) = access_tokens::find(pool, plaintext)
+        .await
+        .map_err(|_| AuthError::InvalidToken)?
+        .ok_or(AuthError::InvalidToken)?;
+    if token.revoked_at.is_some() || user.is_disabled() || is_reserved_username(&user.username) {
+        return Err(AuthError::RevokedToken);
+    }
+    if token.expires_at.is_some_and(|expires_at| expires_at <= chrono::Utc::now().timestamp()) {
+        return Err(AuthError::ExpiredToken);
+    }
+    if let Some(ranges) = &token.allowed_ips {
+        let allowed = client_ip.is_some_and(|ip| {
+            ranges.0.iter()
+                .filter_map(|range| range.parse::<IpRange>().ok())
+                .any(|range| range.contains(ip))
+        });
+        if !allowed {
+            return Err(AuthError::InsufficientPermissions);
+        }
+    }
+
+    let owner = Claims { is_dev: user.is_dev, features: user.features.0.clone(), ..Claims::default() };
+    let features = token.features.0.iter()
+        .filter(|feature| owner.has_feature(feature))
+        .cloned()
+        .collect();
+
+    let client_ip = client_ip.map(|ip| ip.to_string());
+    if let Err(e) = access_tokens::record_use(pool, &token.id, client_ip.as_deref()).await {
+        tracing::warn!("Failed to record use of access token {}: {}", token.id, e);
+    }
+
+    Ok(Claims {
+        sub: user.username,
+        exp: token.expires_at.map_or(usize::MAX, |expires_at| expires_at as usize),
+        iat: token.created_at as usize,
+        jti: format!("{}{}", ACCESS_TOKEN_JTI_PREFIX, token.id),
+        is_dev: false,
+        features,
+        ..Claims::default()
+    })
+}
+
+/// Claims for a session that has been unlocked with the passphrase
+pub async fn session_claims(session: &Session) -> Option<Claims> {
+    let unlocked = session.get::<bool>("authenticated").await.ok().flatten().unwrap_or(false);
+    if !unlocked {
+        return None;
+    }
+    Some(Claims {
+        sub: SESSION_USER.to_string(),
+        exp: session.expiry_date().unix_timestamp() as usize,
+        iat: chrono::Utc::now().timestamp() as usize,
+        jti: session.id().map(|id| format!("session:{}", id)).unwrap_or_default(),
+        is_dev: false,
+        features: vec!["*".to_string()],
+        ..Claims::default()
+    })
+}
+
+/// Middleware that requires an identity and attaches its claims to the request
+pub async fn require_auth(req: Request, next: Next) -> Result<Response, AuthError> {
+    let (mut parts, body) = req.into_parts();
+    let Identity(claims) = Identity::from_request_parts(&mut parts, &()).await?;
+
     // Attach claims to request extensions for downstream handlers
+    let mut req = Request::from_parts(parts, body);
     req.extensions_mut().insert(claims);
-    
+
     Ok(next.run(req).await)
 }
 
@@ -106,4 +275,54 @@ pub fn generate_token(claims: &Claims, secret: &[u8]) -> Result<String, jsonwebt
         claims,
         &EncodingKey::from_secret(secret),
     )
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::sessions::SqliteSessionStore;
+
+    async fn identify(
+        bearer: Option<&str>,
+        session: Option<Session>,
+        secret: &Arc<Vec<u8>>,
+        pool: &SqlitePool,
+    ) -> Result<Claims, AuthError> {
+        let mut request = axum::http::Request::builder().uri("/echo");
+        if let Some(token) = bearer {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        parts.extensions.insert(secret.clone());
+        parts.extensions.insert(pool.clone());
+        if let Some(session) = session {
+            parts.extensions.insert(session);
+        }
+        Identity::from_request_parts(&mut parts, &()).await.map(|Identity(claims)| claims)
+    }
+
+    #[tokio::test]
+    async fn test_identity_accepts_jwt_or_unlocked_session() {
+        let pool = crate::db::test_pool().await;
+        let secret = Arc::new(b"test-secret".to_vec());
+        let store = Arc::new(SqliteSessionStore::new(pool.clone()));
+
+        let token = generate_token(&Claims::new("alice".to_string(), false, vec!["chat".to_string()]), &secret).unwrap();
+        let claims = identify(Some(&token), None, &secret, &pool).await.unwrap();
+        assert_eq!(claims.sub, "alice");
+        assert!(!claims.has_feature("admin"));
+
+        let session = Session::new(None, store.clone(), None);
+        assert!(matches!(identify(None, Some(session.clone()), &secret, &pool).await, Err(AuthError::MissingToken)));
+
+        session.insert("authenticated", true).await.unwrap();
+        let claims = identify(None, Some(session.clone()), &secret, &pool).await.unwrap();
+        assert_eq!(claims.sub, SESSION_USER);
+        assert!(claims.has_feature("admin"));
+        assert!(is_reserved_username("Owner") && is_reserved_username("ANONYMOUS"));
+        assert!(!is_reserved_username("owner2"));
+
+        // A bad bearer token is rejected even alongside an unlocked session
+        let result = identify(Some("garbage"), Some(session), &secret, &pool).await;
+        assert!(matches!(result, Err(AuthError::InvalidToken)));
+    }
+}
@@ -0,0 +1,123 @@
+// src/db/access_tokens.rs
+use serde::Serialize;
+use sqlx::types::Json;
+use sqlx::SqlitePool;
+
+use crate::db::tokens::{hash_token, new_token};
+use crate::db::users::User;
+
+/// Marks a bearer token as a personal access token rather than a JWT
+pub const TOKEN_PREFIX: &str = "echo_pat_";
+const DISPLAY_PREFIX_CHARS: usize = 12;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AccessToken {
+    pub id: String,
+    #[serde(skip_serializing)]
+    pub user_id: String,
+    pub name: String,
+    pub prefix: String,
+    pub features: Json<Vec<String>>,
+    pub allowed_ips: Option<Json<Vec<String>>>,
+    pub expires_at: Option<i64>,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub last_used_ip: Option<String>,
+    pub revoked_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NewAccessToken {
+    pub name: String,
+    pub features: Vec<String>,
+    pub allowed_ips: Option<Vec<String>>,
+    pub expires_at: Option<i64>,
+}
+
+/// Stores a new token and returns it along with its plaintext, which is
+/// never available again
+pub async fn create(pool: &SqlitePool, user_id: &str, new: NewAccessToken) -> Result<(AccessToken, String), sqlx::Error> {
+    let plaintext = format!("{}{}", TOKEN_PREFIX, new_token());
+    let token = AccessToken {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        name: new.name,
+        prefix: plaintext.chars().take(TOKEN_PREFIX.len() + DISPLAY_PREFIX_CHARS).collect(),
+        features: Json(new.features),
+        allowed_ips: new.allowed_ips.map(Json),
+        expires_at: new.expires_at,
+        created_at: chrono::Utc::now().timestamp(),
+        last_used_at: None,
+        last_used_ip: None,
+        revoked_at: None,
+    };
+
+    sqlx::query(
+        "INSERT INTO access_tokens (id, user_id, name, token_hash, prefix, features, allowed_ips, expires_at, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&token.id)
+    .bind(&token.user_id)
+    .bind(&token.name)
+    .bind(hash_token(&plaintext))
+    .bind(&token.prefix)
+    .bind(&token.features)
+    .bind(&token.allowed_ips)
+    .bind(token.expires_at)
+    .bind(token.created_at)
+    .execute(pool)
+    .await?;
+
+    Ok((token, plaintext))
+}
+
+pub async fn list_for_user(pool: &SqlitePool, user_id: &str) -> Result<Vec<AccessToken>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM access_tokens WHERE user_id = ? ORDER BY created_at DESC")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Returns false if the user has no such live token
+pub async fn revoke(pool: &SqlitePool, user_id: &str, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE access_tokens SET revoked_at = ? WHERE id = ? AND user_id = ? AND revoked_at IS NULL")
+        .bind(chrono::Utc::now().timestamp())
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revokes every live token the user has; returns how many there were
+pub async fn revoke_all_for_user(pool: &SqlitePool, user_id: &str) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("UPDATE access_tokens SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL")
+        .bind(chrono::Utc::now().timestamp())
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// The token matching a presented plaintext, with its owner
+pub async fn find(pool: &SqlitePool, plaintext: &str) -> Result<Option<(AccessToken, User)>, sqlx::Error> {
+    let token: Option<AccessToken> = sqlx::query_as("SELECT * FROM access_tokens WHERE token_hash = ?")
+        .bind(hash_token(plaintext))
+        .fetch_optional(pool)
+        .await?;
+    let Some(token) = token else {
+        return Ok(None);
+    };
+    let user = crate::db::users::get(pool, &token.user_id).await?;
+    Ok(user.map(|user| (token, user)))
+}
+
+pub async fn record_use(pool: &SqlitePool, id: &str, ip: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE access_tokens SET last_used_at = ?, last_used_ip = ? WHERE id = ?")
+        .bind(chrono::Utc::now().timestamp())
+        .bind(ip)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
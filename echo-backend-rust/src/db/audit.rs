@@ -0,0 +1,96 @@
+// src/db/audit.rs
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+
+/// One provider call as it went over the wire, after redaction
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub user_id: String,
+    pub provider: String,
+    pub model: String,
+    pub request: Json<Value>, // {"messages": [...], "params": {...}, "images": n}
+    pub payload: Json<Value>,
+    pub response: Option<Json<Value>>,
+    pub error: Option<String>,
+    pub latency_ms: i64,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub redacted: bool,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewAuditEntry {
+    pub user_id: String,
+    pub provider: String,
+    pub model: String,
+    pub request: Value,
+    pub payload: Value,
+    pub response: Option<Value>,
+    pub error: Option<String>,
+    pub latency_ms: i64,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub redacted: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilters {
+    pub user_id: Option<String>,
+    pub model: Option<String>,
+    pub before_id: Option<i64>, // page backwards from here
+    pub errors_only: bool,
+    pub limit: i64,
+}
+
+pub async fn record(pool: &SqlitePool, entry: &NewAuditEntry) -> Result<i64, sqlx::Error> {
+    Ok(sqlx::query(
+        "INSERT INTO llm_audit (user_id, provider, model, request, payload, response, error, latency_ms, input_tokens, output_tokens, redacted, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&entry.user_id)
+    .bind(&entry.provider)
+    .bind(&entry.model)
+    .bind(Json(&entry.request))
+    .bind(Json(&entry.payload))
+    .bind(entry.response.as_ref().map(Json))
+    .bind(&entry.error)
+    .bind(entry.latency_ms)
+    .bind(entry.input_tokens)
+    .bind(entry.output_tokens)
+    .bind(entry.redacted)
+    .bind(chrono::Utc::now().timestamp())
+    .execute(pool)
+    .await?
+    .last_insert_rowid())
+}
+
+pub async fn get(pool: &SqlitePool, id: i64) -> Result<Option<AuditEntry>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM llm_audit WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Newest first
+pub async fn list(pool: &SqlitePool, filters: &AuditFilters) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM llm_audit WHERE 1 = 1");
+    if let Some(user_id) = &filters.user_id {
+        builder.push(" AND user_id = ").push_bind(user_id);
+    }
+    if let Some(model) = &filters.model {
+        builder.push(" AND model = ").push_bind(model);
+    }
+    if let Some(before_id) = filters.before_id {
+        builder.push(" AND id < ").push_bind(before_id);
+    }
+    if filters.errors_only {
+        builder.push(" AND error IS NOT NULL");
+    }
+    builder.push(" ORDER BY id DESC LIMIT ").push_bind(filters.limit);
+
+    builder.build_query_as().fetch_all(pool).await
+}
@@ -0,0 +1,199 @@
+// src/db/batches.rs
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::SqlitePool;
+
+use crate::models::llm::{ChatMessage, GenerationParams};
+
+/// One line of an uploaded batch file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequest {
+    pub custom_id: Option<String>,
+    pub model: Option<String>, // defaults to the active model when the batch runs
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub params: GenerationParams,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Batch {
+    pub id: String,
+    pub user_id: String,
+    pub status: String, // "queued", "running", "completed", "failed" or "cancelled"
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchProgress {
+    pub total: i64,
+    pub pending: i64,
+    pub succeeded: i64,
+    pub failed: i64,
+    pub cancelled: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BatchItem {
+    pub id: i64,
+    pub line: i64,
+    pub custom_id: Option<String>,
+    pub request: Json<BatchRequest>,
+    pub status: String, // "pending", "succeeded", "failed" or "cancelled"
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub completed_at: Option<i64>,
+}
+
+pub async fn create(pool: &SqlitePool, user_id: &str, requests: &[(i64, BatchRequest)]) -> Result<Batch, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let batch = Batch {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        status: "queued".to_string(),
+        created_at: now,
+        updated_at: now,
+    };
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("INSERT INTO batches (id, user_id, status, created_at, updated_at) VALUES (?, ?, ?, ?, ?)")
+        .bind(&batch.id)
+        .bind(&batch.user_id)
+        .bind(&batch.status)
+        .bind(now)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    for (line, request) in requests {
+        sqlx::query("INSERT INTO batch_items (batch_id, line, custom_id, request, status) VALUES (?, ?, ?, ?, 'pending')")
+            .bind(&batch.id)
+            .bind(line)
+            .bind(&request.custom_id)
+            .bind(Json(request))
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(batch)
+}
+
+pub async fn get(pool: &SqlitePool, id: &str, user_id: &str) -> Result<Option<Batch>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM batches WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn list(pool: &SqlitePool, user_id: &str) -> Result<Vec<Batch>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM batches WHERE user_id = ? ORDER BY created_at DESC")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Batches a restart interrupted, oldest first
+pub async fn unfinished(pool: &SqlitePool) -> Result<Vec<Batch>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM batches WHERE status IN ('queued', 'running') ORDER BY created_at")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn status(pool: &SqlitePool, id: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT status FROM batches WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Move a batch on from queued or running; finished batches stay as they are
+pub async fn set_status(pool: &SqlitePool, id: &str, status: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE batches SET status = ?, updated_at = ? WHERE id = ? AND status IN ('queued', 'running')")
+        .bind(status)
+        .bind(chrono::Utc::now().timestamp())
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Stop a queued or running batch; returns false if it had already finished
+pub async fn cancel(pool: &SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let cancelled = sqlx::query(
+        "UPDATE batches SET status = 'cancelled', updated_at = ? WHERE id = ? AND status IN ('queued', 'running')",
+    )
+    .bind(chrono::Utc::now().timestamp())
+    .bind(id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected() > 0;
+
+    if cancelled {
+        sqlx::query("UPDATE batch_items SET status = 'cancelled' WHERE batch_id = ? AND status = 'pending'")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(cancelled)
+}
+
+pub async fn progress(pool: &SqlitePool, id: &str) -> Result<BatchProgress, sqlx::Error> {
+    let counts: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT status, COUNT(*) FROM batch_items WHERE batch_id = ? GROUP BY status",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut progress = BatchProgress::default();
+    for (status, count) in counts {
+        progress.total += count;
+        match status.as_str() {
+            "pending" => progress.pending = count,
+            "succeeded" => progress.succeeded = count,
+            "failed" => progress.failed = count,
+            "cancelled" => progress.cancelled = count,
+            _ => {},
+        }
+    }
+    Ok(progress)
+}
+
+/// The next pending items after `after_id`, in upload order
+pub async fn pending_items(pool: &SqlitePool, id: &str, after_id: i64, limit: i64) -> Result<Vec<BatchItem>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT * FROM batch_items WHERE batch_id = ? AND status = 'pending' AND id > ? ORDER BY id LIMIT ?",
+    )
+    .bind(id)
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn items(pool: &SqlitePool, id: &str) -> Result<Vec<BatchItem>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM batch_items WHERE batch_id = ? ORDER BY line")
+        .bind(id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Record a provider call's outcome; answers that land after a cancel are still kept
+pub async fn finish_item(pool: &SqlitePool, item_id: i64, result: Result<&str, &str>) -> Result<(), sqlx::Error> {
+    let (status, response, error) = match result {
+        Ok(response) => ("succeeded", Some(response), None),
+        Err(error) => ("failed", None, Some(error)),
+    };
+    sqlx::query("UPDATE batch_items SET status = ?, response = ?, error = ?, completed_at = ? WHERE id = ?")
+        .bind(status)
+        .bind(response)
+        .bind(error)
+        .bind(chrono::Utc::now().timestamp())
+        .bind(item_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,336 @@
+// src/db/conversations.rs
+use serde::Serialize;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use std::collections::{HashMap, HashSet};
+
+use crate::models::chat_import::ImportedConversation;
+use crate::models::llm::{ChatMessage, ChatRole};
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Conversation {
+    pub id: String,
+    pub user_id: String,
+    pub title: Option<String>,
+    pub model: String,
+    pub mode: String, // "chat", or the multi-model mode that produced it
+    pub persona_id: Option<String>,
+    pub source: Option<String>,    // "chatgpt" or "claude" for imported history
+    pub source_id: Option<String>, // the conversation's id in that export
+    pub summary: Option<String>,
+    pub summary_through: Option<i64>, // id of the last message folded into the summary
+    pub active_leaf: Option<i64>,     // end of the branch replayed to the provider
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct StoredMessage {
+    pub id: i64,
+    pub conversation_id: String,
+    pub parent_id: Option<i64>,
+    pub role: String,
+    pub content: String,
+    pub model: Option<String>,
+    pub created_at: i64,
+}
+
+impl StoredMessage {
+    pub fn to_chat(&self) -> ChatMessage {
+        ChatMessage {
+            role: ChatRole::parse(&self.role).unwrap_or(ChatRole::User),
+            content: self.content.clone(),
+        }
+    }
+}
+
+pub async fn create(
+    pool: &SqlitePool,
+    user_id: &str,
+    model: &str,
+    mode: &str,
+    title: Option<&str>,
+) -> Result<Conversation, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let conversation = Conversation {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        title: title.map(str::to_string),
+        model: model.to_string(),
+        mode: mode.to_string(),
+        persona_id: None,
+        source: None,
+        source_id: None,
+        summary: None,
+        summary_through: None,
+        active_leaf: None,
+        created_at: now,
+        updated_at: now,
+    };
+
+    sqlx::query(
+        "INSERT INTO conversations (id, user_id, title, model, mode, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&conversation.id)
+    .bind(&conversation.user_id)
+    .bind(&conversation.title)
+    .bind(&conversation.model)
+    .bind(&conversation.mode)
+    .bind(now)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(conversation)
+}
+
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Conversation>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM conversations WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn find_by_source(
+    pool: &SqlitePool,
+    user_id: &str,
+    source: &str,
+    source_id: &str,
+) -> Result<Option<Conversation>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM conversations WHERE user_id = ? AND source = ? AND source_id = ?")
+        .bind(user_id)
+        .bind(source)
+        .bind(source_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Store a conversation from another service as a single branch, keeping its timestamps
+pub async fn import(
+    pool: &SqlitePool,
+    user_id: &str,
+    source: &str,
+    imported: &ImportedConversation,
+) -> Result<Conversation, sqlx::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let model = imported.messages.iter()
+        .find_map(|m| m.model.clone())
+        .unwrap_or_else(|| source.to_string());
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO conversations (id, user_id, title, model, mode, source, source_id, created_at, updated_at)
+         VALUES (?, ?, ?, ?, 'chat', ?, ?, ?, ?)",
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(&imported.title)
+    .bind(&model)
+    .bind(source)
+    .bind(&imported.source_id)
+    .bind(imported.created_at)
+    .bind(imported.updated_at)
+    .execute(&mut *tx)
+    .await?;
+
+    let mut parent_id = None;
+    for message in &imported.messages {
+        let message_id = sqlx::query(
+            "INSERT INTO messages (conversation_id, parent_id, role, content, model, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(parent_id)
+        .bind(message.role.as_str())
+        .bind(&message.content)
+        .bind(&message.model)
+        .bind(message.created_at)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        parent_id = Some(message_id);
+    }
+
+    sqlx::query("UPDATE conversations SET active_leaf = ? WHERE id = ?")
+        .bind(parent_id)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    get(pool, &id).await?.ok_or(sqlx::Error::RowNotFound)
+}
+
+/// Every message in the conversation across all branches, oldest first
+pub async fn messages(pool: &SqlitePool, conversation_id: &str) -> Result<Vec<StoredMessage>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM messages WHERE conversation_id = ? ORDER BY id")
+        .bind(conversation_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Store a reply to `parent_id` and make it the active leaf
+pub async fn add_message(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    parent_id: Option<i64>,
+    role: ChatRole,
+    content: &str,
+    model: Option<&str>,
+) -> Result<StoredMessage, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let id = sqlx::query(
+        "INSERT INTO messages (conversation_id, parent_id, role, content, model, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(conversation_id)
+    .bind(parent_id)
+    .bind(role.as_str())
+    .bind(content)
+    .bind(model)
+    .bind(now)
+    .execute(pool)
+    .await?
+    .last_insert_rowid();
+
+    sqlx::query("UPDATE conversations SET active_leaf = ?, updated_at = ? WHERE id = ?")
+        .bind(id)
+        .bind(now)
+        .bind(conversation_id)
+        .execute(pool)
+        .await?;
+
+    Ok(StoredMessage {
+        id,
+        conversation_id: conversation_id.to_string(),
+        parent_id,
+        role: role.as_str().to_string(),
+        content: content.to_string(),
+        model: model.map(str::to_string),
+        created_at: now,
+    })
+}
+
+/// Replace the rolling summary; `through` is the last message it covers
+pub async fn set_summary(
+    pool: &SqlitePool,
+    id: &str,
+    summary: Option<&str>,
+    through: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE conversations SET summary = ?, summary_through = ? WHERE id = ?")
+        .bind(summary)
+        .bind(through)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    pub model: Option<String>,
+    pub mode: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: i64,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SearchHit {
+    pub conversation_id: String,
+    pub conversation_title: Option<String>,
+    pub mode: String,
+    pub message_id: i64,
+    pub role: String,
+    pub model: Option<String>,
+    pub created_at: i64,
+    pub snippet: String,
+}
+
+/// Full-text search over one user's messages, best matches first
+pub async fn search(
+    pool: &SqlitePool,
+    user_id: &str,
+    query: &str,
+    filters: &SearchFilters,
+) -> Result<Vec<SearchHit>, sqlx::Error> {
+    let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT c.id AS conversation_id, c.title AS conversation_title, c.mode, \
+         m.id AS message_id, m.role, m.model, m.created_at, \
+         snippet(messages_fts, 0, '<mark>', '</mark>', '…', 16) AS snippet \
+         FROM messages_fts \
+         JOIN messages m ON m.id = messages_fts.rowid \
+         JOIN conversations c ON c.id = m.conversation_id \
+         WHERE messages_fts MATCH ",
+    );
+    builder.push_bind(fts_query(query));
+    builder.push(" AND c.user_id = ").push_bind(user_id);
+    if let Some(model) = &filters.model {
+        builder.push(" AND m.model = ").push_bind(model);
+    }
+    if let Some(mode) = &filters.mode {
+        builder.push(" AND c.mode = ").push_bind(mode);
+    }
+    if let Some(since) = filters.since {
+        builder.push(" AND m.created_at >= ").push_bind(since);
+    }
+    if let Some(until) = filters.until {
+        builder.push(" AND m.created_at < ").push_bind(until);
+    }
+    builder.push(" ORDER BY rank LIMIT ").push_bind(filters.limit);
+
+    builder.build_query_as().fetch_all(pool).await
+}
+
+// Quote each word so user input can't be parsed as FTS5 query syntax
+fn fts_query(query: &str) -> String {
+    query.split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub async fn set_persona(pool: &SqlitePool, id: &str, persona_id: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE conversations SET persona_id = ? WHERE id = ?")
+        .bind(persona_id)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_active_leaf(pool: &SqlitePool, id: &str, leaf: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE conversations SET active_leaf = ? WHERE id = ?")
+        .bind(leaf)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The chain of messages from the root down to `leaf`
+pub fn path_to(messages: &[StoredMessage], leaf: Option<i64>) -> Vec<StoredMessage> {
+    let by_id: HashMap<i64, &StoredMessage> = messages.iter().map(|m| (m.id, m)).collect();
+    let mut path = Vec::new();
+    let mut current = leaf.and_then(|id| by_id.get(&id));
+    while let Some(message) = current {
+        path.push((*message).clone());
+        current = message.parent_id.and_then(|id| by_id.get(&id));
+    }
+    path.reverse();
+    path
+}
+
+/// Messages with no replies; each one ends a branch
+pub fn leaves(messages: &[StoredMessage]) -> Vec<&StoredMessage> {
+    let parents: HashSet<i64> = messages.iter().filter_map(|m| m.parent_id).collect();
+    messages.iter().filter(|m| !parents.contains(&m.id)).collect()
+}
+
+/// Follow the newest reply at each step until reaching a leaf
+pub fn latest_leaf_under(messages: &[StoredMessage], id: i64) -> i64 {
+    let mut current = id;
+    while let Some(child) = messages.iter().filter(|m| m.parent_id == Some(current)).map(|m| m.id).max() {
+        current = child;
+    }
+    current
+}
@@ -0,0 +1,101 @@
+// src/db/login_failures.rs
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct LoginFailure {
+    pub key: String,
+    pub failures: i64,
+    pub first_failure_at: i64,
+    pub last_failure_at: i64,
+    pub locked_until: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AuthEvent {
+    pub id: i64,
+    pub kind: String, // "lockout" or "lockout_cleared"
+    pub subject: String,
+    pub ip: Option<String>,
+    pub detail: Option<String>,
+    pub created_at: i64,
+}
+
+pub async fn get(pool: &SqlitePool, key: &str) -> Result<Option<LoginFailure>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM login_failures WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Counts one more failure in a single statement, so concurrent failures are
+/// never lost. A key whose last failure is older than `window_secs` starts over.
+pub async fn increment(pool: &SqlitePool, key: &str, now: i64, window_secs: i64) -> Result<LoginFailure, sqlx::Error> {
+    sqlx::query_as(
+        "INSERT INTO login_failures (key, failures, first_failure_at, last_failure_at, locked_until) VALUES (?1, 1, ?2, ?2, NULL)
+         ON CONFLICT(key) DO UPDATE SET
+             failures = CASE WHEN ?2 - last_failure_at > ?3 THEN 1 ELSE failures + 1 END,
+             first_failure_at = CASE WHEN ?2 - last_failure_at > ?3 THEN ?2 ELSE first_failure_at END,
+             locked_until = CASE WHEN ?2 - last_failure_at > ?3 THEN NULL ELSE locked_until END,
+             last_failure_at = ?2
+         RETURNING *",
+    )
+    .bind(key)
+    .bind(now)
+    .bind(window_secs)
+    .fetch_one(pool)
+    .await
+}
+
+/// Locks the key until `until` unless it is locked already. Returns false if
+/// it was, so only one of several racing failures starts the lockout.
+pub async fn lock(pool: &SqlitePool, key: &str, now: i64, until: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE login_failures SET locked_until = ? WHERE key = ? AND (locked_until IS NULL OR locked_until <= ?)")
+        .bind(until)
+        .bind(key)
+        .bind(now)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns false if there was nothing recorded for the key
+pub async fn clear(pool: &SqlitePool, key: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM login_failures WHERE key = ?")
+        .bind(key)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn active_lockouts(pool: &SqlitePool, now: i64) -> Result<Vec<LoginFailure>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM login_failures WHERE locked_until > ? ORDER BY locked_until DESC")
+        .bind(now)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn record_event(
+    pool: &SqlitePool,
+    kind: &str,
+    subject: &str,
+    ip: Option<&str>,
+    detail: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO auth_events (kind, subject, ip, detail, created_at) VALUES (?, ?, ?, ?, ?)")
+        .bind(kind)
+        .bind(subject)
+        .bind(ip)
+        .bind(detail)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn recent_events(pool: &SqlitePool, limit: i64) -> Result<Vec<AuthEvent>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM auth_events ORDER BY id DESC LIMIT ?")
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
@@ -0,0 +1,46 @@
+// src/db/mod.rs
+pub mod conversations;
+pub mod personas;
+pub mod votes;
+pub mod batches;
+pub mod audit;
+pub mod users;
+pub mod tokens;
+pub mod sessions;
+pub mod access_tokens;
+pub mod login_failures;
+pub mod passphrase;
+pub mod secrets;
+
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+
+// A single connection that never expires, so every query sees the same in-memory database
+#[cfg(test)]
+pub async fn test_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .expect("Failed to open in-memory database");
+
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("Failed to run migrations");
+
+    pool
+}
+
+/// Open the application database and bring its schema up to date
+pub async fn connect(url: &str) -> Result<SqlitePool, sqlx::Error> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect(url)
+        .await?;
+
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    Ok(pool)
+}
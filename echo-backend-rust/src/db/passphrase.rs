@@ -0,0 +1,70 @@
+// src/db/passphrase.rs
+use sqlx::{Sqlite, SqlitePool};
+
+use crate::db::users::{hash_password, verify_hash};
+
+/// The stored vault passphrase hash, if one has been set
+pub async fn get(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT hash FROM auth WHERE id = 1")
+        .fetch_optional(pool)
+        .await
+}
+
+/// Hashes with argon2 and stores, replacing any earlier passphrase
+pub async fn set<'e>(executor: impl sqlx::Executor<'e, Database = Sqlite>, passphrase: &str) -> Result<(), sqlx::Error> {
+    let hash = hash_password(passphrase)
+        .map_err(|e| sqlx::Error::Protocol(format!("Failed to hash passphrase: {}", e)))?;
+    sqlx::query("INSERT INTO auth (id, hash) VALUES (1, ?) ON CONFLICT(id) DO UPDATE SET hash = excluded.hash")
+        .bind(hash)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Stores the first passphrase; returns false, leaving it alone, if one is
+/// already set
+pub async fn set_initial(pool: &SqlitePool, passphrase: &str) -> Result<bool, sqlx::Error> {
+    let hash = hash_password(passphrase)
+        .map_err(|e| sqlx::Error::Protocol(format!("Failed to hash passphrase: {}", e)))?;
+    let result = sqlx::query("INSERT INTO auth (id, hash) VALUES (1, ?) ON CONFLICT(id) DO NOTHING")
+        .bind(hash)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Passphrases set before the switch to argon2 are bcrypt hashes
+pub fn is_bcrypt(hash: &str) -> bool {
+    hash.starts_with("$2")
+}
+
+pub fn verify(hash: &str, passphrase: &str) -> bool {
+    if is_bcrypt(hash) {
+        bcrypt::verify(passphrase, hash).unwrap_or(false)
+    } else {
+        verify_hash(hash, passphrase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_passphrases_verify_with_either_hash() {
+        let pool = crate::db::test_pool().await;
+        assert_eq!(get(&pool).await.unwrap(), None);
+
+        let legacy = bcrypt::hash("open sesame", 4).unwrap();
+        assert!(is_bcrypt(&legacy));
+        assert!(verify(&legacy, "open sesame"));
+        assert!(!verify(&legacy, "open barley"));
+
+        set(&pool, "open sesame").await.unwrap();
+        set(&pool, "open barley").await.unwrap();
+        let stored = get(&pool).await.unwrap().unwrap();
+        assert!(!is_bcrypt(&stored));
+        assert!(verify(&stored, "open barley"));
+        assert!(!verify(&stored, "open sesame"));
+    }
+}
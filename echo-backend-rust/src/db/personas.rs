@@ -0,0 +1,128 @@
+// src/db/personas.rs
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use crate::models::tts::VoiceSettings;
+
+/// A server-side assistant identity: which model answers, how it is briefed,
+/// how it sounds and which parts of the vault it may draw on
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Persona {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub model: String, // A model name, alias or profile
+    pub system_prompt: Option<String>,
+    pub voice: Json<VoiceSettings>,
+    pub vault_folders: Json<Vec<String>>, // Vault-relative; empty means the whole vault
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// The editable fields of a persona
+#[derive(Debug, Clone, Deserialize)]
+pub struct PersonaFields {
+    pub name: String,
+    pub model: String,
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub voice: VoiceSettings,
+    #[serde(default)]
+    pub vault_folders: Vec<String>,
+}
+
+impl Persona {
+    /// Whether a vault-relative path falls inside one of the persona's folders
+    pub fn allows_vault_path(&self, relative: &Path) -> bool {
+        self.vault_folders.is_empty()
+            || self.vault_folders.iter().any(|folder| relative.starts_with(folder))
+    }
+}
+
+pub async fn create(pool: &SqlitePool, user_id: &str, fields: &PersonaFields) -> Result<Persona, sqlx::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp();
+
+    sqlx::query(
+        "INSERT INTO personas (id, user_id, name, model, system_prompt, voice, vault_folders, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(&fields.name)
+    .bind(&fields.model)
+    .bind(&fields.system_prompt)
+    .bind(Json(&fields.voice))
+    .bind(Json(&fields.vault_folders))
+    .bind(now)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(Persona {
+        id,
+        user_id: user_id.to_string(),
+        name: fields.name.clone(),
+        model: fields.model.clone(),
+        system_prompt: fields.system_prompt.clone(),
+        voice: Json(fields.voice.clone()),
+        vault_folders: Json(fields.vault_folders.clone()),
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+/// Personas are private to their owner; anyone else gets None
+pub async fn get(pool: &SqlitePool, id: &str, user_id: &str) -> Result<Option<Persona>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM personas WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn list(pool: &SqlitePool, user_id: &str) -> Result<Vec<Persona>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM personas WHERE user_id = ? ORDER BY name")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Returns None if the persona doesn't exist for this user
+pub async fn update(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: &str,
+    fields: &PersonaFields,
+) -> Result<Option<Persona>, sqlx::Error> {
+    let updated = sqlx::query(
+        "UPDATE personas SET name = ?, model = ?, system_prompt = ?, voice = ?, vault_folders = ?, updated_at = ?
+         WHERE id = ? AND user_id = ?",
+    )
+    .bind(&fields.name)
+    .bind(&fields.model)
+    .bind(&fields.system_prompt)
+    .bind(Json(&fields.voice))
+    .bind(Json(&fields.vault_folders))
+    .bind(chrono::Utc::now().timestamp())
+    .bind(id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    if updated.rows_affected() == 0 {
+        return Ok(None);
+    }
+    get(pool, id, user_id).await
+}
+
+pub async fn delete(pool: &SqlitePool, id: &str, user_id: &str) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query("DELETE FROM personas WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(deleted.rows_affected() > 0)
+}
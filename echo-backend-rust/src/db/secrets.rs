@@ -0,0 +1,293 @@
+// src/db/secrets.rs
+use argon2::Argon2;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use serde::Serialize;
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+
+const SALT_LEN: usize = 16;
+const MAX_NAME_LEN: usize = 64;
+// Sealed under the key so unlocking can tell a wrong key from a corrupt secret
+const CHECK_VALUE: &[u8] = b"echo-secrets";
+const CHECK_AAD: &str = "secrets_key";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecretError {
+    #[error("the secrets key does not match the vault passphrase")]
+    WrongKey,
+    #[error("failed to derive the secrets key: {0}")]
+    KeyDerivation(String),
+    #[error("secret {0} could not be decrypted")]
+    Corrupt(String),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// The key secrets are sealed with, derived from the vault passphrase.
+/// Only ever held in memory.
+#[derive(Clone)]
+pub struct SecretKey([u8; 32]);
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+/// What can be said about a secret without revealing it
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SecretInfo {
+    pub name: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct SealedSecret {
+    name: String,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+#[derive(sqlx::FromRow)]
+struct KeyParams {
+    salt: Vec<u8>,
+    check_nonce: Vec<u8>,
+    check_ciphertext: Vec<u8>,
+}
+
+/// Names are referenced from config.json as `secret:<name>`
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<SecretKey, SecretError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| SecretError::KeyDerivation(e.to_string()))?;
+    Ok(SecretKey(key))
+}
+
+fn aead_key(key: &SecretKey) -> LessSafeKey {
+    LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &key.0).expect("ChaCha20-Poly1305 takes a 32-byte key"))
+}
+
+// A fresh random nonce per seal; the secret's name is bound in as associated
+// data so ciphertexts can't be swapped between names
+fn seal(key: &SecretKey, name: &str, plaintext: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let mut in_out = plaintext.to_vec();
+    aead_key(key)
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(name.as_bytes()), &mut in_out)
+        .expect("sealing only fails for oversized input");
+    (nonce.to_vec(), in_out)
+}
+
+fn open(key: &SecretKey, name: &str, nonce: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+    let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = aead_key(key)
+        .open_in_place(nonce, Aad::from(name.as_bytes()), &mut in_out)
+        .ok()?;
+    Some(plaintext.to_vec())
+}
+
+async fn key_params(conn: &mut SqliteConnection) -> Result<Option<KeyParams>, sqlx::Error> {
+    sqlx::query_as("SELECT salt, check_nonce, check_ciphertext FROM secrets_key WHERE id = 1")
+        .fetch_optional(conn)
+        .await
+}
+
+// Derives a key under a new salt, with the check value sealed under it
+fn fresh_key(passphrase: &str) -> Result<(SecretKey, KeyParams), SecretError> {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let key = derive_key(passphrase, &salt)?;
+    let (check_nonce, check_ciphertext) = seal(&key, CHECK_AAD, CHECK_VALUE);
+    Ok((key, KeyParams { salt: salt.to_vec(), check_nonce, check_ciphertext }))
+}
+
+// Derives a key under a new salt and records it as the store's key
+async fn new_key(conn: &mut SqliteConnection, passphrase: &str) -> Result<SecretKey, SecretError> {
+    let (key, params) = fresh_key(passphrase)?;
+    sqlx::query(
+        "INSERT INTO secrets_key (id, salt, check_nonce, check_ciphertext) VALUES (1, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET salt = excluded.salt, check_nonce = excluded.check_nonce,
+             check_ciphertext = excluded.check_ciphertext",
+    )
+    .bind(params.salt)
+    .bind(params.check_nonce)
+    .bind(params.check_ciphertext)
+    .execute(conn)
+    .await?;
+    Ok(key)
+}
+
+// Records a key only if the store has none, so of two first unlocks racing
+// each other the second keeps the first one's key
+async fn create_key_if_missing(conn: &mut SqliteConnection, passphrase: &str) -> Result<(), SecretError> {
+    let (_, params) = fresh_key(passphrase)?;
+    sqlx::query(
+        "INSERT INTO secrets_key (id, salt, check_nonce, check_ciphertext) VALUES (1, ?, ?, ?)
+         ON CONFLICT(id) DO NOTHING",
+    )
+    .bind(params.salt)
+    .bind(params.check_nonce)
+    .bind(params.check_ciphertext)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn existing_key(conn: &mut SqliteConnection, passphrase: &str) -> Result<Option<SecretKey>, SecretError> {
+    let Some(params) = key_params(conn).await? else {
+        return Ok(None);
+    };
+    let key = derive_key(passphrase, &params.salt)?;
+    match open(&key, CHECK_AAD, &params.check_nonce, &params.check_ciphertext) {
+        Some(check) if check == CHECK_VALUE => Ok(Some(key)),
+        _ => Err(SecretError::WrongKey),
+    }
+}
+
+/// Derives the store's key from an already verified passphrase, setting the
+/// store up on first use
+pub async fn unlock(pool: &SqlitePool, passphrase: &str) -> Result<SecretKey, SecretError> {
+    let mut tx = pool.begin().await?;
+    let key = match existing_key(&mut tx, passphrase).await? {
+        Some(key) => key,
+        None => {
+            create_key_if_missing(&mut tx, passphrase).await?;
+            existing_key(&mut tx, passphrase).await?.ok_or(SecretError::WrongKey)?
+        }
+    };
+    tx.commit().await?;
+    Ok(key)
+}
+
+/// Re-encrypts every secret under a key derived from the new passphrase.
+/// Run it in the transaction that stores the new passphrase.
+pub async fn rekey(conn: &mut SqliteConnection, old_passphrase: &str, new_passphrase: &str) -> Result<SecretKey, SecretError> {
+    let Some(old_key) = existing_key(conn, old_passphrase).await? else {
+        return new_key(conn, new_passphrase).await;
+    };
+    let sealed: Vec<SealedSecret> = sqlx::query_as("SELECT name, nonce, ciphertext FROM secrets")
+        .fetch_all(&mut *conn)
+        .await?;
+
+    let key = new_key(conn, new_passphrase).await?;
+    for secret in sealed {
+        let plaintext = open(&old_key, &secret.name, &secret.nonce, &secret.ciphertext)
+            .ok_or_else(|| SecretError::Corrupt(secret.name.clone()))?;
+        let (nonce, ciphertext) = seal(&key, &secret.name, &plaintext);
+        sqlx::query("UPDATE secrets SET nonce = ?, ciphertext = ? WHERE name = ?")
+            .bind(nonce)
+            .bind(ciphertext)
+            .bind(&secret.name)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(key)
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<SecretInfo>, sqlx::Error> {
+    sqlx::query_as("SELECT name, created_at, updated_at FROM secrets ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn find(pool: &SqlitePool, name: &str) -> Result<Option<SecretInfo>, sqlx::Error> {
+    sqlx::query_as("SELECT name, created_at, updated_at FROM secrets WHERE name = ?")
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Stores a new secret or replaces an existing one's value
+pub async fn put(pool: &SqlitePool, key: &SecretKey, name: &str, value: &str) -> Result<SecretInfo, sqlx::Error> {
+    let (nonce, ciphertext) = seal(key, name, value.as_bytes());
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query_as(
+        "INSERT INTO secrets (name, nonce, ciphertext, created_at, updated_at) VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(name) DO UPDATE SET nonce = excluded.nonce, ciphertext = excluded.ciphertext,
+             updated_at = excluded.updated_at
+         RETURNING name, created_at, updated_at",
+    )
+    .bind(name)
+    .bind(nonce)
+    .bind(ciphertext)
+    .bind(now)
+    .bind(now)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete(pool: &SqlitePool, name: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM secrets WHERE name = ?")
+        .bind(name)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Every secret's plaintext by name, for resolving config references
+pub async fn values(pool: &SqlitePool, key: &SecretKey) -> Result<HashMap<String, String>, SecretError> {
+    let sealed: Vec<SealedSecret> = sqlx::query_as("SELECT name, nonce, ciphertext FROM secrets")
+        .fetch_all(pool)
+        .await?;
+    sealed
+        .into_iter()
+        .map(|secret| {
+            let plaintext = open(key, &secret.name, &secret.nonce, &secret.ciphertext)
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(|| SecretError::Corrupt(secret.name.clone()))?;
+            Ok((secret.name, plaintext))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_secrets_survive_a_passphrase_change_only_under_the_new_key() {
+        let pool = crate::db::test_pool().await;
+        let key = unlock(&pool, "first").await.unwrap();
+        put(&pool, &key, "openai", "sk-one").await.unwrap();
+        put(&pool, &key, "openai", "sk-two").await.unwrap();
+        put(&pool, &key, "elevenlabs", "xi-key").await.unwrap();
+
+        // Nothing is stored in the clear
+        let raw: Vec<Vec<u8>> = sqlx::query_scalar("SELECT ciphertext FROM secrets")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert!(raw.iter().all(|c| !c.windows(6).any(|w| w == b"sk-two")));
+
+        let names: Vec<String> = list(&pool).await.unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["elevenlabs".to_string(), "openai".to_string()]);
+        assert_eq!(values(&pool, &key).await.unwrap()["openai"], "sk-two");
+        assert!(matches!(unlock(&pool, "guess").await, Err(SecretError::WrongKey)));
+        // A second first-unlock doesn't replace the key the first one created
+        let mut conn = pool.acquire().await.unwrap();
+        create_key_if_missing(&mut conn, "first").await.unwrap();
+        drop(conn);
+        assert_eq!(values(&pool, &key).await.unwrap()["openai"], "sk-two");
+
+        let mut tx = pool.begin().await.unwrap();
+        let new_key = rekey(&mut tx, "first", "second").await.unwrap();
+        tx.commit().await.unwrap();
+        assert!(matches!(unlock(&pool, "first").await, Err(SecretError::WrongKey)));
+        assert!(matches!(values(&pool, &key).await, Err(SecretError::Corrupt(_))));
+        let unlocked = unlock(&pool, "second").await.unwrap();
+        assert_eq!(values(&pool, &unlocked).await.unwrap()["elevenlabs"], "xi-key");
+        assert_eq!(values(&pool, &new_key).await.unwrap().len(), 2);
+
+        assert!(delete(&pool, "openai").await.unwrap());
+        assert!(!delete(&pool, "openai").await.unwrap());
+        assert!(!is_valid_name("../openai") && !is_valid_name("") && is_valid_name("proxy.main_key"));
+    }
+}
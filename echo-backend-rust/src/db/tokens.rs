@@ -0,0 +1,180 @@
+// src/db/tokens.rs
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Outcome of presenting a refresh token
+#[derive(Debug, PartialEq)]
+pub enum Rotation {
+    /// The token was valid; it is now spent and `token` replaces it
+    Rotated { user_id: String, token: String },
+    /// The token had already been rotated. Presenting it again means it was
+    /// copied, so every session for the user is revoked.
+    Reused { user_id: String },
+    Invalid,
+}
+
+#[derive(sqlx::FromRow)]
+struct StoredRefresh {
+    id: String,
+    user_id: String,
+    expires_at: i64,
+    revoked_at: Option<i64>,
+    replaced_by: Option<String>,
+}
+
+pub(crate) fn hash_token(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+pub(crate) fn new_token() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+async fn insert_refresh(
+    conn: &mut sqlx::SqliteConnection,
+    user_id: &str,
+) -> Result<(String, String), sqlx::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let token = new_token();
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query(
+        "INSERT INTO refresh_tokens (id, user_id, token_hash, created_at, expires_at) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(now)
+    .bind(now + REFRESH_TOKEN_TTL_DAYS * 24 * 60 * 60)
+    .execute(conn)
+    .await?;
+    Ok((id, token))
+}
+
+/// Issues a new refresh token and returns its plaintext
+pub async fn create_refresh(pool: &SqlitePool, user_id: &str) -> Result<String, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let (_, token) = insert_refresh(&mut conn, user_id).await?;
+    Ok(token)
+}
+
+pub async fn rotate_refresh(pool: &SqlitePool, token: &str) -> Result<Rotation, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = pool.begin().await?;
+    let row: Option<StoredRefresh> = sqlx::query_as(
+        "SELECT id, user_id, expires_at, revoked_at, replaced_by FROM refresh_tokens WHERE token_hash = ?",
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(StoredRefresh { id, user_id, expires_at, revoked_at, replaced_by }) = row else {
+        return Ok(Rotation::Invalid);
+    };
+    // Only a rotated token is evidence of copying; one revoked by logout or
+    // revoke-all is just dead
+    if revoked_at.is_some() {
+        return reuse_or_invalid(pool, tx, user_id, replaced_by.is_some()).await;
+    }
+    if expires_at <= now {
+        return Ok(Rotation::Invalid);
+    }
+
+    // Spend the token only if nobody else has; a concurrent refresh with the
+    // same token finds it already spent and counts as reuse
+    let spent = sqlx::query("UPDATE refresh_tokens SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+        .bind(now)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await?;
+    if spent.rows_affected() == 0 {
+        let replaced_by: Option<String> = sqlx::query_scalar("SELECT replaced_by FROM refresh_tokens WHERE id = ?")
+            .bind(&id)
+            .fetch_one(&mut *tx)
+            .await?;
+        return reuse_or_invalid(pool, tx, user_id, replaced_by.is_some()).await;
+    }
+
+    let (new_id, new_token) = insert_refresh(&mut tx, &user_id).await?;
+    sqlx::query("UPDATE refresh_tokens SET replaced_by = ? WHERE id = ?")
+        .bind(&new_id)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Rotation::Rotated { user_id, token: new_token })
+}
+
+// Abandons the rotation; a token that was rotated before signs the user out everywhere
+async fn reuse_or_invalid(
+    pool: &SqlitePool,
+    tx: sqlx::Transaction<'_, sqlx::Sqlite>,
+    user_id: String,
+    rotated: bool,
+) -> Result<Rotation, sqlx::Error> {
+    tx.rollback().await?;
+    if !rotated {
+        return Ok(Rotation::Invalid);
+    }
+    revoke_all_for_user(pool, &user_id).await?;
+    Ok(Rotation::Reused { user_id })
+}
+
+/// Returns false if the token was unknown or already revoked
+pub async fn revoke_refresh(pool: &SqlitePool, token: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE refresh_tokens SET revoked_at = ? WHERE token_hash = ? AND revoked_at IS NULL")
+        .bind(chrono::Utc::now().timestamp())
+        .bind(hash_token(token))
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Adds an access token to the denylist until it would have expired anyway
+pub async fn deny_access_token(pool: &SqlitePool, jti: &str, expires_at: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM revoked_tokens WHERE expires_at < ?")
+        .bind(chrono::Utc::now().timestamp())
+        .execute(pool)
+        .await?;
+    sqlx::query("INSERT OR IGNORE INTO revoked_tokens (jti, expires_at) VALUES (?, ?)")
+        .bind(jti)
+        .bind(expires_at)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Revokes every refresh token for the user and rejects access tokens issued until now
+pub async fn revoke_all_for_user(pool: &SqlitePool, user_id: &str) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now();
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE refresh_tokens SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL")
+        .bind(now.timestamp())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE users SET sessions_revoked_at = ? WHERE id = ?")
+        .bind(now.timestamp_micros())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+/// Whether an access token has been revoked, either by its jti or by a
+/// revoke-all for its user. `issued_at_micros` is in microseconds, like
+/// `sessions_revoked_at`.
+pub async fn is_access_revoked(pool: &SqlitePool, jti: &str, username: &str, issued_at_micros: i64) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM revoked_tokens WHERE jti = ?)
+             OR EXISTS (SELECT 1 FROM users WHERE username = ? AND sessions_revoked_at >= ?)",
+    )
+    .bind(jti)
+    .bind(username)
+    .bind(issued_at_micros)
+    .fetch_one(pool)
+    .await
+}
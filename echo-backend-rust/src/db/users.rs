@@ -0,0 +1,195 @@
+// src/db/users.rs
+use argon2::password_hash::{rand_core::OsRng, SaltString};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use serde::Serialize;
+use sqlx::types::Json;
+use sqlx::SqlitePool;
+
+/// Features every new account starts with
+pub const DEFAULT_FEATURES: &[&str] = &["chat", "streaming", "voice-basic"];
+/// Features granted to accounts made with `create-admin`
+pub const ADMIN_FEATURES: &[&str] = &["admin", "*"];
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct User {
+    pub id: String,
+    pub username: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub features: Json<Vec<String>>,
+    pub is_dev: bool,
+    pub created_at: i64,
+    pub last_login_at: Option<i64>,
+    pub disabled_at: Option<i64>,
+}
+
+impl User {
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_at.is_some()
+    }
+
+    pub fn verify_password(&self, password: &str) -> bool {
+        verify_hash(&self.password_hash, password)
+    }
+}
+
+/// Checks a password against an argon2 PHC string
+pub fn verify_hash(hash: &str, password: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+/// Does the same argon2 work as checking a real account's password, for
+/// logins to a username that has none, so timing doesn't reveal which exist
+pub fn verify_dummy_password(password: &str) {
+    static DUMMY_HASH: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    let hash = DUMMY_HASH.get_or_init(|| hash_password("no such account").expect("hashing a fixed password"));
+    verify_hash(hash, password);
+}
+
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+/// Whether a failed insert was because the username is already taken
+pub fn is_username_taken(error: &sqlx::Error) -> bool {
+    error.as_database_error().is_some_and(|e| e.is_unique_violation())
+}
+
+pub async fn create(
+    pool: &SqlitePool,
+    username: &str,
+    password: &str,
+    features: &[String],
+    is_dev: bool,
+) -> Result<User, sqlx::Error> {
+    let password_hash = hash_password(password)
+        .map_err(|e| sqlx::Error::Protocol(format!("Failed to hash password: {}", e)))?;
+    let user = User {
+        id: uuid::Uuid::new_v4().to_string(),
+        username: username.to_string(),
+        password_hash,
+        features: Json(features.to_vec()),
+        is_dev,
+        created_at: chrono::Utc::now().timestamp(),
+        last_login_at: None,
+        disabled_at: None,
+    };
+
+    sqlx::query(
+        "INSERT INTO users (id, username, password_hash, features, is_dev, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&user.id)
+    .bind(&user.username)
+    .bind(&user.password_hash)
+    .bind(&user.features)
+    .bind(user.is_dev)
+    .bind(user.created_at)
+    .execute(pool)
+    .await?;
+
+    Ok(user)
+}
+
+/// Usernames match case-insensitively
+pub async fn find_by_username(pool: &SqlitePool, username: &str) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM users WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn record_login(pool: &SqlitePool, id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET last_login_at = ? WHERE id = ?")
+        .bind(chrono::Utc::now().timestamp())
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<User>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM users ORDER BY created_at, username")
+        .fetch_all(pool)
+        .await
+}
+
+/// Total and disabled account counts
+pub async fn counts(pool: &SqlitePool) -> Result<(i64, i64), sqlx::Error> {
+    sqlx::query_as("SELECT COUNT(*), COUNT(disabled_at) FROM users")
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn set_features(pool: &SqlitePool, id: &str, features: &[String]) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET features = ? WHERE id = ?")
+        .bind(Json(features))
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_dev(pool: &SqlitePool, id: &str, is_dev: bool) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET is_dev = ? WHERE id = ?")
+        .bind(is_dev)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_disabled(pool: &SqlitePool, id: &str, disabled: bool) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET disabled_at = ? WHERE id = ?")
+        .bind(disabled.then(|| chrono::Utc::now().timestamp()))
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_password(pool: &SqlitePool, id: &str, password: &str) -> Result<(), sqlx::Error> {
+    let password_hash = hash_password(password)
+        .map_err(|e| sqlx::Error::Protocol(format!("Failed to hash password: {}", e)))?;
+    sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+        .bind(password_hash)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_users_persist_with_hashed_passwords() {
+        let pool = crate::db::test_pool().await;
+        let features: Vec<String> = DEFAULT_FEATURES.iter().map(|f| f.to_string()).collect();
+
+        let user = create(&pool, "Alice", "correct horse", &features, false).await.unwrap();
+        assert_ne!(user.password_hash, "correct horse");
+
+        let duplicate = create(&pool, "alice", "another", &features, false).await.unwrap_err();
+        assert!(is_username_taken(&duplicate));
+
+        let found = find_by_username(&pool, "ALICE").await.unwrap().unwrap();
+        assert_eq!(found.id, user.id);
+        assert!(found.verify_password("correct horse"));
+        assert!(!found.verify_password("wrong"));
+        assert_eq!(found.features.0, features);
+
+        record_login(&pool, &user.id).await.unwrap();
+        assert!(find_by_username(&pool, "alice").await.unwrap().unwrap().last_login_at.is_some());
+    }
+}
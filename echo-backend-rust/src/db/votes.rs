@@ -0,0 +1,103 @@
+// src/db/votes.rs
+use serde::Serialize;
+use sqlx::types::Json;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+
+use crate::models::ratings::VoteOutcome;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Vote {
+    pub id: i64,
+    pub user_id: String,
+    pub team: String,
+    pub category: String,
+    pub conversation_id: Option<String>,
+    pub prompt: Option<String>,
+    pub model_a: String,
+    pub response_a: String,
+    pub model_b: String,
+    pub response_b: String,
+    pub outcome: String, // "a", "b" or "tie"
+    pub tags: Json<Vec<String>>,
+    pub created_at: i64,
+}
+
+impl Vote {
+    pub fn outcome(&self) -> Option<VoteOutcome> {
+        VoteOutcome::parse(&self.outcome)
+    }
+}
+
+/// Everything about a vote except what the database assigns
+#[derive(Debug, Clone)]
+pub struct NewVote {
+    pub team: String,
+    pub category: String,
+    pub conversation_id: Option<String>,
+    pub prompt: Option<String>,
+    pub model_a: String,
+    pub response_a: String,
+    pub model_b: String,
+    pub response_b: String,
+    pub outcome: VoteOutcome,
+    pub tags: Vec<String>,
+}
+
+pub async fn create(pool: &SqlitePool, user_id: &str, vote: &NewVote) -> Result<Vote, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let id = sqlx::query(
+        "INSERT INTO model_votes (user_id, team, category, conversation_id, prompt, model_a, response_a, model_b, response_b, outcome, tags, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(user_id)
+    .bind(&vote.team)
+    .bind(&vote.category)
+    .bind(&vote.conversation_id)
+    .bind(&vote.prompt)
+    .bind(&vote.model_a)
+    .bind(&vote.response_a)
+    .bind(&vote.model_b)
+    .bind(&vote.response_b)
+    .bind(vote.outcome.as_str())
+    .bind(Json(&vote.tags))
+    .bind(now)
+    .execute(pool)
+    .await?
+    .last_insert_rowid();
+
+    Ok(Vote {
+        id,
+        user_id: user_id.to_string(),
+        team: vote.team.clone(),
+        category: vote.category.clone(),
+        conversation_id: vote.conversation_id.clone(),
+        prompt: vote.prompt.clone(),
+        model_a: vote.model_a.clone(),
+        response_a: vote.response_a.clone(),
+        model_b: vote.model_b.clone(),
+        response_b: vote.response_b.clone(),
+        outcome: vote.outcome.as_str().to_string(),
+        tags: Json(vote.tags.clone()),
+        created_at: now,
+    })
+}
+
+/// A team's votes in the order they were cast, optionally limited to one category
+pub async fn for_team(pool: &SqlitePool, team: &str, category: Option<&str>) -> Result<Vec<Vote>, sqlx::Error> {
+    let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM model_votes WHERE team = ");
+    builder.push_bind(team);
+    if let Some(category) = category {
+        builder.push(" AND category = ").push_bind(category);
+    }
+    builder.push(" ORDER BY id");
+
+    builder.build_query_as().fetch_all(pool).await
+}
+
+/// The categories a team has voted in, with how many votes each holds
+pub async fn categories(pool: &SqlitePool, team: &str) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    sqlx::query_as("SELECT category, COUNT(*) FROM model_votes WHERE team = ? GROUP BY category ORDER BY category")
+        .bind(team)
+        .fetch_all(pool)
+        .await
+}
@@ -1,5 +1,80 @@
-use crate::auth::Claims;
+use axum::{
+    extract::Request,
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+use axum::http::StatusCode;
+use crate::auth::{AuthError, Claims};
+
+// Feature flags that routes require beyond a valid identity
+pub const CHAT: &str = "chat";                             // every /llm route
+pub const LLM_USE: &str = "llm.use";                       // switch the primary model for everyone
+pub const LLM_ADMIN: &str = "llm.admin";                   // pull, copy and delete local models
+pub const VAULT_READ_PRIVATE: &str = "vault.read.private"; // Private-scope notes, directly or as LLM context
+pub const VAULT_WRITE: &str = "vault.write";               // reindex the vault or write notes into it
+pub const ADMIN: &str = "admin";                           // /admin routes and the audit log
+pub const TEAM_PREFIX: &str = "team:";                     // membership, e.g. "team:research" (lowercase)
 
 pub fn has_feature(claims: &Claims, key: &str) -> bool {
-    claims.features.contains(&key.to_string()) || claims.features.contains(&"*".to_string())
-}
\ No newline at end of file
+    claims.has_feature(key)
+}
+
+/// Whether the caller belongs to `team`, given as a lowercase name
+pub fn in_team(claims: &Claims, team: &str) -> bool {
+    has_feature(claims, &format!("{}{}", TEAM_PREFIX, team))
+}
+
+/// Checked inside admin handlers, which all need the claims anyway
+pub fn require_admin(claims: &Claims) -> Result<(), StatusCode> {
+    if has_feature(claims, ADMIN) {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// Require `feature` on every route in `router`. Runs after the auth
+/// middleware, so a missing identity has already been turned away.
+pub fn require<S>(feature: &'static str, router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.route_layer(middleware::from_fn(move |req: Request, next: Next| enforce(feature, req, next)))
+}
+
+async fn enforce(feature: &'static str, req: Request, next: Next) -> Result<Response, AuthError> {
+    let claims = req.extensions().get::<Claims>().ok_or(AuthError::MissingToken)?;
+    if !has_feature(claims, feature) {
+        return Err(AuthError::InsufficientPermissions);
+    }
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::post, Extension};
+    use tower::ServiceExt;
+
+    async fn status_for(claims: Option<Claims>) -> StatusCode {
+        let mut app = require(LLM_USE, Router::new().route("/use", post(|| async { "switched" })));
+        if let Some(claims) = claims {
+            app = app.layer(Extension(claims));
+        }
+        let request = Request::builder().method("POST").uri("/use").body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_require_checks_the_route_feature() {
+        let chat_only = Claims { features: vec![CHAT.to_string()], ..Claims::default() };
+        let switcher = Claims { features: vec![LLM_USE.to_string()], ..Claims::default() };
+        let admin = Claims { features: vec!["*".to_string()], ..Claims::default() };
+
+        assert_eq!(status_for(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status_for(Some(chat_only)).await, StatusCode::FORBIDDEN);
+        assert_eq!(status_for(Some(switcher)).await, StatusCode::OK);
+        assert_eq!(status_for(Some(admin)).await, StatusCode::OK);
+    }
+}
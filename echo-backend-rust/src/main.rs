@@ -1,37 +1,37 @@
 // src/main.rs - Complete updated version wsqlxstore::newith vault integration and session support
 use axum::{
-    extract::{Query, Request, State},
+    extract::{ConnectInfo, Query, Request, State},
     http::StatusCode,
     middleware::{self, Next},
-    response::{Json, Sse},
+    response::{IntoResponse, Json, Response, Sse},
     routing::{get, post},
     Extension, Router,
 };
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tower_http::cors::CorsLayer;
 use anyhow::Result;
 use chrono;
-use argon2::{Argon2, PasswordHash, PasswordVerifier, PasswordHasher};
-use argon2::password_hash::{SaltString, rand_core::OsRng};
 use tower_sessions::{Expiry, SessionManagerLayer};
-use tower_sessions::MemoryStore;
 use sqlx::SqlitePool;
 use crate::state::vault_state::{VaultConfig, VaultStructure};
 
 mod auth;
+mod db;
 mod routes;
 mod features;
+mod rate_limit;
 mod models;
 mod state;
 mod vault;
 
-use auth::{generate_token, require_auth, Claims};
+use auth::{require_auth, Claims};
 use state::{RuntimeState, RuntimeConfig, VaultState};
+use state::llm_queue::LlmQueue;
+use vault::prompt_templates::TemplateLibrary;
 use models::{
     llm::{LLMModule, LLMProvider},
     whisper::{WhisperEngine, WhisperConfig},
@@ -42,12 +42,17 @@ use models::{
 #[derive(Clone)]
 pub struct AppState {
     pub jwt_secret: Arc<Vec<u8>>,
-    pub users: Arc<RwLock<HashMap<String, UserData>>>,
     pub messages: Arc<RwLock<Vec<EchoMessage>>>,
     pub whisper: Option<Arc<WhisperEngine>>,
     pub tts: Option<Arc<TTSEngine>>,
     pub runtime_state: Arc<RwLock<RuntimeState>>,
     pub vault_state: Arc<RwLock<VaultState>>,
+    pub llm_queue: Arc<LlmQueue>,
+    pub db: SqlitePool,
+    pub templates: Arc<TemplateLibrary>,
+    pub rate_limiter: Arc<rate_limit::RateLimiter>,
+    pub setup_code: Arc<std::sync::Mutex<Option<String>>>, // only while no vault passphrase is set
+    pub secrets_key: Arc<std::sync::RwLock<Option<db::secrets::SecretKey>>>, // once the vault passphrase is entered
 }
 
 impl AppState {
@@ -58,13 +63,6 @@ impl AppState {
     }
 }
 
-// User data structure
-#[derive(Clone, Debug)]
-struct UserData {
-    password_hash: String,
-    features: Vec<String>,
-}
-
 // Echo message structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EchoMessage {
@@ -104,9 +102,11 @@ pub struct LoginRequest {
     pub password: String,
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64, // seconds until the access token expires
     pub username: String,
 }
 
@@ -152,89 +152,69 @@ async fn signup(
     State(state): State<AppState>,
     Json(payload): Json<SignupRequest>,
 ) -> Result<Json<AuthResponse>, StatusCode> {
-    let mut users = state.users.write().await;
-    
-    // Check if user already exists
-    if users.contains_key(&payload.username) {
+    let username = payload.username.trim();
+    if username.is_empty() || payload.password.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if auth::is_reserved_username(username) {
         return Err(StatusCode::CONFLICT);
     }
-    
-    // Hash password
-    let argon2 = Argon2::default();
-    let salt = SaltString::generate(&mut OsRng);
-    let password_hash = argon2
-        .hash_password(payload.password.as_bytes(), &salt)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .to_string();
-    
+
     // Create user with voice features
-    users.insert(
-        payload.username.clone(),
-        UserData {
-            password_hash,
-            features: vec![
-                "chat".to_string(), 
-                "streaming".to_string(),
-                "voice-basic".to_string()
-            ],
-        },
-    );
-    
-    // Generate token
-    let claims = Claims {
-        sub: payload.username.clone(),
-        exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
-        is_dev: false,
-        features: vec![
-            "chat".to_string(), 
-            "streaming".to_string(),
-            "voice-basic".to_string()
-        ],
-    };
-    
-    let token = generate_token(&claims, &state.jwt_secret)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
-    Ok(Json(AuthResponse {
-        token,
-        username: payload.username,
-    }))
+    let features: Vec<String> = db::users::DEFAULT_FEATURES.iter().map(|f| f.to_string()).collect();
+    let user = db::users::create(&state.db, username, &payload.password, &features, false)
+        .await
+        .map_err(|e| {
+            if db::users::is_username_taken(&e) {
+                StatusCode::CONFLICT
+            } else {
+                eprintln!("Failed to create user: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    routes::sessions::issue_tokens(&state, &user).await.map(Json)
 }
 
 // Login endpoint
 async fn login(
     State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<std::net::SocketAddr>>,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<AuthResponse>, StatusCode> {
-    let users = state.users.read().await;
-    
-    // Find user
-    let user_data = users.get(&payload.username).ok_or(StatusCode::UNAUTHORIZED)?;
-    
-    // Verify password
-    let argon2 = Argon2::default();
-    let parsed_hash = PasswordHash::new(&user_data.password_hash)
+) -> Result<Response, StatusCode> {
+    let username = payload.username.trim();
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let keys = routes::lockouts::attempt_keys(username, ip);
+    if let Err(refused) = routes::lockouts::check(&state.db, &keys).await {
+        return Ok(refused);
+    }
+
+    let user = db::users::find_by_username(&state.db, username)
+        .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
-    argon2
-        .verify_password(payload.password.as_bytes(), &parsed_hash)
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
-    
-    // Generate token
-    let claims = Claims {
-        sub: payload.username.clone(),
-        exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
-        is_dev: false,
-        features: user_data.features.clone(),
+    // Accounts made before the built-in names were reserved can't log in
+    let user = match user.filter(|user| !auth::is_reserved_username(&user.username)) {
+        Some(user) => user.verify_password(&payload.password).then_some(user),
+        None => {
+            db::users::verify_dummy_password(&payload.password);
+            None
+        }
     };
-    
-    let token = generate_token(&claims, &state.jwt_secret)
+    let Some(user) = user else {
+        routes::lockouts::record_failure(&state.db, &keys, ip).await;
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    routes::lockouts::record_success(&state.db, &keys).await;
+    if user.is_disabled() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    db::users::record_login(&state.db, &user.id)
+        .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
-    Ok(Json(AuthResponse {
-        token,
-        username: payload.username,
-    }))
+
+    let tokens = routes::sessions::issue_tokens(&state, &user).await?;
+    Ok(Json(tokens).into_response())
 }
 
 // Echo endpoint - requires authentication
@@ -327,39 +307,110 @@ async fn message_stream(
     )
 }
 
-// Admin endpoint - requires admin features
-async fn admin_stats(
-    State(state): State<AppState>,
-    Extension(claims): Extension<Claims>,
-) -> Result<Json<Value>, StatusCode> {
-    // Check if user has admin feature
-    if !claims.has_feature("admin") {
-        return Err(StatusCode::FORBIDDEN);
-    }
-    
-    let messages = state.messages.read().await;
-    let total_messages = messages.len();
-    let unique_users = messages
-        .iter()
-        .map(|m| &m.user_id)
-        .collect::<std::collections::HashSet<_>>()
-        .len();
-    
-    // Add voice session stats if available
-    let voice_sessions = 0; // Placeholder since voice_manager is commented out
+// Minimal application state for handler tests: no voice engines, no indexer
+#[cfg(test)]
+pub(crate) async fn test_app_state(config: models::config::Config) -> AppState {
+    let vault_state = VaultState {
+        vault_path: std::path::PathBuf::from("vault"),
+        indexed_files: std::collections::HashMap::new(),
+        pending_files: std::collections::VecDeque::new(),
+        last_scan: None,
+        watcher: None,
+        indexer: None,
+        event_rx: None,
+    };
     
-    Ok(Json(serde_json::json!({
-        "total_messages": total_messages,
-        "unique_users": unique_users,
-        "active_voice_sessions": voice_sessions,
-        "service": "echo-rubicon-admin"
-    })))
+    AppState {
+        jwt_secret: Arc::new(b"test-secret".to_vec()),
+        messages: Arc::new(RwLock::new(Vec::new())),
+        whisper: None,
+        tts: None,
+        runtime_state: Arc::new(RwLock::new(RuntimeState::new(config))),
+        vault_state: Arc::new(RwLock::new(vault_state)),
+        llm_queue: Arc::new(LlmQueue::new()),
+        db: db::test_pool().await,
+        templates: Arc::new(TemplateLibrary::new()),
+        rate_limiter: Arc::new(rate_limit::RateLimiter::new()),
+        setup_code: Arc::default(),
+        secrets_key: Arc::default(),
+    }
+}
+
+const DATABASE_URL: &str = "sqlite:echo_sessions.db?mode=rwc";
+
+// echo-backend import <conversations.json> <user> [--notes] [--folder <vault folder>]
+async fn run_import_command(args: &[String]) -> Result<()> {
+    let (Some(file), Some(user)) = (args.first(), args.get(1)) else {
+        anyhow::bail!("Usage: echo-backend import <conversations.json> <user> [--notes] [--folder <vault folder>]");
+    };
+    let folder = args.iter()
+        .position(|arg| arg == "--folder")
+        .and_then(|i| args.get(i + 1));
+    let vault_path = if args.iter().any(|arg| arg == "--notes") {
+        let config = RuntimeConfig::load_or_create("config.json").await?;
+        Some(std::path::PathBuf::from(config.vault_path.unwrap_or_else(|| "vault".to_string())))
+    } else {
+        None
+    };
+
+    let data = tokio::fs::read(file).await?;
+    let pool = db::connect(DATABASE_URL).await?;
+    let report = routes::import::import_export(&pool, user, &data, vault_path.as_deref(), folder.map(String::as_str))
+        .await
+        .map_err(anyhow::Error::msg)?;
+
+    println!("📥 Imported {} {} conversations for {} ({} already present)",
+        report.imported, report.source.label(), user, report.skipped);
+    if !report.notes.is_empty() {
+        println!("📝 Wrote {} notes into the vault", report.notes.len());
+    }
+    Ok(())
+}
+
+// echo-backend create-admin <username>
+// The password comes from ECHO_ADMIN_PASSWORD, or the first line of stdin
+async fn run_create_admin_command(args: &[String]) -> Result<()> {
+    let Some(username) = args.first() else {
+        anyhow::bail!("Usage: echo-backend create-admin <username>");
+    };
+    if auth::is_reserved_username(username) {
+        anyhow::bail!("{} is reserved and can't be used as a username", username);
+    }
+    let password = match std::env::var("ECHO_ADMIN_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => {
+            println!("Password for {}:", username);
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+    if password.is_empty() {
+        anyhow::bail!("Refusing to create an admin with an empty password");
+    }
+
+    let pool = db::connect(DATABASE_URL).await?;
+    let features: Vec<String> = db::users::ADMIN_FEATURES.iter().map(|f| f.to_string()).collect();
+    match db::users::create(&pool, username, &password, &features, false).await {
+        Ok(user) => println!("👤 Created admin user {} ({})", user.username, user.id),
+        Err(e) if db::users::is_username_taken(&e) => anyhow::bail!("User {} already exists", username),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize tracing
     tracing_subscriber::fmt::init();
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("import") {
+        return run_import_command(&args[2..]).await;
+    }
+    if args.get(1).map(String::as_str) == Some("create-admin") {
+        return run_create_admin_command(&args[2..]).await;
+    }
     
     // Generate JWT secret
     let jwt_secret = Arc::new(
@@ -373,7 +424,11 @@ async fn main() -> Result<()> {
     println!("🚀 Server starting on port {}", config.server_port);
 
     // Initialize runtime state
-    let runtime_state = Arc::new(RwLock::new(RuntimeState::new(config.clone())));
+    let runtime_state = RuntimeState::new(config.clone());
+    if !runtime_state.secret_refs().is_empty() {
+        println!("🔒 API keys kept in the secrets store stay unset until the vault passphrase is entered");
+    }
+    let runtime_state = Arc::new(RwLock::new(runtime_state));
 
     // Initialize vault state
     println!("📁 Initializing vault system...");
@@ -409,37 +464,55 @@ async fn main() -> Result<()> {
         None
     };
     
+    // Create SQLite connection pool for sessions and conversation history
+    let pool = db::connect(DATABASE_URL)
+        .await
+        .expect("Failed to open database");
+    
+    // A fresh install can only have its passphrase set with this code
+    let setup_code = match db::passphrase::get(&pool).await? {
+        Some(_) => None,
+        None => {
+            let code = routes::auth::generate_setup_code();
+            println!("🔑 No vault passphrase is set. Set one with POST /api/auth/setup using setup code: {}", code);
+            Some(code)
+        }
+    };
+
     // Create application state
     let app_state = AppState {
         jwt_secret: jwt_secret.clone(),
-        users: Arc::new(RwLock::new(HashMap::new())),
         messages: Arc::new(RwLock::new(Vec::new())),
         whisper,
         tts,
         runtime_state,
         vault_state,
+        llm_queue: Arc::new(LlmQueue::new()),
+        db: pool.clone(),
+        templates: Arc::new(TemplateLibrary::new()),
+        rate_limiter: Arc::new(rate_limit::RateLimiter::new()),
+        setup_code: Arc::new(std::sync::Mutex::new(setup_code)),
+        secrets_key: Arc::default(),
     };
 
-    // Create SQLite connection pool for sessions
-    // Create SQLite connection pool for sessions
-let db_path = "sqlite:echo_sessions.db?mode=rwc";
-let pool = sqlx::sqlite::SqlitePoolOptions::new()
-    .max_connections(5)
-    .connect(db_path)
-    .await
-    .expect("Failed to create session database pool");
-
-    // Run auth migration
-    sqlx::migrate!("./migrations")
-        .run(&pool)
-        .await
-        .expect("Failed to run migrations");
+    // Pick up batch jobs that were still running when the server last stopped
+    routes::batches::resume_batches(&app_state).await;
 
-    
-    // Create session store
-    use tower_sessions::MemoryStore;
-    let session_store = MemoryStore::default();
-// No migration needed for memory store
+    // Create session store; unlocked sessions are kept in SQLite so they survive restarts
+    let session_store = db::sessions::SqliteSessionStore::new(pool.clone());
+    tokio::spawn({
+        use tower_sessions::ExpiredDeletion;
+        let session_store = session_store.clone();
+        async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+            loop {
+                interval.tick().await;
+                if let Err(e) = session_store.delete_expired().await {
+                    eprintln!("Failed to purge expired sessions: {}", e);
+                }
+            }
+        }
+    });
 
     let session_layer = SessionManagerLayer::new(session_store)
         .with_secure(false)
@@ -455,32 +528,44 @@ let pool = sqlx::sqlite::SqlitePoolOptions::new()
         .route("/echo", post(echo_message))
         .route("/messages", get(get_messages))
         .route("/stream", get(message_stream))
-        .route("/admin/stats", get(admin_stats))
+        .merge(routes::audit::routes())
+        .merge(routes::sessions::routes())
+        .merge(routes::admin::routes())
+        .merge(routes::access_tokens::routes())
+        .merge(routes::lockouts::routes())
+        .merge(routes::secrets::routes())
         // LLM routes
-        .nest("/llm", routes::llm::routes())
+        .nest("/llm", features::require(features::CHAT, routes::llm::routes()
+            .merge(routes::chat::routes())
+            .merge(routes::personas::routes())
+            .merge(routes::votes::routes())
+            .merge(routes::import::routes())
+            .merge(routes::batches::routes())
+            .merge(routes::templates::routes())))
         // Vault routes
         .nest("/vault", routes::vault::routes())
         // Voice routes
         .nest("/voice", routes::voice::voice_routes())
         // Auth routes
         .nest("/api/auth", routes::auth::routes())
+        // Rate limits run inside the auth middleware so they can key on the caller
+        .layer(middleware::from_fn_with_state(app_state.clone(), rate_limit::enforce))
         // Apply auth middleware to protected routes
-        .layer(middleware::from_fn_with_state(
-            app_state.clone(),
-            |state: State<AppState>, req: Request, next: Next| async move {
+        .layer(middleware::from_fn(
+            |req: Request, next: Next| async move {
                 // Skip auth for public routes
                 let path = req.uri().path();
                 if path == "/health" 
                     || path == "/signup" 
                     || path == "/login"
-                    || path.starts_with("/llm/")
-                    || path.starts_with("/vault/") {
+                    || path == "/auth/refresh"
+                    || path == "/auth/logout"
+                    || path.starts_with("/api/auth/") {
                     return Ok(next.run(req).await);
                 }
                 
-                // Apply auth middleware
-                let jwt_secret = state.jwt_secret.clone();
-                require_auth(Extension(jwt_secret), req, next).await
+                // Apply auth middleware: a bearer JWT or an unlocked session
+                require_auth(req, next).await
             },
         ))
         .layer(CorsLayer::permissive())
@@ -499,6 +584,12 @@ let pool = sqlx::sqlite::SqlitePoolOptions::new()
     println!("\n🔐 Auth endpoints:");
     println!("   - POST /signup - Create new account");
     println!("   - POST /login - Authenticate");
+    println!("   - POST /auth/refresh - Exchange a refresh token for a new token pair");
+    println!("   - POST /auth/logout - Revoke the current tokens");
+    println!("   - GET/POST /auth/tokens - List or create personal access tokens");
+    println!("   - DELETE /auth/tokens/:id - Revoke a personal access token");
+    println!("   - POST /api/auth/setup - Set the first vault passphrase with the setup code");
+    println!("   - POST /api/auth/passphrase - Change the vault passphrase");
     println!("\n💬 Core endpoints:");
     println!("   - POST /echo - Echo a message");
     println!("   - GET  /messages - Get message history");
@@ -507,7 +598,32 @@ let pool = sqlx::sqlite::SqlitePoolOptions::new()
     println!("   - GET  /llm/models - List available models");
     println!("   - POST /llm/use - Set active model");
     println!("   - POST /llm/conversation - Multi-model conversation");
+    println!("   - POST /llm/conversation/upload - Multi-model conversation with image uploads");
     println!("   - GET  /llm/status - Model status");
+    println!("   - POST /llm/chat - Multi-turn chat with rolling summaries");
+    println!("   - GET  /llm/conversations/search - Search stored messages");
+    println!("   - GET  /llm/conversations/:id - Conversation history");
+    println!("   - GET  /llm/conversations/:id/branches - List conversation branches");
+    println!("   - POST /llm/conversations/:id/active - Pick the active branch");
+    println!("   - POST /llm/conversations/:id/messages/:message_id/edit - Edit a message into a new branch");
+    println!("   - POST /llm/conversations/:id/messages/:message_id/regenerate - Regenerate a reply");
+    println!("   - GET  /llm/conversations/:id/summary - Current summary");
+    println!("   - POST /llm/conversations/:id/summary/regenerate - Rebuild the summary");
+    println!("   - GET/POST /llm/personas - List or create personas");
+    println!("   - GET/PUT/DELETE /llm/personas/:id - Manage a persona");
+    println!("   - POST /llm/votes - Record a preference between two responses");
+    println!("   - GET  /llm/leaderboard - Model ratings from preference votes");
+    println!("   - POST /llm/import - Import a ChatGPT or Claude conversations.json");
+    println!("   - GET/POST /llm/batches - List or submit JSONL batch jobs");
+    println!("   - GET  /llm/batches/:id - Batch progress");
+    println!("   - GET  /llm/batches/:id/results - Download batch results as JSONL");
+    println!("   - POST /llm/batches/:id/cancel - Cancel a running batch");
+    println!("   - GET  /llm/templates - List prompt templates from the vault");
+    println!("   - GET  /llm/templates/:id - Show a prompt template");
+    println!("   - POST /llm/templates/:id/run - Render and run a prompt template");
+    println!("   - POST /llm/local/pull - Pull an Ollama model (SSE progress)");
+    println!("   - POST /llm/local/delete - Delete an Ollama model");
+    println!("   - POST /llm/local/copy - Copy an Ollama model");
     println!("\n📂 Vault endpoints:");
     println!("   - GET  /vault/query - Query vault documents");
     println!("   - GET  /vault/index/progress - Indexing progress");
@@ -518,10 +634,24 @@ let pool = sqlx::sqlite::SqlitePoolOptions::new()
     println!("   - GET  /voice/voices - List available voices");
     println!("\n🛠️  Admin endpoints:");
     println!("   - GET  /admin/stats - Server statistics (requires admin role)");
+    println!("   - GET  /admin/users - List user accounts");
+    println!("   - GET  /admin/users/:username - One user account");
+    println!("   - POST /admin/users/:username/features - Grant or revoke features");
+    println!("   - POST /admin/users/:username/dev - Toggle dev access");
+    println!("   - POST /admin/users/:username/disable - Disable an account and sign it out");
+    println!("   - POST /admin/users/:username/enable - Re-enable an account");
+    println!("   - POST /admin/users/:username/password - Reset a password");
+    println!("   - POST /admin/users/:username/revoke-sessions - Sign a user out everywhere");
+    println!("   - GET  /admin/lockouts - Current login lockouts and recent lockout events");
+    println!("   - DELETE /admin/lockouts/:key - Clear a lockout");
+    println!("   - GET  /admin/audit - Browse the LLM call log");
+    println!("   - GET  /admin/audit/:id - One logged LLM call");
+    println!("   - POST /admin/audit/:id/replay - Replay a logged call, optionally on another model");
     println!("\n");
     
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    // Connection info gives the client address that access-token IP ranges are checked against
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await?;
     
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,285 @@
+// src/models/chat_import.rs
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::models::llm::ChatRole;
+
+/// The services whose data exports can be imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportSource {
+    ChatGpt,
+    Claude,
+}
+
+impl ExportSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportSource::ChatGpt => "chatgpt",
+            ExportSource::Claude => "claude",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportSource::ChatGpt => "ChatGPT",
+            ExportSource::Claude => "Claude",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedMessage {
+    pub role: ChatRole,
+    pub content: String,
+    pub model: Option<String>,
+    pub created_at: i64,
+}
+
+/// One conversation from an export, flattened to the branch that was last shown
+#[derive(Debug, Clone)]
+pub struct ImportedConversation {
+    pub source_id: String,
+    pub title: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub messages: Vec<ImportedMessage>,
+}
+
+impl ImportedConversation {
+    /// The conversation as a Markdown note with frontmatter the vault indexer understands
+    pub fn to_markdown(&self, source: ExportSource) -> String {
+        let title = self.title.as_deref().unwrap_or("Untitled conversation");
+        let created = chrono::DateTime::from_timestamp(self.created_at, 0)
+            .map(|d| d.to_rfc3339())
+            .unwrap_or_default();
+
+        let mut note = format!(
+            "---\ntitle: {}\nsource: {}\nsource_id: {}\ncreated: {}\ntags: [imported, {}]\n---\n# {}\n",
+            serde_json::to_string(title).unwrap_or_default(),
+            source.as_str(),
+            self.source_id,
+            created,
+            source.as_str(),
+            title,
+        );
+        for message in &self.messages {
+            let speaker = match message.role {
+                ChatRole::User => "You".to_string(),
+                ChatRole::Assistant => message.model.clone().unwrap_or_else(|| source.label().to_string()),
+            };
+            note.push_str(&format!("\n## {}\n\n{}\n", speaker, message.content.trim()));
+        }
+        note
+    }
+}
+
+/// Parse a `conversations.json` from a ChatGPT or Claude data export, telling the two apart by shape
+pub fn parse_export(data: &[u8]) -> Result<(ExportSource, Vec<ImportedConversation>), String> {
+    let value: Value = serde_json::from_slice(data)
+        .map_err(|e| format!("Export is not valid JSON: {}", e))?;
+    let items = value.as_array()
+        .ok_or("Expected the conversations.json array from a ChatGPT or Claude export")?;
+
+    let source = match items.first() {
+        None => return Ok((ExportSource::ChatGpt, Vec::new())),
+        Some(first) if first.get("mapping").is_some() => ExportSource::ChatGpt,
+        Some(first) if first.get("chat_messages").is_some() => ExportSource::Claude,
+        Some(_) => return Err("Unrecognised export format".to_string()),
+    };
+
+    let conversations = items.iter()
+        .filter_map(|item| match source {
+            ExportSource::ChatGpt => parse_chatgpt(item),
+            ExportSource::Claude => parse_claude(item),
+        })
+        .filter(|conversation| !conversation.messages.is_empty())
+        .collect();
+    Ok((source, conversations))
+}
+
+// ChatGPT stores each conversation as a tree of nodes; walk up from current_node to
+// recover the branch the user was looking at
+fn parse_chatgpt(item: &Value) -> Option<ImportedConversation> {
+    let source_id = item.get("conversation_id").or_else(|| item.get("id"))?.as_str()?.to_string();
+    let created_at = seconds(item.get("create_time")).unwrap_or_default();
+    let mapping = item.get("mapping")?.as_object()?;
+
+    // A branch can't be longer than the tree; a longer walk means the parents form a cycle
+    let mut chain = Vec::new();
+    let mut current = item.get("current_node").and_then(Value::as_str);
+    while let Some(node_id) = current {
+        if chain.len() == mapping.len() {
+            return None;
+        }
+        let node = mapping.get(node_id)?;
+        chain.push(node);
+        current = node.get("parent").and_then(Value::as_str);
+    }
+    chain.reverse();
+
+    let messages = chain.iter()
+        .filter_map(|node| {
+            let message = node.get("message")?;
+            let role = ChatRole::parse(message.pointer("/author/role")?.as_str()?)?;
+            let content = message.get("content")?;
+            let text = match content.get("content_type")?.as_str()? {
+                "text" | "multimodal_text" => content.get("parts")?.as_array()?
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => return None,
+            };
+            if text.trim().is_empty() {
+                return None;
+            }
+            Some(ImportedMessage {
+                role,
+                content: text,
+                model: message.pointer("/metadata/model_slug").and_then(Value::as_str).map(str::to_string),
+                created_at: seconds(message.get("create_time")).unwrap_or(created_at),
+            })
+        })
+        .collect();
+
+    Some(ImportedConversation {
+        source_id,
+        title: item.get("title").and_then(Value::as_str).map(str::to_string),
+        created_at,
+        updated_at: seconds(item.get("update_time")).unwrap_or(created_at),
+        messages,
+    })
+}
+
+fn parse_claude(item: &Value) -> Option<ImportedConversation> {
+    let source_id = item.get("uuid")?.as_str()?.to_string();
+    let created_at = rfc3339(item.get("created_at")).unwrap_or_default();
+
+    let messages = item.get("chat_messages")?.as_array()?
+        .iter()
+        .filter_map(|message| {
+            let role = match message.get("sender")?.as_str()? {
+                "human" => ChatRole::User,
+                "assistant" => ChatRole::Assistant,
+                _ => return None,
+            };
+            // Newer exports split the text into typed content blocks
+            let text = match message.get("text").and_then(Value::as_str) {
+                Some(text) if !text.trim().is_empty() => text.to_string(),
+                _ => message.get("content")?.as_array()?
+                    .iter()
+                    .filter(|block| block.get("type").and_then(Value::as_str) == Some("text"))
+                    .filter_map(|block| block.get("text").and_then(Value::as_str))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            };
+            if text.trim().is_empty() {
+                return None;
+            }
+            Some(ImportedMessage {
+                role,
+                content: text,
+                model: None,
+                created_at: rfc3339(message.get("created_at")).unwrap_or(created_at),
+            })
+        })
+        .collect();
+
+    Some(ImportedConversation {
+        source_id,
+        title: item.get("name").and_then(Value::as_str).filter(|name| !name.is_empty()).map(str::to_string),
+        created_at,
+        updated_at: rfc3339(item.get("updated_at")).unwrap_or(created_at),
+        messages,
+    })
+}
+
+fn seconds(value: Option<&Value>) -> Option<i64> {
+    value?.as_f64().map(|secs| secs as i64)
+}
+
+fn rfc3339(value: Option<&Value>) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(value?.as_str()?).ok().map(|d| d.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chatgpt_follows_current_branch() {
+        let export = serde_json::json!([{
+            "id": "conv-1",
+            "title": "Rust lifetimes",
+            "create_time": 1700000000.5,
+            "update_time": 1700000300.0,
+            "current_node": "c",
+            "mapping": {
+                "root": { "message": null, "parent": null },
+                "sys": { "parent": "root", "message": {
+                    "author": { "role": "system" },
+                    "content": { "content_type": "text", "parts": [""] } } },
+                "a": { "parent": "sys", "message": {
+                    "author": { "role": "user" }, "create_time": 1700000100.0,
+                    "content": { "content_type": "text", "parts": ["What is 'a?"] } } },
+                "b": { "parent": "a", "message": {
+                    "author": { "role": "assistant" }, "metadata": { "model_slug": "gpt-4" },
+                    "content": { "content_type": "text", "parts": ["A discarded answer"] } } },
+                "c": { "parent": "a", "message": {
+                    "author": { "role": "assistant" }, "create_time": 1700000200.0, "metadata": { "model_slug": "gpt-4o" },
+                    "content": { "content_type": "text", "parts": ["A lifetime parameter"] } } }
+            }
+        }]);
+        let (source, conversations) = parse_export(export.to_string().as_bytes()).unwrap();
+
+        assert_eq!(source, ExportSource::ChatGpt);
+        let conversation = &conversations[0];
+        assert_eq!(conversation.source_id, "conv-1");
+        assert_eq!(conversation.created_at, 1700000000);
+        assert_eq!(conversation.messages.len(), 2);
+        assert_eq!(conversation.messages[1].content, "A lifetime parameter");
+        assert_eq!(conversation.messages[1].model.as_deref(), Some("gpt-4o"));
+        assert_eq!(conversation.messages[1].created_at, 1700000200);
+    }
+
+    #[test]
+    fn test_parse_chatgpt_rejects_parent_cycles() {
+        let message = |text: &str| serde_json::json!({
+            "author": { "role": "user" }, "content": { "content_type": "text", "parts": [text] } });
+        let export = serde_json::json!([
+            { "id": "looped", "current_node": "a", "mapping": {
+                "a": { "parent": "b", "message": message("one") },
+                "b": { "parent": "a", "message": message("two") } } },
+            { "id": "fine", "current_node": "a", "mapping": {
+                "a": { "parent": null, "message": message("hello") } } }
+        ]);
+        let (_, conversations) = parse_export(export.to_string().as_bytes()).unwrap();
+        assert_eq!(conversations.len(), 1);
+        assert_eq!(conversations[0].source_id, "fine");
+    }
+
+    #[test]
+    fn test_parse_claude_export() {
+        let export = serde_json::json!([{
+            "uuid": "claude-1",
+            "name": "",
+            "created_at": "2024-03-01T10:00:00.000000Z",
+            "updated_at": "2024-03-01T10:05:00.000000Z",
+            "chat_messages": [
+                { "sender": "human", "text": "Hello", "created_at": "2024-03-01T10:00:00Z" },
+                { "sender": "assistant", "text": "", "created_at": "2024-03-01T10:00:05Z",
+                  "content": [{ "type": "text", "text": "Hi there" }] }
+            ]
+        }]);
+        let (source, conversations) = parse_export(export.to_string().as_bytes()).unwrap();
+
+        assert_eq!(source, ExportSource::Claude);
+        let conversation = &conversations[0];
+        assert!(conversation.title.is_none());
+        assert_eq!(conversation.messages[1].role, ChatRole::Assistant);
+        assert_eq!(conversation.messages[1].content, "Hi there");
+        assert_eq!(conversation.messages[1].created_at - conversation.created_at, 5);
+        assert!(conversation.to_markdown(source).contains("## Claude\n\nHi there"));
+    }
+}
@@ -1,6 +1,7 @@
 // src/models/config.rs
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::models::tts::VoiceTier;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -10,8 +11,11 @@ pub struct Config {
     pub models: Option<Vec<String>>,
     pub local_models: Option<Vec<String>>,
     pub system_prompts: Option<HashMap<String, String>>,
+    pub model_aliases: Option<HashMap<String, String>>,       // e.g. "fast" -> "llama3:8b"
+    pub model_profiles: Option<HashMap<String, ModelProfile>>,
+    pub vision_models: Option<Vec<String>>,  // Extra models that accept image input
     
-    // API Keys
+    // API Keys: a value of "secret:<name>" refers to the encrypted secrets store
     pub openai_key: Option<String>,
     pub openai_api_key: Option<String>,  // Alias for compatibility
     pub anthropic_key: Option<String>,
@@ -21,6 +25,8 @@ pub struct Config {
     pub tts_provider: Option<String>,
     pub stt_provider: Option<String>,
     pub ollama_base_url: Option<String>,
+    pub mock_provider: Option<bool>, // Scripted `mock:` models; off unless set to true
+    pub mock_fixtures_dir: Option<String>, // Fixture files for `mock:fixture:<name>` models
     
     // Voice Configuration
     pub elevenlabs_voice_id: Option<String>,
@@ -36,6 +42,138 @@ pub struct Config {
     
     // Proxy Providers Configuration
     pub proxy_providers: Option<Vec<ProxyProvider>>,
+    
+    // Request Queue Configuration
+    pub concurrency: Option<ConcurrencyConfig>,
+    
+    // Conversation History Configuration
+    pub summarization: Option<SummarizationConfig>,
+    
+    // Audit Log Configuration
+    pub audit: Option<AuditConfig>,
+    
+    // Rate Limit Configuration
+    pub rate_limits: Option<RateLimitConfig>,
+    
+    // Vault Passphrase Configuration
+    pub passphrase: Option<PassphraseConfig>,
+}
+
+/// Rolling summaries for long chats. Once a conversation's estimated size passes
+/// `token_threshold`, all but the `keep_recent` latest messages are folded into a summary.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SummarizationConfig {
+    pub model: Option<String>, // Defaults to the conversation's own model
+    pub token_threshold: Option<usize>,
+    pub keep_recent: Option<usize>,
+}
+
+impl SummarizationConfig {
+    // Leaves headroom in a 4k-token local model context
+    const DEFAULT_TOKEN_THRESHOLD: usize = 3000;
+    const DEFAULT_KEEP_RECENT: usize = 6;
+
+    pub fn token_threshold(&self) -> usize {
+        self.token_threshold.unwrap_or(Self::DEFAULT_TOKEN_THRESHOLD)
+    }
+
+    pub fn keep_recent(&self) -> usize {
+        self.keep_recent.unwrap_or(Self::DEFAULT_KEEP_RECENT)
+    }
+}
+
+/// The provider call log. Matches of `redact_patterns` (regexes) and configured API
+/// keys are masked before anything is written, as is text taken from Private-scope notes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditConfig {
+    pub enabled: Option<bool>, // On unless set to false
+    pub redact_patterns: Option<Vec<String>>,
+    pub redact_private_notes: Option<bool>, // On unless set to false
+}
+
+impl AuditConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    pub fn redact_private_notes(&self) -> bool {
+        self.redact_private_notes.unwrap_or(true)
+    }
+}
+
+/// The vault unlock passphrase. A passphrase stored as bcrypt by older versions
+/// is rehashed with argon2 on the next successful unlock unless `upgrade_hash` is false.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PassphraseConfig {
+    pub upgrade_hash: Option<bool>,
+}
+
+impl PassphraseConfig {
+    pub fn upgrade_hash(&self) -> bool {
+        self.upgrade_hash.unwrap_or(true)
+    }
+}
+
+/// Token-bucket request limits. `limits` maps a route class ("auth", "llm",
+/// "voice" or "default") to limits per voice tier; a tier without an entry
+/// uses the class's "basic" entry, then the built-in default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub enabled: Option<bool>, // On unless set to false
+    pub limits: Option<HashMap<String, HashMap<VoiceTier, RateLimit>>>,
+}
+
+impl RateLimitConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    pub fn limit(&self, class: &str, tier: VoiceTier) -> Option<RateLimit> {
+        let tiers = self.limits.as_ref()?.get(class)?;
+        tiers.get(&tier).or_else(|| tiers.get(&VoiceTier::Basic)).copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub per_minute: u32, // sustained rate
+    pub burst: u32,      // bucket size
+}
+
+/// Maximum simultaneous calls per provider ("ollama", "openai", ...) and per model
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConcurrencyConfig {
+    pub provider_limits: Option<HashMap<String, usize>>,
+    pub model_limits: Option<HashMap<String, usize>>,
+}
+
+impl ConcurrencyConfig {
+    // A CPU-bound local Ollama only handles one generation at a time well
+    const DEFAULT_OLLAMA_LIMIT: usize = 1;
+
+    pub fn provider_limit(&self, provider: &str) -> usize {
+        self.provider_limits.as_ref()
+            .and_then(|limits| limits.get(provider).copied())
+            .unwrap_or(if provider == "ollama" { Self::DEFAULT_OLLAMA_LIMIT } else { usize::MAX })
+            .max(1)
+    }
+
+    pub fn model_limit(&self, model: &str) -> usize {
+        self.model_limits.as_ref()
+            .and_then(|limits| limits.get(model).copied())
+            .unwrap_or(usize::MAX)
+            .max(1)
+    }
+}
+
+/// A named bundle of model, system prompt, generation parameters and fallbacks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelProfile {
+    pub model: String,
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub fallbacks: Option<Vec<String>>, // Tried in order when the model call fails
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +184,7 @@ pub struct ProxyProvider {
     pub headers: Option<HashMap<String, String>>,
     pub model_prefix: Option<String>,  // Optional prefix for model names
     pub response_path: Option<String>, // JSONPath to extract response
+    pub supports_images: Option<bool>,  // Forward image attachments to this provider
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +210,9 @@ impl Default for Config {
             models: None,
             local_models: None,
             system_prompts: None,
+            model_aliases: None,
+            model_profiles: None,
+            vision_models: None,
             
             openai_key: None,
             openai_api_key: None,
@@ -80,6 +222,8 @@ impl Default for Config {
             tts_provider: None,
             stt_provider: Some("whisper".to_string()),
             ollama_base_url: Some("http://localhost:11434".to_string()),
+            mock_provider: None,
+            mock_fixtures_dir: None,
             
             elevenlabs_voice_id: None,
             openai_voice_id: None,
@@ -94,11 +238,105 @@ impl Default for Config {
             server_port: 3000,
             
             proxy_providers: None,
+            
+            concurrency: None,
+            
+            summarization: None,
+            audit: None,
+            rate_limits: None,
+            passphrase: None,
         }
     }
 }
 
+/// Marks a key field as a reference into the secrets store rather than the key itself
+pub const SECRET_REF_PREFIX: &str = "secret:";
+
 impl Config {
+    // Aliases and profiles may point at each other; cap the chain to avoid cycles
+    const MAX_MODEL_RESOLUTION_DEPTH: usize = 8;
+
+    pub fn model_profile(&self, name: &str) -> Option<&ModelProfile> {
+        self.model_profiles.as_ref().and_then(|profiles| profiles.get(name))
+    }
+
+    /// Follow aliases and profiles down to a concrete provider model name
+    pub fn resolve_model_name<'a>(&'a self, name: &'a str) -> &'a str {
+        let mut resolved = name;
+        for _ in 0..Self::MAX_MODEL_RESOLUTION_DEPTH {
+            if let Some(profile) = self.model_profile(resolved) {
+                resolved = &profile.model;
+            } else if let Some(target) = self.model_aliases.as_ref().and_then(|a| a.get(resolved)) {
+                resolved = target;
+            } else {
+                break;
+            }
+        }
+        resolved
+    }
+
+    /// The first profile reached while resolving `name`, if any
+    pub fn resolve_profile(&self, name: &str) -> Option<&ModelProfile> {
+        let mut resolved = name;
+        for _ in 0..Self::MAX_MODEL_RESOLUTION_DEPTH {
+            if let Some(profile) = self.model_profile(resolved) {
+                return Some(profile);
+            }
+            resolved = self.model_aliases.as_ref()?.get(resolved)?;
+        }
+        None
+    }
+
+    /// Every field that may hold an API key, by the name admin routes use for it
+    fn secret_slots_mut(&mut self) -> Vec<(String, &mut Option<String>)> {
+        let mut slots = vec![
+            ("openai_key".to_string(), &mut self.openai_key),
+            ("openai_api_key".to_string(), &mut self.openai_api_key),
+            ("anthropic_key".to_string(), &mut self.anthropic_key),
+            ("elevenlabs_api_key".to_string(), &mut self.elevenlabs_api_key),
+        ];
+        for provider in self.proxy_providers.iter_mut().flatten() {
+            slots.push((format!("proxy_providers.{}.api_key", provider.name), &mut provider.api_key));
+        }
+        slots
+    }
+
+    /// Swaps secret references for their values and returns which slot
+    /// referred to which secret. A reference to a secret that isn't known
+    /// (or isn't unlocked yet) leaves the slot unset.
+    pub fn resolve_secrets(&mut self, values: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut refs = HashMap::new();
+        for (slot, value) in self.secret_slots_mut() {
+            let Some(name) = value.as_deref().and_then(|v| v.strip_prefix(SECRET_REF_PREFIX)) else {
+                continue;
+            };
+            let name = name.to_string();
+            *value = values.get(&name).cloned();
+            refs.insert(slot, name);
+        }
+        refs
+    }
+
+    /// Puts the references returned by `resolve_secrets` back, so the config
+    /// can be written to disk without any resolved values
+    pub fn restore_secret_refs(&mut self, refs: &HashMap<String, String>) {
+        for (slot, value) in self.secret_slots_mut() {
+            if let Some(name) = refs.get(&slot) {
+                *value = Some(format!("{}{}", SECRET_REF_PREFIX, name));
+            }
+        }
+    }
+
+    /// Names of the fields that may refer to a secret
+    pub fn secret_slots(&self) -> Vec<String> {
+        self.clone().secret_slots_mut().into_iter().map(|(slot, _)| slot).collect()
+    }
+
+    /// Whether `mock:` model names reach the scripted provider; always in tests
+    pub fn mock_enabled(&self) -> bool {
+        cfg!(test) || self.mock_provider.unwrap_or(false)
+    }
+
     pub async fn load() -> anyhow::Result<Self> {
         if tokio::fs::metadata("config.json").await.is_ok() {
             let content = tokio::fs::read_to_string("config.json").await?;
@@ -138,4 +376,74 @@ impl Config {
         tokio::fs::write("config.json", content).await?;
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alias_and_profile_resolution() {
+        let config = Config {
+            model_aliases: Some(HashMap::from([
+                ("fast".to_string(), "llama3:8b".to_string()),
+                ("writer".to_string(), "smart".to_string()),
+                ("loop".to_string(), "loop".to_string()),
+            ])),
+            model_profiles: Some(HashMap::from([(
+                "smart".to_string(),
+                ModelProfile {
+                    model: "claude-3-opus".to_string(),
+                    system_prompt: Some("Be precise.".to_string()),
+                    temperature: Some(0.2),
+                    max_tokens: None,
+                    fallbacks: Some(vec!["fast".to_string()]),
+                },
+            )])),
+            ..Config::default()
+        };
+
+        assert_eq!(config.resolve_model_name("fast"), "llama3:8b");
+        assert_eq!(config.resolve_model_name("writer"), "claude-3-opus");
+        assert_eq!(config.resolve_model_name("gpt-4"), "gpt-4");
+        assert_eq!(config.resolve_model_name("loop"), "loop");
+        assert_eq!(config.resolve_profile("writer").unwrap().temperature, Some(0.2));
+        assert!(config.resolve_profile("fast").is_none());
+    }
+
+    #[test]
+    fn test_secret_refs_resolve_and_restore() {
+        let mut config = Config {
+            openai_key: Some("secret:openai".to_string()),
+            anthropic_key: Some("secret:anthropic".to_string()),
+            elevenlabs_api_key: Some("secret:missing".to_string()),
+            proxy_providers: Some(vec![ProxyProvider {
+                name: "groq".to_string(),
+                endpoint: "https://api.groq.com/openai/v1/chat/completions".to_string(),
+                api_key: Some("gsk-plain".to_string()),
+                headers: None,
+                model_prefix: None,
+                response_path: None,
+                supports_images: None,
+            }]),
+            ..Config::default()
+        };
+        assert!(config.secret_slots().contains(&"proxy_providers.groq.api_key".to_string()));
+
+        let values = HashMap::from([
+            ("openai".to_string(), "sk-open".to_string()),
+            ("anthropic".to_string(), "sk-ant".to_string()),
+        ]);
+        let refs = config.resolve_secrets(&values);
+        assert_eq!(config.openai_key.as_deref(), Some("sk-open"));
+        assert_eq!(config.anthropic_key.as_deref(), Some("sk-ant"));
+        assert_eq!(config.elevenlabs_api_key, None);
+        assert_eq!(refs.len(), 3);
+        assert_eq!(config.proxy_providers.as_ref().unwrap()[0].api_key.as_deref(), Some("gsk-plain"));
+
+        config.restore_secret_refs(&refs);
+        assert_eq!(config.openai_key.as_deref(), Some("secret:openai"));
+        assert_eq!(config.anthropic_key.as_deref(), Some("secret:anthropic"));
+        assert_eq!(config.elevenlabs_api_key.as_deref(), Some("secret:missing"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,85 @@
+// src/models/ip_range.rs
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// An address or CIDR block, e.g. `10.0.0.0/8`, `192.168.1.20` or `fd00::/8`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // Dual-stack sockets report IPv4 clients as ::ffff:a.b.c.d
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match s.trim().split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let network: IpAddr = address.parse().map_err(|_| format!("Invalid IP address: {}", address))?;
+        let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().ok()
+                .filter(|prefix| *prefix <= max_prefix)
+                .ok_or_else(|| format!("Invalid prefix length in {}", s))?,
+            None => max_prefix,
+        };
+        Ok(Self { network, prefix })
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges_match_v4_v6_and_mapped_addresses() {
+        let lan: IpRange = "192.168.1.0/24".parse().unwrap();
+        assert!(lan.contains("192.168.1.77".parse().unwrap()));
+        assert!(lan.contains("::ffff:192.168.1.77".parse().unwrap()));
+        assert!(!lan.contains("192.168.2.1".parse().unwrap()));
+
+        let host: IpRange = "10.0.0.5".parse().unwrap();
+        assert_eq!(host.to_string(), "10.0.0.5/32");
+        assert!(!host.contains("10.0.0.6".parse().unwrap()));
+
+        let anywhere: IpRange = "0.0.0.0/0".parse().unwrap();
+        assert!(anywhere.contains("8.8.8.8".parse().unwrap()));
+
+        let ula: IpRange = "fd00::/8".parse().unwrap();
+        assert!(ula.contains("fd12:3456::1".parse().unwrap()));
+        assert!(!ula.contains("10.0.0.1".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("not-an-ip".parse::<IpRange>().is_err());
+    }
+}
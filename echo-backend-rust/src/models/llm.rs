@@ -10,6 +10,120 @@ pub enum LLMProvider {
     Proxy,  // For external microservices
 }
 
+/// Per-call generation settings, usually taken from a model profile
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerationParams {
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+impl From<&crate::models::config::ModelProfile> for GenerationParams {
+    fn from(profile: &crate::models::config::ModelProfile) -> Self {
+        Self {
+            system_prompt: profile.system_prompt.clone(),
+            temperature: profile.temperature,
+            max_tokens: profile.max_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+impl ChatRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "user" => Some(ChatRole::User),
+            "assistant" => Some(ChatRole::Assistant),
+            _ => None,
+        }
+    }
+}
+
+/// One turn of a multi-turn chat; system prompts travel in GenerationParams
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self { role: ChatRole::User, content: content.into() }
+    }
+}
+
+/// What went over the wire for one provider call, kept for the audit log
+#[derive(Debug, Clone, Default)]
+pub struct ProviderExchange {
+    pub payload: serde_json::Value,          // the request body as sent
+    pub response: Option<serde_json::Value>, // the raw reply, or the error text
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+}
+
+/// An image sent alongside a prompt to a vision-capable model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageAttachment {
+    pub media_type: String,
+    pub data: String, // base64, without a data: URL prefix
+}
+
+impl ImageAttachment {
+    /// Encode raw image bytes; returns None if they aren't a supported image format
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        use base64::Engine;
+
+        Some(Self {
+            media_type: sniff_image_type(bytes)?.to_string(),
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        })
+    }
+
+    /// Accept plain base64 or a `data:image/...;base64,` URL
+    pub fn from_base64(encoded: &str) -> Option<Self> {
+        use base64::Engine;
+
+        let encoded = match encoded.split_once(";base64,") {
+            Some((prefix, data)) if prefix.starts_with("data:") => data,
+            _ => encoded,
+        };
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+}
+
+// The formats every vision provider accepts, identified by magic number
+fn sniff_image_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
 pub struct LLMModule {
     provider: LLMProvider,
 }
@@ -0,0 +1,294 @@
+// src/models/mock_llm.rs
+//
+// Scripted LLM provider for offline development and tests. Models are
+// addressed as `mock:<script>[;option=value...]`, for example:
+//
+//   mock:echo                      - reply with the prompt
+//   mock:reply:Hello there         - reply with fixed text
+//   mock:fixture:greeting.txt      - reply with a file from the fixtures dir
+//                                    (a bare file name; subpaths are refused)
+//   mock:error:429                 - fail like a rate-limited API
+//   mock:error:timeout;latency=50  - wait, then fail with a timeout
+//   mock:error:malformed           - fail while parsing a broken JSON body
+//   mock:echo;latency=200;stream=20
+//
+// `latency` delays the first token; `stream` splits the reply into word
+// chunks delivered `stream` milliseconds apart. The provider is off unless
+// `mock_provider` is set in config.json (it is always on in tests). A
+// fixtures dir inside the vault follows its scopes, so fixtures under
+// Private need `vault.read.private`.
+use futures::Stream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MockError {
+    #[error("invalid mock script: {0}")]
+    InvalidScript(String),
+    #[error("429 Too Many Requests (mock)")]
+    RateLimited,
+    #[error("request timed out (mock)")]
+    Timeout,
+    #[error("malformed JSON in response (mock): {0}")]
+    MalformedJson(#[from] serde_json::Error),
+    #[error("HTTP {0} (mock)")]
+    Status(u16),
+    #[error("failed to load fixture {0}: {1}")]
+    Fixture(PathBuf, std::io::Error),
+    #[error("fixture {0} is outside the fixtures dir")]
+    FixtureOutsideDir(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockBehavior {
+    Echo,
+    Reply(String),
+    Fixture(String),
+    Fail(MockFailure),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MockFailure {
+    RateLimited,
+    Timeout,
+    MalformedJson,
+    Status(u16),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockScript {
+    pub behavior: MockBehavior,
+    pub latency_ms: u64,
+    pub stream_delay_ms: Option<u64>,
+}
+
+impl MockScript {
+    /// Parse the part of a model name after the `mock:` prefix
+    pub fn parse(script: &str) -> Result<Self, MockError> {
+        let mut parts = script.split(';');
+        let head = parts.next().unwrap_or("").trim();
+
+        let behavior = match head.split_once(':') {
+            None if head == "echo" || head.is_empty() => MockBehavior::Echo,
+            Some(("reply", text)) => MockBehavior::Reply(text.to_string()),
+            Some(("fixture", name)) if is_fixture_name(name) => MockBehavior::Fixture(name.to_string()),
+            Some(("error", kind)) => MockBehavior::Fail(match kind {
+                "429" | "rate_limit" => MockFailure::RateLimited,
+                "timeout" => MockFailure::Timeout,
+                "malformed" | "malformed_json" => MockFailure::MalformedJson,
+                code => MockFailure::Status(
+                    code.parse()
+                        .map_err(|_| MockError::InvalidScript(format!("unknown error kind '{}'", code)))?,
+                ),
+            }),
+            _ => return Err(MockError::InvalidScript(head.to_string())),
+        };
+
+        let mut mock = Self {
+            behavior,
+            latency_ms: 0,
+            stream_delay_ms: None,
+        };
+
+        for option in parts {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| MockError::InvalidScript(format!("option '{}' has no value", option)))?;
+            let value: u64 = value
+                .trim()
+                .parse()
+                .map_err(|_| MockError::InvalidScript(format!("option '{}' is not a number", key)))?;
+
+            match key.trim() {
+                "latency" => mock.latency_ms = value,
+                "stream" => mock.stream_delay_ms = Some(value),
+                other => return Err(MockError::InvalidScript(format!("unknown option '{}'", other))),
+            }
+        }
+
+        Ok(mock)
+    }
+
+    /// Stream the scripted reply chunk by chunk, honouring latency settings
+    pub fn stream(
+        &self,
+        prompt: &str,
+        fixtures_dir: &Path,
+    ) -> impl Stream<Item = Result<String, MockError>> + Send + 'static {
+        let mock = self.clone();
+        let prompt = prompt.to_string();
+        let fixtures_dir = fixtures_dir.to_path_buf();
+
+        async_stream::stream! {
+            if mock.latency_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(mock.latency_ms)).await;
+            }
+
+            let text = match &mock.behavior {
+                MockBehavior::Echo => prompt,
+                MockBehavior::Reply(text) => text.clone(),
+                MockBehavior::Fixture(name) => {
+                    let path = match fixture_path(&fixtures_dir, name).await {
+                        Ok(path) => path,
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    };
+                    match tokio::fs::read_to_string(&path).await {
+                        Ok(content) => content,
+                        Err(e) => {
+                            yield Err(MockError::Fixture(path, e));
+                            return;
+                        }
+                    }
+                }
+                MockBehavior::Fail(failure) => {
+                    yield Err(failure.to_error());
+                    return;
+                }
+            };
+
+            match mock.stream_delay_ms {
+                Some(delay) => {
+                    for (i, chunk) in text.split_inclusive(' ').enumerate() {
+                        if i > 0 {
+                            tokio::time::sleep(Duration::from_millis(delay)).await;
+                        }
+                        yield Ok(chunk.to_string());
+                    }
+                }
+                None => yield Ok(text),
+            }
+        }
+    }
+
+    /// Produce the complete scripted reply
+    pub async fn respond(&self, prompt: &str, fixtures_dir: &Path) -> Result<String, MockError> {
+        use futures::StreamExt;
+
+        let mut stream = Box::pin(self.stream(prompt, fixtures_dir));
+        let mut response = String::new();
+        while let Some(chunk) = stream.next().await {
+            response.push_str(&chunk?);
+        }
+        Ok(response)
+    }
+}
+
+// Same rule as vault_file_path, and a single component so only files
+// directly in the fixtures dir can be named
+fn is_fixture_name(name: &str) -> bool {
+    let path = Path::new(name);
+    !name.is_empty()
+        && !name.contains(['/', '\\'])
+        && !path.is_absolute()
+        && path.components().count() == 1
+        && matches!(path.components().next(), Some(std::path::Component::Normal(_)))
+}
+
+// Resolves symlinks so a link in the fixtures dir can't point elsewhere
+async fn fixture_path(fixtures_dir: &Path, name: &str) -> Result<PathBuf, MockError> {
+    let path = fixtures_dir.join(name);
+    let dir = tokio::fs::canonicalize(fixtures_dir).await
+        .map_err(|e| MockError::Fixture(fixtures_dir.to_path_buf(), e))?;
+    let resolved = tokio::fs::canonicalize(&path).await
+        .map_err(|e| MockError::Fixture(path.clone(), e))?;
+    if !resolved.starts_with(&dir) {
+        return Err(MockError::FixtureOutsideDir(path));
+    }
+    Ok(resolved)
+}
+
+impl MockFailure {
+    fn to_error(self) -> MockError {
+        match self {
+            MockFailure::RateLimited => MockError::RateLimited,
+            MockFailure::Timeout => MockError::Timeout,
+            MockFailure::MalformedJson => {
+                match serde_json::from_str::<serde_json::Value>(r#"{"choices": [{"message": "#) {
+                    Err(e) => MockError::MalformedJson(e),
+                    Ok(_) => unreachable!("mock payload is intentionally truncated"),
+                }
+            }
+            MockFailure::Status(code) => MockError::Status(code),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_parse_scripts() {
+        assert_eq!(MockScript::parse("echo").unwrap().behavior, MockBehavior::Echo);
+        assert_eq!(
+            MockScript::parse("reply:hi there").unwrap().behavior,
+            MockBehavior::Reply("hi there".to_string())
+        );
+        assert_eq!(
+            MockScript::parse("error:429").unwrap().behavior,
+            MockBehavior::Fail(MockFailure::RateLimited)
+        );
+        assert_eq!(
+            MockScript::parse("error:503").unwrap().behavior,
+            MockBehavior::Fail(MockFailure::Status(503))
+        );
+
+        let mock = MockScript::parse("echo;latency=25;stream=5").unwrap();
+        assert_eq!(mock.latency_ms, 25);
+        assert_eq!(mock.stream_delay_ms, Some(5));
+
+        assert!(MockScript::parse("bogus").is_err());
+        assert!(MockScript::parse("echo;speed=3").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_echo_and_streaming() {
+        let dir = Path::new(".");
+        let mock = MockScript::parse("echo;stream=1").unwrap();
+        let chunks: Vec<_> = mock.stream("one two three", dir).collect().await;
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(mock.respond("one two three", dir).await.unwrap(), "one two three");
+    }
+
+    #[tokio::test]
+    async fn test_injected_errors() {
+        let dir = Path::new(".");
+        let err = MockScript::parse("error:429").unwrap().respond("x", dir).await.unwrap_err();
+        assert!(matches!(err, MockError::RateLimited));
+
+        let err = MockScript::parse("error:malformed").unwrap().respond("x", dir).await.unwrap_err();
+        assert!(matches!(err, MockError::MalformedJson(_)));
+    }
+
+    #[tokio::test]
+    async fn test_fixture_loading() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("answer.txt"), "forty-two").unwrap();
+
+        let mock = MockScript::parse("fixture:answer.txt").unwrap();
+        assert_eq!(mock.respond("ignored", temp_dir.path()).await.unwrap(), "forty-two");
+
+        let missing = MockScript::parse("fixture:missing.txt").unwrap();
+        assert!(missing.respond("ignored", temp_dir.path()).await.is_err());
+
+        for name in ["../../config.json", "/etc/passwd", "sub/answer.txt", "..", "C:\\Windows\\win.ini"] {
+            assert!(MockScript::parse(&format!("fixture:{}", name)).is_err(), "{} was accepted", name);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_fixture_symlinks_stay_in_dir() {
+        let outside = tempfile::TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "private").unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret.txt"), temp_dir.path().join("link.txt")).unwrap();
+
+        let err = MockScript::parse("fixture:link.txt").unwrap().respond("x", temp_dir.path()).await.unwrap_err();
+        assert!(matches!(err, MockError::FixtureOutsideDir(_)));
+    }
+}
@@ -2,6 +2,12 @@ pub mod config;
 pub mod stt;
 pub mod tts;
 pub mod llm;
+pub mod chat_import;
+pub mod mock_llm;
+pub mod ollama;
+pub mod ip_range;
+pub mod ratings;
+pub mod redaction;
 pub mod whisper;
 
 
@@ -0,0 +1,234 @@
+// src/models/ollama.rs
+use anyhow::{anyhow, Result};
+use futures::{Stream, StreamExt};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::models::config::Config;
+use crate::models::llm::{ChatMessage, GenerationParams, ImageAttachment, ProviderExchange};
+
+const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// One progress line from `/api/pull`; layer downloads carry digest and byte counts
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PullProgress {
+    #[serde(default)]
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Client for the Ollama HTTP API: generation and model management
+pub struct OllamaClient {
+    client: Client,
+    base_url: String,
+}
+
+impl OllamaClient {
+    pub fn new(config: &Config) -> Self {
+        let base_url = config.ollama_base_url.as_deref().unwrap_or(DEFAULT_OLLAMA_URL);
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Run a single non-streaming chat completion through `/api/chat`
+    pub async fn chat(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        params: &GenerationParams,
+        images: &[ImageAttachment],
+        exchange: &mut ProviderExchange,
+    ) -> Result<String> {
+        let mut options = serde_json::Map::new();
+        if let Some(temperature) = params.temperature {
+            options.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            options.insert("num_predict".to_string(), json!(max_tokens));
+        }
+
+        let mut turns = Vec::new();
+        if let Some(system) = &params.system_prompt {
+            turns.push(json!({ "role": "system", "content": system }));
+        }
+        for (i, message) in messages.iter().enumerate() {
+            let mut turn = json!({ "role": message.role, "content": message.content });
+            // Images belong to the latest turn
+            if i + 1 == messages.len() && !images.is_empty() {
+                turn["images"] = json!(images.iter().map(|image| &image.data).collect::<Vec<_>>());
+            }
+            turns.push(turn);
+        }
+
+        let body = json!({
+            "model": model,
+            "messages": turns,
+            "stream": false,
+            "options": options,
+        });
+        exchange.payload = body.clone();
+
+        let response = self.client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            exchange.response = Some(json!(error_text));
+            return Err(anyhow!("Ollama failed ({}): {}", status, error_text));
+        }
+
+        let json: serde_json::Value = response.json().await?;
+        exchange.input_tokens = json["prompt_eval_count"].as_i64();
+        exchange.output_tokens = json["eval_count"].as_i64();
+        let text = json["message"]["content"].as_str().unwrap_or_default().trim().to_string();
+        exchange.response = Some(json);
+        Ok(text)
+    }
+
+    pub async fn has_model(&self, model: &str) -> Result<bool> {
+        let response = self.client
+            .post(format!("{}/api/show", self.base_url))
+            .json(&json!({ "name": model }))
+            .send()
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => Err(anyhow!("Ollama show failed ({}): {}", status, response.text().await?)),
+        }
+    }
+
+    /// Pull a model, yielding progress as Ollama reports it
+    pub async fn pull_stream(&self, model: &str) -> Result<impl Stream<Item = Result<PullProgress>>> {
+        let response = self.client
+            .post(format!("{}/api/pull", self.base_url))
+            .json(&json!({ "name": model, "stream": true }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(anyhow!("Ollama pull failed ({}): {}", status, response.text().await?));
+        }
+
+        let mut bytes = response.bytes_stream();
+        Ok(async_stream::stream! {
+            let mut buffer = String::new();
+            while let Some(chunk) = bytes.next().await {
+                match chunk {
+                    Ok(chunk) => {
+                        buffer.push_str(&String::from_utf8_lossy(&chunk));
+                        for progress in drain_progress_lines(&mut buffer) {
+                            yield progress;
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(anyhow!(e));
+                        return;
+                    }
+                }
+            }
+            buffer.push('\n');
+            for progress in drain_progress_lines(&mut buffer) {
+                yield progress;
+            }
+        })
+    }
+
+    /// Pull a model and wait for it to finish
+    pub async fn pull(&self, model: &str) -> Result<()> {
+        let mut progress = Box::pin(self.pull_stream(model).await?);
+        while let Some(update) = progress.next().await {
+            if let Some(error) = update?.error {
+                return Err(anyhow!("Ollama pull failed: {}", error));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns false if the model does not exist
+    pub async fn delete(&self, model: &str) -> Result<bool> {
+        let response = self.client
+            .delete(format!("{}/api/delete", self.base_url))
+            .json(&json!({ "name": model }))
+            .send()
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => Err(anyhow!("Ollama delete failed ({}): {}", status, response.text().await?)),
+        }
+    }
+
+    /// Returns false if the source model does not exist
+    pub async fn copy(&self, source: &str, destination: &str) -> Result<bool> {
+        let response = self.client
+            .post(format!("{}/api/copy", self.base_url))
+            .json(&json!({ "source": source, "destination": destination }))
+            .send()
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => Err(anyhow!("Ollama copy failed ({}): {}", status, response.text().await?)),
+        }
+    }
+}
+
+// Parse every complete newline-delimited JSON line, leaving any partial line buffered
+fn drain_progress_lines(buffer: &mut String) -> Vec<Result<PullProgress>> {
+    let Some(last_newline) = buffer.rfind('\n') else {
+        return Vec::new();
+    };
+
+    let complete: String = buffer.drain(..=last_newline).collect();
+    complete
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_str::<PullProgress>(line).map_err(|e| anyhow!("Bad pull progress line: {}", e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_progress_lines() {
+        let mut buffer = String::from(
+            "{\"status\":\"pulling manifest\"}\n{\"status\":\"pulling abc\",\"digest\":\"sha256:abc\",\"total\":100,\"completed\":40}\n{\"status\":\"verif",
+        );
+
+        let progress: Vec<_> = drain_progress_lines(&mut buffer)
+            .into_iter()
+            .map(|p| p.unwrap())
+            .collect();
+        assert_eq!(progress.len(), 2);
+        assert_eq!(progress[1].digest.as_deref(), Some("sha256:abc"));
+        assert_eq!(progress[1].completed, Some(40));
+        assert_eq!(buffer, "{\"status\":\"verif");
+
+        buffer.push_str("ying sha256 digest\"}\n");
+        let progress = drain_progress_lines(&mut buffer);
+        assert_eq!(progress[0].as_ref().unwrap().status, "verifying sha256 digest");
+        assert!(buffer.is_empty());
+    }
+}
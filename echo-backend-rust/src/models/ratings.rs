@@ -0,0 +1,131 @@
+// src/models/ratings.rs
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const INITIAL_RATING: f64 = 1500.0;
+const K_FACTOR: f64 = 32.0;
+
+/// The result of comparing two answers side by side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteOutcome {
+    A,
+    B,
+    Tie,
+}
+
+impl VoteOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VoteOutcome::A => "a",
+            VoteOutcome::B => "b",
+            VoteOutcome::Tie => "tie",
+        }
+    }
+
+    pub fn parse(outcome: &str) -> Option<Self> {
+        match outcome {
+            "a" => Some(VoteOutcome::A),
+            "b" => Some(VoteOutcome::B),
+            "tie" => Some(VoteOutcome::Tie),
+            _ => None,
+        }
+    }
+
+    // Points scored by model A
+    fn score(&self) -> f64 {
+        match self {
+            VoteOutcome::A => 1.0,
+            VoteOutcome::B => 0.0,
+            VoteOutcome::Tie => 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelRating {
+    pub model: String,
+    pub rating: f64,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    pub games: u32,
+}
+
+impl ModelRating {
+    fn new(model: &str) -> Self {
+        Self { model: model.to_string(), rating: INITIAL_RATING, wins: 0, losses: 0, ties: 0, games: 0 }
+    }
+
+    fn record(&mut self, score: f64) {
+        self.games += 1;
+        if score == 1.0 {
+            self.wins += 1;
+        } else if score == 0.0 {
+            self.losses += 1;
+        } else {
+            self.ties += 1;
+        }
+    }
+}
+
+/// Replay `(model_a, model_b, outcome)` votes in order and return Elo ratings, best first
+pub fn elo_ratings<'a>(votes: impl IntoIterator<Item = (&'a str, &'a str, VoteOutcome)>) -> Vec<ModelRating> {
+    let mut ratings: HashMap<&str, ModelRating> = HashMap::new();
+
+    for (model_a, model_b, outcome) in votes {
+        if model_a == model_b {
+            continue;
+        }
+        let rating_a = ratings.entry(model_a).or_insert_with(|| ModelRating::new(model_a)).rating;
+        let rating_b = ratings.entry(model_b).or_insert_with(|| ModelRating::new(model_b)).rating;
+
+        let expected_a = 1.0 / (1.0 + 10f64.powf((rating_b - rating_a) / 400.0));
+        let score_a = outcome.score();
+        let delta = K_FACTOR * (score_a - expected_a);
+
+        if let Some(a) = ratings.get_mut(model_a) {
+            a.rating += delta;
+            a.record(score_a);
+        }
+        if let Some(b) = ratings.get_mut(model_b) {
+            b.rating -= delta;
+            b.record(1.0 - score_a);
+        }
+    }
+
+    let mut ratings: Vec<ModelRating> = ratings.into_values().collect();
+    for rating in &mut ratings {
+        rating.rating = rating.rating.round();
+    }
+    ratings.sort_by(|a, b| b.rating.total_cmp(&a.rating).then_with(|| a.model.cmp(&b.model)));
+    ratings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elo_ratings() {
+        let votes = [
+            ("gpt-4", "llama3", VoteOutcome::A),
+            ("gpt-4", "llama3", VoteOutcome::A),
+            ("llama3", "mistral", VoteOutcome::Tie),
+            ("mistral", "mistral", VoteOutcome::A),
+        ];
+        let ratings = elo_ratings(votes);
+
+        assert_eq!(ratings.len(), 3);
+        assert_eq!(ratings[0].model, "gpt-4");
+        assert_eq!((ratings[0].wins, ratings[0].games), (2, 2));
+        assert!(ratings[0].rating > INITIAL_RATING);
+
+        // Total rating is conserved because every exchange is zero-sum
+        let total: f64 = ratings.iter().map(|r| r.rating).sum();
+        assert!((total - 3.0 * INITIAL_RATING).abs() <= 1.0);
+
+        let llama = ratings.iter().find(|r| r.model == "llama3").unwrap();
+        assert_eq!((llama.losses, llama.ties), (2, 1));
+    }
+}
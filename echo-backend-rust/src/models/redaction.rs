@@ -0,0 +1,128 @@
+// src/models/redaction.rs
+use regex::Regex;
+use serde_json::Value;
+
+use crate::models::config::Config;
+
+const REDACTED: &str = "[REDACTED]";
+const REDACTED_NOTE: &str = "[REDACTED: private note]";
+// Shorter values are too likely to collide with ordinary text
+const MIN_SECRET_LEN: usize = 8;
+// Key shapes worth masking even when they aren't ours, e.g. pasted into a prompt
+const BUILTIN_PATTERNS: &[&str] = &[
+    r"sk-[A-Za-z0-9_\-]{20,}",
+    r"(?i)bearer\s+[A-Za-z0-9._\-]{16,}",
+];
+
+/// Masks secrets and private note text in audit log records
+pub struct Redactor {
+    secrets: Vec<String>,
+    patterns: Vec<Regex>,
+    private_texts: Vec<String>,
+}
+
+impl Redactor {
+    /// `private_texts` are excerpts of Private-scope notes that went into the prompt
+    pub fn new(config: &Config, private_texts: Vec<String>) -> Self {
+        let audit = config.audit.clone().unwrap_or_default();
+
+        let mut secrets: Vec<String> = [
+            &config.openai_key,
+            &config.openai_api_key,
+            &config.anthropic_key,
+            &config.elevenlabs_api_key,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+        for provider in config.proxy_providers.iter().flatten() {
+            secrets.extend(provider.api_key.iter().cloned());
+            secrets.extend(provider.headers.iter().flat_map(|headers| headers.values().cloned()));
+        }
+        secrets.retain(|secret| secret.len() >= MIN_SECRET_LEN);
+
+        let patterns = BUILTIN_PATTERNS.iter()
+            .map(|pattern| pattern.to_string())
+            .chain(audit.redact_patterns.iter().flatten().cloned())
+            .filter_map(|pattern| match Regex::new(&pattern) {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid audit redaction pattern '{}': {}", pattern, e);
+                    None
+                },
+            })
+            .collect();
+
+        let mut private_texts = if audit.redact_private_notes() { private_texts } else { Vec::new() };
+        private_texts.retain(|text| !text.trim().is_empty());
+        // Longest first, so an excerpt is masked whole before any text it contains
+        private_texts.sort_by_key(|text| std::cmp::Reverse(text.len()));
+
+        Self { secrets, patterns, private_texts }
+    }
+
+    /// Returns the masked text and whether anything was masked
+    pub fn redact_str(&self, text: &str) -> (String, bool) {
+        let mut result = text.to_string();
+        for private in &self.private_texts {
+            result = result.replace(private.as_str(), REDACTED_NOTE);
+        }
+        for secret in &self.secrets {
+            result = result.replace(secret.as_str(), REDACTED);
+        }
+        for pattern in &self.patterns {
+            result = pattern.replace_all(&result, REDACTED).into_owned();
+        }
+        let changed = result != text;
+        (result, changed)
+    }
+
+    /// Mask every string inside a JSON value in place; returns whether anything was masked
+    pub fn redact(&self, value: &mut Value) -> bool {
+        match value {
+            Value::String(text) => {
+                let (redacted, changed) = self.redact_str(text);
+                *text = redacted;
+                changed
+            },
+            Value::Array(items) => items.iter_mut().fold(false, |changed, item| self.redact(item) | changed),
+            Value::Object(fields) => fields.values_mut().fold(false, |changed, field| self.redact(field) | changed),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::AuditConfig;
+
+    #[test]
+    fn test_redacts_keys_patterns_and_private_notes() {
+        let config = Config {
+            anthropic_key: Some("ant-secret-123456".to_string()),
+            audit: Some(AuditConfig {
+                redact_patterns: Some(vec![r"\d{3}-\d{2}-\d{4}".to_string(), "(".to_string()]),
+                ..AuditConfig::default()
+            }),
+            ..Config::default()
+        };
+        let redactor = Redactor::new(&config, vec!["My diary entry".to_string()]);
+
+        let mut payload = serde_json::json!({
+            "system": "Notes:\nMy diary entry\nend",
+            "messages": [{ "content": "key ant-secret-123456 and sk-abcdefghijklmnopqrstuvwx, ssn 123-45-6789" }],
+            "temperature": 0.7,
+        });
+        assert!(redactor.redact(&mut payload));
+        assert_eq!(payload["system"], "Notes:\n[REDACTED: private note]\nend");
+        assert_eq!(payload["messages"][0]["content"], "key [REDACTED] and [REDACTED], ssn [REDACTED]");
+
+        let quiet = Redactor::new(
+            &Config { audit: Some(AuditConfig { redact_private_notes: Some(false), ..AuditConfig::default() }), ..Config::default() },
+            vec!["My diary entry".to_string()],
+        );
+        assert_eq!(quiet.redact_str("My diary entry"), ("My diary entry".to_string(), false));
+    }
+}
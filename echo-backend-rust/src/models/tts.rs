@@ -7,7 +7,7 @@ use std::sync::Arc;
 use tokio::sync::mpsc;
 use crate::models::config::Config;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum VoiceTier {
     Basic,
@@ -22,6 +22,7 @@ impl Default for VoiceTier {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VoiceSettings {
     pub voice_id: Option<String>,
     pub tier: VoiceTier,
@@ -239,28 +240,19 @@ impl TTSModule {
         }
     }
 
-    pub async fn synthesize(&self, text: String, voice_id: Option<String>) -> Result<Vec<u8>> {
-        let settings = VoiceSettings {
-            voice_id,
-            ..Default::default()
-        };
-        self.engine.synthesize(&text, &settings).await
+    pub async fn synthesize(&self, text: String, settings: &VoiceSettings) -> Result<Vec<u8>> {
+        self.engine.synthesize(&text, settings).await
     }
 
     pub async fn stream_synthesize(
         &self,
         text: String,
-        voice_id: Option<String>,
+        settings: VoiceSettings,
     ) -> Result<mpsc::Receiver<Result<Vec<u8>>>> {
         let (tx, rx) = mpsc::channel(10);
         let engine = TTSEngine::with_config(Arc::clone(&self.engine.config));
         
         tokio::spawn(async move {
-            let settings = VoiceSettings {
-                voice_id,
-                ..Default::default()
-            };
-            
             match engine.synthesize(&text, &settings).await {
                 Ok(data) => {
                     let _ = tx.send(Ok(data)).await;
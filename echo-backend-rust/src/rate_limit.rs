@@ -0,0 +1,222 @@
+// src/rate_limit.rs - Token-bucket rate limiting per caller and route class
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::AppState;
+use crate::auth::Claims;
+use crate::models::config::RateLimit;
+use crate::models::tts::VoiceTier;
+
+// Buckets that have refilled completely are dropped once there are this many
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Groups of routes that share a limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    Auth,    // password and passphrase checks, keyed by client address
+    Llm,     // provider calls
+    Voice,   // transcription and synthesis
+    Default,
+}
+
+impl RouteClass {
+    pub fn for_path(path: &str) -> Self {
+        match path {
+            "/login" | "/signup" | "/auth/refresh"
+            | "/api/auth/unlock" | "/api/auth/setup" | "/api/auth/passphrase" => RouteClass::Auth,
+            _ if path.starts_with("/llm/") => RouteClass::Llm,
+            _ if path.starts_with("/voice/") => RouteClass::Voice,
+            _ => RouteClass::Default,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteClass::Auth => "auth",
+            RouteClass::Llm => "llm",
+            RouteClass::Voice => "voice",
+            RouteClass::Default => "default",
+        }
+    }
+
+    fn default_limit(&self, tier: VoiceTier) -> RateLimit {
+        let (per_minute, burst) = match (self, tier) {
+            (RouteClass::Auth, _) => (10, 5),
+            (RouteClass::Llm, VoiceTier::Basic) => (30, 10),
+            (RouteClass::Llm, VoiceTier::Pro) => (120, 30),
+            (RouteClass::Llm, VoiceTier::Creator) => (300, 60),
+            (RouteClass::Voice, VoiceTier::Basic) => (10, 5),
+            (RouteClass::Voice, VoiceTier::Pro) => (60, 20),
+            (RouteClass::Voice, VoiceTier::Creator) => (200, 40),
+            (RouteClass::Default, _) => (300, 60),
+        };
+        RateLimit { per_minute, burst }
+    }
+}
+
+/// The quota tier a caller's features put them in
+pub fn tier_for(claims: &Claims) -> VoiceTier {
+    if claims.has_feature("voice-creator") {
+        VoiceTier::Creator
+    } else if claims.has_feature("voice-pro") {
+        VoiceTier::Pro
+    } else {
+        VoiceTier::Basic
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: Duration,       // until the bucket is full again
+    pub retry_after: Duration, // until the next request is allowed; zero when allowed
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<(RouteClass, String), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes one token from the caller's bucket for `class` if there is one
+    pub fn check(&self, class: RouteClass, key: &str, limit: RateLimit, now: Instant) -> Decision {
+        let capacity = limit.burst.max(1) as f64;
+        let per_second = limit.per_minute.max(1) as f64 / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * per_second < capacity);
+        }
+        let bucket = buckets.entry((class, key.to_string()))
+            .or_insert(Bucket { tokens: capacity, updated: now });
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * per_second).min(capacity);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        Decision {
+            allowed,
+            limit: capacity as u32,
+            remaining: bucket.tokens.floor() as u32,
+            reset: Duration::from_secs_f64((capacity - bucket.tokens) / per_second),
+            retry_after: if allowed {
+                Duration::ZERO
+            } else {
+                Duration::from_secs_f64((1.0 - bucket.tokens) / per_second)
+            },
+        }
+    }
+}
+
+/// Middleware that charges each request to its caller: the access token or
+/// user when there is an identity, the client address otherwise.
+pub async fn enforce(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let config = state.runtime_state.read().await.config.rate_limits.clone().unwrap_or_default();
+    if !config.enabled() {
+        return next.run(req).await;
+    }
+
+    let class = RouteClass::for_path(req.uri().path());
+    let claims = req.extensions().get::<Claims>().filter(|_| class != RouteClass::Auth);
+    let (key, tier) = match claims {
+        Some(claims) if claims.is_access_token() => (format!("token:{}", claims.jti), tier_for(claims)),
+        Some(claims) => (format!("user:{}", claims.sub), tier_for(claims)),
+        None => {
+            let ip = req.extensions().get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            (format!("ip:{}", ip), VoiceTier::Basic)
+        }
+    };
+    let limit = config.limit(class.as_str(), tier).unwrap_or_else(|| class.default_limit(tier));
+    let decision = state.rate_limiter.check(class, &key, limit, Instant::now());
+
+    let mut response = if decision.allowed {
+        next.run(req).await
+    } else {
+        let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(serde_json::json!({
+            "error": "Rate limit exceeded",
+            "route_class": class.as_str(),
+        }))).into_response();
+        response.headers_mut().insert("retry-after", ceil_secs(decision.retry_after));
+        response
+    };
+    set_headers(response.headers_mut(), &decision);
+    response
+}
+
+fn set_headers(headers: &mut HeaderMap, decision: &Decision) {
+    headers.insert("ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("ratelimit-reset", ceil_secs(decision.reset));
+}
+
+fn ceil_secs(duration: Duration) -> HeaderValue {
+    HeaderValue::from(duration.as_secs_f64().ceil() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_drain_refill_and_stay_separate() {
+        let limiter = RateLimiter::new();
+        let limit = RateLimit { per_minute: 60, burst: 2 };
+        let start = Instant::now();
+
+        assert!(limiter.check(RouteClass::Llm, "user:a", limit, start).allowed);
+        let second = limiter.check(RouteClass::Llm, "user:a", limit, start);
+        assert_eq!((second.allowed, second.remaining), (true, 0));
+        let denied = limiter.check(RouteClass::Llm, "user:a", limit, start);
+        assert!(!denied.allowed);
+        assert_eq!(denied.retry_after, Duration::from_secs(1));
+
+        // Other callers and other route classes have their own buckets
+        assert!(limiter.check(RouteClass::Llm, "user:b", limit, start).allowed);
+        assert!(limiter.check(RouteClass::Voice, "user:a", limit, start).allowed);
+
+        // One token a second comes back
+        assert!(limiter.check(RouteClass::Llm, "user:a", limit, start + Duration::from_secs(1)).allowed);
+    }
+
+    #[test]
+    fn test_routes_and_tiers_map_to_limits() {
+        assert_eq!(RouteClass::for_path("/login"), RouteClass::Auth);
+        assert_eq!(RouteClass::for_path("/llm/conversation"), RouteClass::Llm);
+        assert_eq!(RouteClass::for_path("/voice/speak"), RouteClass::Voice);
+        assert_eq!(RouteClass::for_path("/messages"), RouteClass::Default);
+
+        let pro = Claims { features: vec!["voice-pro".to_string()], ..Claims::default() };
+        assert_eq!(tier_for(&pro), VoiceTier::Pro);
+        assert_eq!(tier_for(&Claims::default()), VoiceTier::Basic);
+
+        let config: crate::models::config::RateLimitConfig = serde_json::from_value(serde_json::json!({
+            "limits": { "llm": { "basic": { "per_minute": 5, "burst": 1 }, "creator": { "per_minute": 500, "burst": 100 } } }
+        })).unwrap();
+        assert_eq!(config.limit("llm", VoiceTier::Pro), Some(RateLimit { per_minute: 5, burst: 1 }));
+        assert_eq!(config.limit("llm", VoiceTier::Creator).map(|l| l.burst), Some(100));
+        assert_eq!(config.limit("voice", VoiceTier::Basic), None);
+    }
+}
@@ -0,0 +1,180 @@
+// src/routes/access_tokens.rs
+use axum::{
+    extract::{Path, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use crate::AppState;
+use crate::auth::{self, AuthError, Claims};
+use crate::db::access_tokens::{self, NewAccessToken};
+use crate::db::users::{self, User};
+use crate::models::ip_range::IpRange;
+
+const MAX_TOKEN_NAME_CHARS: usize = 100;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    #[serde(default)]
+    pub features: Vec<String>,      // must be a subset of the caller's features
+    pub expires_in_days: Option<i64>, // never expires when absent
+    pub allowed_ips: Option<Vec<String>>, // addresses or CIDR ranges
+}
+
+// GET /auth/tokens
+pub async fn list_tokens(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Response, StatusCode> {
+    let user = token_owner(&state, &claims).await?;
+    let tokens = access_tokens::list_for_user(&state.db, &user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(serde_json::json!({ "tokens": tokens })).into_response())
+}
+
+// POST /auth/tokens
+//
+// The plaintext token is in this response only; it is stored hashed.
+pub async fn create_token(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateTokenRequest>,
+) -> Result<Response, StatusCode> {
+    let user = token_owner(&state, &claims).await?;
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_TOKEN_NAME_CHARS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if !payload.features.iter().all(|feature| claims.has_feature(feature)) {
+        return Ok(AuthError::InsufficientPermissions.into_response());
+    }
+
+    let allowed_ips = match payload.allowed_ips {
+        Some(ranges) => match ranges.iter().map(|range| range.parse::<IpRange>()).collect::<Result<Vec<_>, _>>() {
+            Ok(ranges) => Some(ranges.iter().map(IpRange::to_string).collect()),
+            Err(message) => {
+                return Ok((StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message }))).into_response());
+            },
+        },
+        None => None,
+    };
+    let expires_at = match payload.expires_in_days {
+        Some(days) if days <= 0 => return Err(StatusCode::BAD_REQUEST),
+        Some(days) => Some((chrono::Utc::now() + chrono::Duration::days(days)).timestamp()),
+        None => None,
+    };
+
+    let new = NewAccessToken {
+        name: name.to_string(),
+        features: payload.features,
+        allowed_ips,
+        expires_at,
+    };
+    let (token, plaintext) = access_tokens::create(&state.db, &user.id, new)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::CREATED, Json(serde_json::json!({
+        "token": plaintext,
+        "details": token,
+    }))).into_response())
+}
+
+// DELETE /auth/tokens/:id
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let user = token_owner(&state, &claims).await?;
+    match access_tokens::revoke(&state.db, &user.id, &id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// Tokens belong to user accounts, and are managed from a login rather than
+// from another token
+async fn token_owner(state: &AppState, claims: &Claims) -> Result<User, StatusCode> {
+    if claims.is_access_token() || auth::is_reserved_username(&claims.sub) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    users::find_by_username(&state.db, &claims.sub)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::FORBIDDEN)
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::{delete, get};
+
+    axum::Router::new()
+        .route("/auth/tokens", get(list_tokens).post(create_token))
+        .route("/auth/tokens/:id", delete(revoke_token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Identity;
+    use crate::models::config::Config;
+    use axum::extract::{ConnectInfo, FromRequestParts};
+    use std::net::SocketAddr;
+
+    async fn identify(state: &AppState, token: &str, from: &str) -> Result<Claims, AuthError> {
+        let (mut parts, _) = axum::http::Request::builder()
+            .header("authorization", format!("Bearer {}", token))
+            .body(())
+            .unwrap()
+            .into_parts();
+        parts.extensions.insert(state.jwt_secret.clone());
+        parts.extensions.insert(state.db.clone());
+        parts.extensions.insert(ConnectInfo(from.parse::<SocketAddr>().unwrap()));
+        Identity::from_request_parts(&mut parts, &()).await.map(|Identity(claims)| claims)
+    }
+
+    #[tokio::test]
+    async fn test_access_tokens_are_scoped_and_revocable() {
+        let state = crate::test_app_state(Config::default()).await;
+        let features = vec!["chat".to_string(), "streaming".to_string()];
+        users::create(&state.db, "dana", "pw", &features, false).await.unwrap();
+        let dana = Claims::new("dana".to_string(), false, features);
+
+        let too_broad = CreateTokenRequest { name: "ci".to_string(), features: vec!["admin".to_string()], ..Default::default() };
+        let response = create_token(State(state.clone()), Extension(dana.clone()), Json(too_broad)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let request = CreateTokenRequest {
+            name: "ci".to_string(),
+            features: vec!["chat".to_string()],
+            expires_in_days: Some(30),
+            allowed_ips: Some(vec!["10.1.0.0/16".to_string()]),
+        };
+        let response = create_token(State(state.clone()), Extension(dana.clone()), Json(request)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let created: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let plaintext = created["token"].as_str().unwrap();
+        let id = created["details"]["id"].as_str().unwrap().to_string();
+
+        let claims = identify(&state, plaintext, "10.1.2.3:5000").await.unwrap();
+        assert_eq!(claims.sub, "dana");
+        assert!(claims.has_feature("chat") && !claims.has_feature("streaming"));
+        assert!(matches!(identify(&state, plaintext, "192.168.0.9:5000").await, Err(AuthError::InsufficientPermissions)));
+
+        // Tokens can't mint tokens, and every use is recorded
+        assert_eq!(list_tokens(State(state.clone()), Extension(claims)).await.unwrap_err(), StatusCode::FORBIDDEN);
+        let user = users::find_by_username(&state.db, "dana").await.unwrap().unwrap();
+        let stored = access_tokens::list_for_user(&state.db, &user.id).await.unwrap();
+        assert_eq!(stored[0].last_used_ip.as_deref(), Some("10.1.2.3"));
+        assert!(!serde_json::to_string(&stored[0]).unwrap().contains(plaintext));
+
+        revoke_token(State(state.clone()), Extension(dana.clone()), Path(id.clone())).await.unwrap();
+        assert!(matches!(identify(&state, plaintext, "10.1.2.3:5000").await, Err(AuthError::RevokedToken)));
+        assert_eq!(revoke_token(State(state.clone()), Extension(dana), Path(id)).await.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,330 @@
+// src/routes/admin.rs
+use axum::{
+    extract::{Path, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use crate::AppState;
+use crate::auth::Claims;
+use crate::features::require_admin;
+use crate::db::{access_tokens, tokens};
+use crate::db::users::{self, User};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct FeaturesRequest {
+    #[serde(default)]
+    pub grant: Vec<String>,
+    #[serde(default)]
+    pub revoke: Vec<String>,
+    #[serde(default)]
+    pub revoke_sessions: bool, // apply now instead of on the user's next refresh
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DevRequest {
+    pub is_dev: bool,
+    #[serde(default)]
+    pub revoke_sessions: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordResetRequest {
+    pub password: String,
+}
+
+// GET /admin/stats
+pub async fn admin_stats(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+
+    let (registered_users, disabled_users) = users::counts(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let messages = state.messages.read().await;
+    let total_messages = messages.len();
+    let unique_users = messages
+        .iter()
+        .map(|m| &m.user_id)
+        .collect::<std::collections::HashSet<_>>()
+        .len();
+    
+    // Add voice session stats if available
+    let voice_sessions = 0; // Placeholder since voice_manager is commented out
+    
+    Ok(Json(serde_json::json!({
+        "total_messages": total_messages,
+        "unique_users": unique_users,
+        "registered_users": registered_users,
+        "disabled_users": disabled_users,
+        "active_voice_sessions": voice_sessions,
+        "service": "echo-rubicon-admin"
+    })).into_response())
+}
+
+// GET /admin/users
+pub async fn list_users(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let users = users::list(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(serde_json::json!({ "users": users })).into_response())
+}
+
+// GET /admin/users/:username
+pub async fn get_user(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(username): Path<String>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let user = load_user(&state, &username).await?;
+    Ok(Json(user).into_response())
+}
+
+// POST /admin/users/:username/features
+//
+// New features reach the user's token on their next refresh, or straight
+// away when `revoke_sessions` is set.
+pub async fn update_features(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(username): Path<String>,
+    Json(payload): Json<FeaturesRequest>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    if payload.grant.iter().chain(&payload.revoke).any(|feature| feature.trim().is_empty()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let user = load_user(&state, &username).await?;
+
+    let mut features = user.features.0.clone();
+    features.retain(|feature| !payload.revoke.contains(feature));
+    for feature in &payload.grant {
+        if !features.contains(feature) {
+            features.push(feature.clone());
+        }
+    }
+    users::set_features(&state.db, &user.id, &features)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if payload.revoke_sessions {
+        revoke_sessions(&state, &user).await?;
+    }
+
+    updated(&state, &user).await
+}
+
+// POST /admin/users/:username/dev
+pub async fn set_dev(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(username): Path<String>,
+    Json(payload): Json<DevRequest>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let user = load_user(&state, &username).await?;
+    users::set_dev(&state.db, &user.id, payload.is_dev)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if payload.revoke_sessions {
+        revoke_sessions(&state, &user).await?;
+    }
+
+    updated(&state, &user).await
+}
+
+// POST /admin/users/:username/disable
+//
+// Signs the user out everywhere as well, so the account stops working at once.
+pub async fn disable_user(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(username): Path<String>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let user = load_user(&state, &username).await?;
+    if user.username.eq_ignore_ascii_case(&claims.sub) {
+        // Locking yourself out is never what was meant
+        return Err(StatusCode::CONFLICT);
+    }
+    users::set_disabled(&state.db, &user.id, true)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    revoke_sessions(&state, &user).await?;
+
+    updated(&state, &user).await
+}
+
+// POST /admin/users/:username/enable
+pub async fn enable_user(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(username): Path<String>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let user = load_user(&state, &username).await?;
+    users::set_disabled(&state.db, &user.id, false)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    updated(&state, &user).await
+}
+
+// POST /admin/users/:username/password
+//
+// Existing sessions and access tokens were issued under the old password, so
+// they are revoked.
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(username): Path<String>,
+    Json(payload): Json<PasswordResetRequest>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    if payload.password.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let user = load_user(&state, &username).await?;
+    users::set_password(&state.db, &user.id, &payload.password)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    revoke_sessions(&state, &user).await?;
+    revoke_access_tokens(&state, &user).await?;
+
+    updated(&state, &user).await
+}
+
+// POST /admin/users/:username/revoke-sessions
+//
+// Signs the user out everywhere, personal access tokens included.
+pub async fn revoke_user_sessions(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(username): Path<String>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let user = load_user(&state, &username).await?;
+    revoke_sessions(&state, &user).await?;
+    let access_tokens_revoked = revoke_access_tokens(&state, &user).await?;
+    Ok(Json(serde_json::json!({
+        "username": user.username,
+        "sessions_revoked": true,
+        "access_tokens_revoked": access_tokens_revoked,
+    })).into_response())
+}
+
+async fn load_user(state: &AppState, username: &str) -> Result<User, StatusCode> {
+    users::find_by_username(&state.db, username)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn revoke_sessions(state: &AppState, user: &User) -> Result<(), StatusCode> {
+    tokens::revoke_all_for_user(&state.db, &user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn revoke_access_tokens(state: &AppState, user: &User) -> Result<u64, StatusCode> {
+    access_tokens::revoke_all_for_user(&state.db, &user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// The user as stored after a change
+async fn updated(state: &AppState, user: &User) -> Result<Response, StatusCode> {
+    match users::get(&state.db, &user.id).await {
+        Ok(Some(user)) => Ok(Json(user).into_response()),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::{get, post};
+
+    axum::Router::new()
+        .route("/admin/stats", get(admin_stats))
+        .route("/admin/users", get(list_users))
+        .route("/admin/users/:username", get(get_user))
+        .route("/admin/users/:username/features", post(update_features))
+        .route("/admin/users/:username/dev", post(set_dev))
+        .route("/admin/users/:username/disable", post(disable_user))
+        .route("/admin/users/:username/enable", post(enable_user))
+        .route("/admin/users/:username/password", post(reset_password))
+        .route("/admin/users/:username/revoke-sessions", post(revoke_user_sessions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::Config;
+    use crate::routes::sessions::{issue_tokens, refresh, RefreshRequest};
+
+    fn admin() -> Claims {
+        Claims { sub: "root".to_string(), features: vec!["admin".to_string()], ..Claims::default() }
+    }
+
+    async fn body(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_admin_manages_features_and_access() {
+        let state = crate::test_app_state(Config::default()).await;
+        let user = users::create(&state.db, "carol", "pw", &["chat".to_string()], false).await.unwrap();
+        let session = issue_tokens(&state, &user).await.unwrap();
+        let name = || Path("carol".to_string());
+
+        let denied = list_users(State(state.clone()), Extension(Claims::default())).await;
+        assert_eq!(denied.unwrap_err(), StatusCode::FORBIDDEN);
+
+        let change = FeaturesRequest { grant: vec!["llm.use".to_string()], revoke: vec!["chat".to_string()], ..Default::default() };
+        let updated = body(update_features(State(state.clone()), Extension(admin()), name(), Json(change)).await.unwrap()).await;
+        assert_eq!(updated["features"], serde_json::json!(["llm.use"]));
+        assert!(updated.get("password_hash").is_none());
+
+        // The next refresh picks up the new features
+        let refreshed = body(refresh(State(state.clone()), Json(RefreshRequest { refresh_token: session.refresh_token })).await.unwrap()).await;
+        let claims = crate::auth::decode_token(refreshed["token"].as_str().unwrap(), &state.jwt_secret).unwrap();
+        assert_eq!(claims.features, vec!["llm.use".to_string()]);
+
+        set_dev(State(state.clone()), Extension(admin()), name(), Json(DevRequest { is_dev: true, revoke_sessions: false })).await.unwrap();
+        let listed = body(list_users(State(state.clone()), Extension(admin())).await.unwrap()).await;
+        assert_eq!(listed["users"][0]["is_dev"], true);
+
+        disable_user(State(state.clone()), Extension(admin()), name()).await.unwrap();
+        let refresh_token = refreshed["refresh_token"].as_str().unwrap().to_string();
+        let after_disable = refresh(State(state.clone()), Json(RefreshRequest { refresh_token })).await;
+        assert_eq!(after_disable.unwrap_err(), StatusCode::UNAUTHORIZED);
+        let stats = body(admin_stats(State(state.clone()), Extension(admin())).await.unwrap()).await;
+        assert_eq!((stats["registered_users"].as_i64(), stats["disabled_users"].as_i64()), (Some(1), Some(1)));
+
+        let own = disable_user(State(state.clone()), Extension(Claims { sub: "carol".to_string(), ..admin() }), name()).await;
+        assert_eq!(own.unwrap_err(), StatusCode::CONFLICT);
+
+        enable_user(State(state.clone()), Extension(admin()), name()).await.unwrap();
+        access_tokens::create(&state.db, &user.id, access_tokens::NewAccessToken {
+            name: "ci".to_string(),
+            features: vec!["chat".to_string()],
+            ..Default::default()
+        }).await.unwrap();
+        let reset = PasswordResetRequest { password: "new password".to_string() };
+        reset_password(State(state.clone()), Extension(admin()), name(), Json(reset)).await.unwrap();
+        let stored = users::find_by_username(&state.db, "carol").await.unwrap().unwrap();
+        assert!(!stored.is_disabled());
+        assert!(stored.verify_password("new password"));
+        let stored_tokens = access_tokens::list_for_user(&state.db, &user.id).await.unwrap();
+        assert!(stored_tokens[0].revoked_at.is_some());
+    }
+}
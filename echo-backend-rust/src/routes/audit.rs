@@ -0,0 +1,175 @@
+// src/routes/audit.rs
+use axum::{
+    extract::{Path, Query, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use crate::AppState;
+use crate::auth::Claims;
+use crate::features::require_admin;
+use crate::db::audit::{self, AuditFilters};
+use crate::models::llm::{ChatMessage, GenerationParams};
+use crate::routes::llm::LlmContext;
+
+const DEFAULT_AUDIT_LIMIT: i64 = 50;
+const MAX_AUDIT_LIMIT: i64 = 500;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    pub user: Option<String>,
+    pub model: Option<String>,
+    pub before: Option<i64>, // id of the oldest entry on the previous page
+    #[serde(default)]
+    pub errors: bool,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReplayRequest {
+    pub model: Option<String>, // defaults to the model that was logged
+}
+
+// GET /admin/audit
+pub async fn list_audit(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let filters = AuditFilters {
+        user_id: query.user,
+        model: query.model,
+        before_id: query.before,
+        errors_only: query.errors,
+        limit: query.limit.unwrap_or(DEFAULT_AUDIT_LIMIT).clamp(1, MAX_AUDIT_LIMIT),
+    };
+
+    let entries = audit::list(&state.db, &filters).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let next_before = entries.last().map(|entry| entry.id);
+    Ok(Json(serde_json::json!({
+        "entries": entries,
+        "next_before": next_before,
+    })).into_response())
+}
+
+// GET /admin/audit/:id
+pub async fn get_audit_entry(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    match audit::get(&state.db, id).await {
+        Ok(Some(entry)) => Ok(Json(entry).into_response()),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// POST /admin/audit/:id/replay
+//
+// Sends the logged messages and generation settings again. Redacted text is
+// replayed as its placeholder and images are not kept, so a replay can differ
+// from the original call in those respects.
+pub async fn replay_audit_entry(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i64>,
+    payload: Option<Json<ReplayRequest>>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let entry = match audit::get(&state.db, id).await {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    let request = &entry.request.0;
+    let messages: Vec<ChatMessage> = serde_json::from_value(request["messages"].clone())
+        .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+    let params: GenerationParams = serde_json::from_value(request["params"].clone()).unwrap_or_default();
+    let model = payload.and_then(|Json(p)| p.model).unwrap_or_else(|| entry.model.clone());
+
+    let config = {
+        let runtime_state = state.runtime_state.read().await;
+        runtime_state.config.clone()
+    };
+    let mut ctx = LlmContext::new(&state, config, Some(Extension(claims)), None);
+    ctx.overrides = params;
+
+    let result = ctx.call_chat(&model, &messages, None).await;
+    let (response, error) = match result {
+        Ok(text) => (Some(text), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    Ok(Json(serde_json::json!({
+        "replayed_from": entry.id,
+        "model": model,
+        "original_model": entry.model,
+        "response": response,
+        "error": error,
+        "redacted": entry.redacted,
+        "images_omitted": request["images"].as_u64().unwrap_or(0),
+    })).into_response())
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::{get, post};
+
+    axum::Router::new()
+        .route("/admin/audit", get(list_audit))
+        .route("/admin/audit/:id", get(get_audit_entry))
+        .route("/admin/audit/:id/replay", post(replay_audit_entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::Config;
+
+    fn admin() -> Claims {
+        Claims { sub: "admin@example.com".to_string(), features: vec!["admin".to_string()], ..Claims::default() }
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_logged_redacted_and_replayable() {
+        let config = Config { openai_key: Some("sk-live-key-abcdefghijklmnopqrstuvwxyz".to_string()), ..Config::default() };
+        let state = crate::test_app_state(config.clone()).await;
+
+        let ctx = LlmContext::new(&state, config, None, None);
+        ctx.mark_private("the vault combination");
+        let prompt = "Remember the vault combination? My key is sk-live-key-abcdefghijklmnopqrstuvwxyz";
+        ctx.call("mock:echo", prompt).await.unwrap();
+
+        let entries = audit::list(&state.db, &AuditFilters { limit: 10, ..AuditFilters::default() }).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!((entry.provider.as_str(), entry.model.as_str(), entry.user_id.as_str()), ("Mock", "mock:echo", "anonymous"));
+        assert!(entry.redacted);
+        let logged = entry.payload.0.to_string();
+        assert!(!logged.contains("vault combination") && !logged.contains("sk-live"));
+        assert!(entry.response.as_ref().unwrap().0.to_string().contains("[REDACTED: private note]"));
+
+        let forbidden = list_audit(
+            State(state.clone()),
+            Extension(Claims { features: vec![], ..admin() }),
+            Query(AuditQuery::default()),
+        ).await;
+        assert_eq!(forbidden.unwrap_err(), StatusCode::FORBIDDEN);
+
+        let replay = ReplayRequest { model: Some("mock:reply:second opinion".to_string()) };
+        let response = replay_audit_entry(State(state.clone()), Extension(admin()), Path(entry.id), Some(Json(replay))).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let replayed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(replayed["response"], "second opinion");
+        assert_eq!(replayed["original_model"], "mock:echo");
+
+        // The replay is itself a logged call, made as the admin
+        let latest = audit::list(&state.db, &AuditFilters { limit: 1, ..AuditFilters::default() }).await.unwrap();
+        assert_eq!(latest[0].user_id, "admin@example.com");
+    }
+}
@@ -1,20 +1,35 @@
 use axum::{
-    extract::{Extension, Json},
+    extract::{ConnectInfo, Extension, Json, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     routing::{get, post},
     Router,
 };
-use bcrypt::{hash, verify, DEFAULT_COST};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use std::net::SocketAddr;
 use tower_sessions::Session;
+use crate::AppState;
+use crate::db::passphrase;
+use crate::routes::{lockouts, secrets};
 
 #[derive(Deserialize)]
 pub struct UnlockRequest {
     passphrase: String,
 }
 
+#[derive(Deserialize)]
+pub struct SetupRequest {
+    setup_code: String,
+    passphrase: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePassphraseRequest {
+    current_passphrase: String,
+    new_passphrase: String,
+}
+
 #[derive(Serialize)]
 pub struct AuthStatus {
     authenticated: bool,
@@ -22,11 +37,10 @@ pub struct AuthStatus {
     session_id: Option<String>,
 }
 
+// Deliberately says nothing about whether a passphrase had been set before
 #[derive(Serialize)]
 pub struct UnlockResponse {
     success: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    first_run: Option<bool>,
 }
 
 #[derive(Serialize)]
@@ -38,9 +52,20 @@ pub fn routes() -> Router<crate::AppState> {
     Router::new()
         .route("/status", get(auth_status))
         .route("/unlock", post(unlock))
+        .route("/setup", post(setup))
+        .route("/passphrase", post(change_passphrase))
         .route("/logout", post(logout))
 }
 
+/// A one-time code for setting the first passphrase, printed to the server log
+/// at startup. Without it, whoever reached a fresh install first could claim it.
+pub fn generate_setup_code() -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    (0..12)
+        .map(|_| ALPHABET[rand::random::<usize>() % ALPHABET.len()] as char)
+        .collect()
+}
+
 async fn auth_status(session: Session) -> impl IntoResponse {
     let authenticated = session
         .get::<bool>("authenticated")
@@ -58,79 +83,238 @@ async fn auth_status(session: Session) -> impl IntoResponse {
     })
 }
 
+fn error(status: StatusCode, message: &str) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.to_string(),
+        }),
+    )
+        .into_response()
+}
+
 async fn unlock(
+    State(state): State<AppState>,
     session: Session,
     Extension(pool): Extension<SqlitePool>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     Json(payload): Json<UnlockRequest>,
 ) -> impl IntoResponse {
-    // Check if first run
-    let existing: Option<(String,)> = sqlx::query_as("SELECT hash FROM auth WHERE id = 1")
-        .fetch_optional(&pool)
-        .await
-        .unwrap_or(None);
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let keys = lockouts::passphrase_keys(ip);
+    if let Err(refused) = lockouts::check(&pool, &keys).await {
+        return refused;
+    }
+
+    let existing = match passphrase::get(&pool).await {
+        Ok(existing) => existing,
+        Err(_) => return error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load passphrase"),
+    };
 
+    // Before setup every passphrase is wrong, so unlock can't be used to set one
     match existing {
-        None => {
-            // First run - set passphrase
-            let hashed = match hash(&payload.passphrase, DEFAULT_COST) {
-                Ok(h) => h,
-                Err(_) => {
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ErrorResponse {
-                            error: "Failed to hash passphrase".to_string(),
-                        }),
-                    )
-                        .into_response();
-                }
-            };
+        Some(hash) if passphrase::verify(&hash, &payload.passphrase) => {
+            lockouts::record_success(&pool, &keys).await;
 
-            if let Err(_) = sqlx::query("INSERT INTO auth (id, hash) VALUES (1, ?)")
-                .bind(&hashed)
-                .execute(&pool)
-                .await
-            {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: "Failed to save passphrase".to_string(),
-                    }),
-                )
-                    .into_response();
+            let upgrade = {
+                let runtime_state = state.runtime_state.read().await;
+                runtime_state.config.passphrase.clone().unwrap_or_default().upgrade_hash()
+            };
+            if upgrade && passphrase::is_bcrypt(&hash) {
+                match passphrase::set(&pool, &payload.passphrase).await {
+                    Ok(()) => tracing::info!("Upgraded the vault passphrase hash from bcrypt to argon2"),
+                    Err(e) => tracing::warn!("Failed to upgrade the vault passphrase hash: {}", e),
+                }
             }
+            secrets::unlock_store(&state, &payload.passphrase).await;
 
             let _ = session.insert("authenticated", true).await;
-            
-            Json(UnlockResponse {
-                success: true,
-                first_run: Some(true),
-            })
-            .into_response()
+            Json(UnlockResponse { success: true }).into_response()
         }
-        Some((hash_str,)) => {
-            // Validate passphrase
-            match verify(&payload.passphrase, &hash_str) {
-                Ok(true) => {
-                    let _ = session.insert("authenticated", true).await;
-                    Json(UnlockResponse {
-                        success: true,
-                        first_run: None,
-                    })
-                    .into_response()
-                }
-                _ => (
-                    StatusCode::UNAUTHORIZED,
-                    Json(ErrorResponse {
-                        error: "Invalid passphrase".to_string(),
-                    }),
-                )
-                    .into_response(),
+        _ => {
+            lockouts::record_failure(&pool, &keys, ip).await;
+            error(StatusCode::UNAUTHORIZED, "Invalid passphrase")
+        }
+    }
+}
+
+// POST /api/auth/setup
+//
+// Sets the first passphrase. Needs the setup code from the server log, which
+// is used up once it works.
+async fn setup(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(pool): Extension<SqlitePool>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(payload): Json<SetupRequest>,
+) -> impl IntoResponse {
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let keys = lockouts::passphrase_keys(ip);
+    if let Err(refused) = lockouts::check(&pool, &keys).await {
+        return refused;
+    }
+    if payload.passphrase.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "Passphrase must not be empty");
+    }
+
+    let code_matches = state.setup_code.lock().unwrap().as_deref()
+        .is_some_and(|code| code == payload.setup_code.trim().to_uppercase());
+    if !code_matches {
+        lockouts::record_failure(&pool, &keys, ip).await;
+        return error(StatusCode::UNAUTHORIZED, "Invalid setup code");
+    }
+
+    // The code only exists while no passphrase is set, but one may have been
+    // written some other way since startup, or by a setup racing this one.
+    // The code is only used up once the passphrase is stored.
+    match passphrase::set_initial(&pool, &payload.passphrase).await {
+        Ok(true) => *state.setup_code.lock().unwrap() = None,
+        Ok(false) => return error(StatusCode::CONFLICT, "A passphrase is already set"),
+        Err(_) => return error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to save passphrase"),
+    }
+
+    lockouts::record_success(&pool, &keys).await;
+    secrets::unlock_store(&state, &payload.passphrase).await;
+    let _ = session.insert("authenticated", true).await;
+    Json(UnlockResponse { success: true }).into_response()
+}
+
+// POST /api/auth/passphrase
+//
+// Replaces the passphrase, re-encrypts the secrets store under it and signs
+// out every other unlocked session.
+async fn change_passphrase(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(pool): Extension<SqlitePool>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(payload): Json<ChangePassphraseRequest>,
+) -> impl IntoResponse {
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let keys = lockouts::passphrase_keys(ip);
+    if let Err(refused) = lockouts::check(&pool, &keys).await {
+        return refused;
+    }
+    if payload.new_passphrase.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "Passphrase must not be empty");
+    }
+
+    let verified = match passphrase::get(&pool).await {
+        Ok(Some(hash)) => passphrase::verify(&hash, &payload.current_passphrase),
+        Ok(None) => false,
+        Err(_) => return error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load passphrase"),
+    };
+    if !verified {
+        lockouts::record_failure(&pool, &keys, ip).await;
+        return error(StatusCode::UNAUTHORIZED, "Invalid passphrase");
+    }
+    lockouts::record_success(&pool, &keys).await;
+
+    // The passphrase and the secrets sealed under it change together or not at all
+    let key = {
+        let Ok(mut tx) = pool.begin().await else {
+            return error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to save passphrase");
+        };
+        let key = match crate::db::secrets::rekey(&mut tx, &payload.current_passphrase, &payload.new_passphrase).await {
+            Ok(key) => key,
+            Err(e) => {
+                tracing::error!("Failed to re-encrypt secrets for a passphrase change: {}", e);
+                return error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to re-encrypt secrets");
             }
+        };
+        if passphrase::set(&mut *tx, &payload.new_passphrase).await.is_err() || tx.commit().await.is_err() {
+            return error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to save passphrase");
         }
+        key
+    };
+    if let Err(e) = secrets::load(&state, key).await {
+        tracing::warn!("Secrets stay locked: {}", e);
     }
+    if let Err(e) = crate::db::sessions::delete_all(&pool).await {
+        tracing::warn!("Failed to sign out sessions after a passphrase change: {}", e);
+    }
+
+    // This caller proved the passphrase, so they stay signed in under a fresh session id
+    let _ = session.cycle_id().await;
+    let _ = session.insert("authenticated", true).await;
+    Json(UnlockResponse { success: true }).into_response()
 }
 
 async fn logout(session: Session) -> impl IntoResponse {
     session.flush().await;
     Json(serde_json::json!({ "success": true }))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::sessions::SqliteSessionStore;
+    use crate::models::config::Config;
+    use std::sync::Arc;
+
+    fn session(state: &AppState) -> Session {
+        Session::new(None, Arc::new(SqliteSessionStore::new(state.db.clone())), None)
+    }
+
+    async fn try_unlock(state: &AppState, passphrase: &str) -> StatusCode {
+        let payload = UnlockRequest { passphrase: passphrase.to_string() };
+        unlock(State(state.clone()), session(state), Extension(state.db.clone()), None, Json(payload))
+            .await
+            .into_response()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_setup_code_then_change_passphrase() {
+        let state = crate::test_app_state(Config::default()).await;
+        *state.setup_code.lock().unwrap() = Some("ABCD2345EFGH".to_string());
+
+        // Unlocking can no longer claim a fresh install
+        assert_eq!(try_unlock(&state, "first").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(passphrase::get(&state.db).await.unwrap(), None);
+
+        let wrong = SetupRequest { setup_code: "WRONG".to_string(), passphrase: "first".to_string() };
+        let response = setup(State(state.clone()), session(&state), Extension(state.db.clone()), None, Json(wrong)).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let right = || SetupRequest { setup_code: "abcd2345efgh".to_string(), passphrase: "first".to_string() };
+        // A failed save keeps the code for another try
+        sqlx::query("ALTER TABLE auth RENAME TO auth_moved").execute(&state.db).await.unwrap();
+        let response = setup(State(state.clone()), session(&state), Extension(state.db.clone()), None, Json(right())).await.into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(state.setup_code.lock().unwrap().is_some());
+        sqlx::query("ALTER TABLE auth_moved RENAME TO auth").execute(&state.db).await.unwrap();
+
+        let response = setup(State(state.clone()), session(&state), Extension(state.db.clone()), None, Json(right())).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(state.setup_code.lock().unwrap().is_none());
+        assert_eq!(try_unlock(&state, "first").await, StatusCode::OK);
+
+        let change = |current: &str| ChangePassphraseRequest {
+            current_passphrase: current.to_string(),
+            new_passphrase: "second".to_string(),
+        };
+        let response = change_passphrase(State(state.clone()), session(&state), Extension(state.db.clone()), None, Json(change("guess"))).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = change_passphrase(State(state.clone()), session(&state), Extension(state.db.clone()), None, Json(change("first"))).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(try_unlock(&state, "first").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(try_unlock(&state, "second").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_unlock_upgrades_bcrypt_hashes() {
+        let state = crate::test_app_state(Config::default()).await;
+        sqlx::query("INSERT INTO auth (id, hash) VALUES (1, ?)")
+            .bind(bcrypt::hash("legacy", 4).unwrap())
+            .execute(&state.db)
+            .await
+            .unwrap();
+
+        assert_eq!(try_unlock(&state, "legacy").await, StatusCode::OK);
+        let stored = passphrase::get(&state.db).await.unwrap().unwrap();
+        assert!(!passphrase::is_bcrypt(&stored));
+        assert_eq!(try_unlock(&state, "legacy").await, StatusCode::OK);
+    }
+}
@@ -0,0 +1,336 @@
+// src/routes/batches.rs
+//
+// Offline prompt runs. An uploaded JSONL file becomes a batch of stored
+// items that a background task works through at Batch priority, so
+// interactive traffic always goes first. Progress lives in the database,
+// which is what lets unfinished batches pick up again after a restart.
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::{header, StatusCode},
+};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use crate::AppState;
+use crate::auth::{user_id, Claims};
+use crate::db::batches::{self, Batch, BatchItem, BatchRequest};
+use crate::routes::llm::LlmContext;
+use crate::state::llm_queue::Priority;
+
+// The JSONL is parsed in memory before any item is stored
+const MAX_BATCH_BODY_BYTES: usize = 32 * 1024 * 1024;
+const MAX_BATCH_ITEMS: usize = 50_000;
+// Items in flight per batch; the LLM queue still applies provider and model limits
+const BATCH_CONCURRENCY: usize = 8;
+const ITEMS_PER_FETCH: i64 = 100;
+
+// POST /llm/batches
+pub async fn create_batch(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    body: Bytes,
+) -> Result<Response, StatusCode> {
+    let requests = match parse_jsonl(&body) {
+        Ok(requests) => requests,
+        Err(message) => {
+            return Ok((StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message }))).into_response());
+        },
+    };
+
+    let batch = batches::create(&state.db, &user_id(&claims), &requests).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tokio::spawn(run_batch(state.clone(), batch.id.clone()));
+
+    let body = batch_json(&state, batch).await?;
+    Ok((StatusCode::CREATED, Json(body)).into_response())
+}
+
+// GET /llm/batches
+pub async fn list_batches(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+) -> Result<Response, StatusCode> {
+    let list = batches::list(&state.db, &user_id(&claims)).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut results = Vec::new();
+    for batch in list {
+        results.push(batch_json(&state, batch).await?);
+    }
+    Ok(Json(serde_json::json!({ "batches": results })).into_response())
+}
+
+// GET /llm/batches/:id
+pub async fn get_batch(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let batch = load_batch(&state, &id, &user_id(&claims)).await?;
+    Ok(Json(batch_json(&state, batch).await?).into_response())
+}
+
+// GET /llm/batches/:id/results
+pub async fn batch_results(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    load_batch(&state, &id, &user_id(&claims)).await?;
+    let items = batches::items(&state.db, &id).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut body = String::new();
+    for item in &items {
+        body.push_str(&result_line(item).to_string());
+        body.push('\n');
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"batch-{}.jsonl\"", id)),
+        ],
+        body,
+    ).into_response())
+}
+
+// POST /llm/batches/:id/cancel
+pub async fn cancel_batch(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    load_batch(&state, &id, &user_id(&claims)).await?;
+    let cancelled = batches::cancel(&state.db, &id).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !cancelled {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let batch = load_batch(&state, &id, &user_id(&claims)).await?;
+    Ok(Json(batch_json(&state, batch).await?).into_response())
+}
+
+/// Restart the background runs for batches a shutdown left unfinished
+pub async fn resume_batches(state: &AppState) {
+    match batches::unfinished(&state.db).await {
+        Ok(unfinished) => {
+            for batch in unfinished {
+                tracing::info!("Resuming batch {}", batch.id);
+                tokio::spawn(run_batch(state.clone(), batch.id));
+            }
+        },
+        Err(e) => tracing::error!("Failed to load unfinished batches: {}", e),
+    }
+}
+
+// Work through a batch's pending items until none are left or it is cancelled
+async fn run_batch(state: AppState, batch_id: String) {
+    let pool = &state.db;
+    let Ok(Some(owner)) = sqlx::query_scalar::<_, String>("SELECT user_id FROM batches WHERE id = ?")
+        .bind(&batch_id)
+        .fetch_optional(pool)
+        .await else {
+        return;
+    };
+    if let Err(e) = batches::set_status(pool, &batch_id, "running").await {
+        tracing::error!("Failed to start batch {}: {}", batch_id, e);
+        return;
+    }
+
+    let config = {
+        let runtime_state = state.runtime_state.read().await;
+        runtime_state.config.clone()
+    };
+    let mut ctx = LlmContext::new(&state, config, None, Some(Priority::Batch));
+    ctx.user = owner;
+
+    let slots = Arc::new(Semaphore::new(BATCH_CONCURRENCY));
+    let mut running = JoinSet::new();
+    let mut last_id = 0;
+
+    let mut final_status = "completed";
+    'fetch: loop {
+        let items = match batches::pending_items(pool, &batch_id, last_id, ITEMS_PER_FETCH).await {
+            Ok(items) if !items.is_empty() => items,
+            Ok(_) => break,
+            Err(e) => {
+                // Marked failed rather than left running forever; unfinished items stay pending
+                tracing::error!("Failed to load items for batch {}: {}", batch_id, e);
+                final_status = "failed";
+                break;
+            },
+        };
+
+        for item in items {
+            let Ok(slot) = Arc::clone(&slots).acquire_owned().await else { break 'fetch };
+            // Checked per item so a cancel takes effect without waiting for the batch to drain
+            if !matches!(batches::status(pool, &batch_id).await, Ok(Some(status)) if status == "running") {
+                break 'fetch;
+            }
+            last_id = item.id;
+
+            let ctx = ctx.clone();
+            let pool = pool.clone();
+            running.spawn(async move {
+                run_item(&pool, ctx, item).await;
+                drop(slot);
+            });
+        }
+    }
+
+    while running.join_next().await.is_some() {}
+    if let Err(e) = batches::set_status(pool, &batch_id, final_status).await {
+        tracing::error!("Failed to mark batch {} {}: {}", batch_id, final_status, e);
+    }
+}
+
+async fn run_item(pool: &sqlx::SqlitePool, mut ctx: LlmContext, item: BatchItem) {
+    let request = item.request.0;
+    let model = request.model.unwrap_or_else(|| ctx.config.llm_model.clone());
+    ctx.overrides = request.params;
+
+    let result = ctx.call_chat(&model, &request.messages, None).await
+        .map_err(|e| e.to_string());
+    let result = match &result {
+        Ok(text) => Ok(text.as_str()),
+        Err(e) => Err(e.as_str()),
+    };
+    if let Err(e) = batches::finish_item(pool, item.id, result).await {
+        tracing::error!("Failed to store result for batch item {}: {}", item.id, e);
+    }
+}
+
+// Every line must parse before anything is stored, so a typo on line 9000 fails fast
+fn parse_jsonl(body: &[u8]) -> Result<Vec<(i64, BatchRequest)>, String> {
+    let text = std::str::from_utf8(body).map_err(|_| "Batch file is not UTF-8".to_string())?;
+    let mut requests = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = index as i64 + 1;
+        let request: BatchRequest = serde_json::from_str(line)
+            .map_err(|e| format!("Line {}: {}", line_number, e))?;
+        if request.messages.is_empty() {
+            return Err(format!("Line {}: messages must not be empty", line_number));
+        }
+        requests.push((line_number, request));
+    }
+
+    if requests.is_empty() {
+        return Err("Batch file contains no requests".to_string());
+    }
+    if requests.len() > MAX_BATCH_ITEMS {
+        return Err(format!("Batches are limited to {} requests", MAX_BATCH_ITEMS));
+    }
+    Ok(requests)
+}
+
+fn result_line(item: &BatchItem) -> serde_json::Value {
+    serde_json::json!({
+        "line": item.line,
+        "custom_id": item.custom_id,
+        "model": item.request.model,
+        "status": item.status,
+        "response": item.response,
+        "error": item.error,
+        "completed_at": item.completed_at,
+    })
+}
+
+async fn batch_json(state: &AppState, batch: Batch) -> Result<serde_json::Value, StatusCode> {
+    let progress = batches::progress(&state.db, &batch.id).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(serde_json::json!({
+        "batch": batch,
+        "progress": progress,
+        "results_url": format!("/llm/batches/{}/results", batch.id),
+    }))
+}
+
+async fn load_batch(state: &AppState, id: &str, user: &str) -> Result<Batch, StatusCode> {
+    match batches::get(&state.db, id, user).await {
+        Ok(Some(batch)) => Ok(batch),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::{get, post};
+
+    axum::Router::new()
+        .route("/batches", get(list_batches)
+            .post(create_batch)
+            .layer(DefaultBodyLimit::max(MAX_BATCH_BODY_BYTES)))
+        .route("/batches/:id", get(get_batch))
+        .route("/batches/:id/results", get(batch_results))
+        .route("/batches/:id/cancel", post(cancel_batch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::Config;
+
+    const JSONL: &str = r#"{"custom_id": "first", "model": "mock:echo", "messages": [{"role": "user", "content": "one"}]}
+
+{"custom_id": "second", "messages": [{"role": "user", "content": "two"}], "params": {"temperature": 0.2}}
+"#;
+
+    #[tokio::test]
+    async fn test_batch_runs_and_resumes() {
+        let state = crate::test_app_state(Config { llm_model: "mock:reply:default".to_string(), ..Config::default() }).await;
+        let requests = parse_jsonl(JSONL.as_bytes()).unwrap();
+        assert_eq!(requests[1].0, 3);
+
+        // A stored batch that never started is what a restart leaves behind
+        let batch = batches::create(&state.db, "alice", &requests).await.unwrap();
+        assert_eq!(batches::unfinished(&state.db).await.unwrap().len(), 1);
+        run_batch(state.clone(), batch.id.clone()).await;
+
+        let progress = batches::progress(&state.db, &batch.id).await.unwrap();
+        assert_eq!((progress.total, progress.succeeded), (2, 2));
+        assert_eq!(batches::status(&state.db, &batch.id).await.unwrap().as_deref(), Some("completed"));
+
+        let response = batch_results(State(state.clone()), None, Path(batch.id.clone())).await;
+        assert_eq!(response.unwrap_err(), StatusCode::NOT_FOUND); // alice's batch, anonymous caller
+        let items = batches::items(&state.db, &batch.id).await.unwrap();
+        assert_eq!(items[0].response.as_deref(), Some("one"));
+        assert_eq!(items[1].response.as_deref(), Some("default"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_batch_stops() {
+        let state = crate::test_app_state(Config { llm_model: "mock:echo".to_string(), ..Config::default() }).await;
+        let batch = batches::create(&state.db, "anonymous", &parse_jsonl(JSONL.as_bytes()).unwrap()).await.unwrap();
+
+        let response = cancel_batch(State(state.clone()), None, Path(batch.id.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        run_batch(state.clone(), batch.id.clone()).await;
+
+        let progress = batches::progress(&state.db, &batch.id).await.unwrap();
+        assert_eq!((progress.cancelled, progress.succeeded), (2, 0));
+        let again = cancel_batch(State(state.clone()), None, Path(batch.id.clone())).await;
+        assert_eq!(again.unwrap_err(), StatusCode::CONFLICT);
+
+        assert!(parse_jsonl(b"{\"messages\": []}").unwrap_err().starts_with("Line 1"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_fails_when_items_cannot_load() {
+        let state = crate::test_app_state(Config { llm_model: "mock:echo".to_string(), ..Config::default() }).await;
+        let batch = batches::create(&state.db, "anonymous", &parse_jsonl(JSONL.as_bytes()).unwrap()).await.unwrap();
+        sqlx::query("ALTER TABLE batch_items RENAME TO batch_items_gone").execute(&state.db).await.unwrap();
+
+        run_batch(state.clone(), batch.id.clone()).await;
+        assert_eq!(batches::status(&state.db, &batch.id).await.unwrap().as_deref(), Some("failed"));
+        assert!(batches::unfinished(&state.db).await.unwrap().is_empty());
+    }
+}
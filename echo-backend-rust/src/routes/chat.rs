@@ -0,0 +1,762 @@
+// src/routes/chat.rs
+//
+// Multi-turn chat with persistent history. Long conversations are kept inside
+// the model's context window by a rolling summary: once the estimated size of
+// the unsummarised turns passes the configured threshold, the older ones are
+// folded into the summary, which is then sent in their place.
+use axum::{
+    extract::{Path, Query, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use crate::AppState;
+use crate::auth::{user_id, Claims};
+use crate::db::conversations::{self, Conversation, SearchFilters, StoredMessage};
+use crate::db::personas::{self, Persona};
+use crate::models::config::SummarizationConfig;
+use crate::models::llm::{ChatMessage, ChatRole};
+use crate::routes::llm::LlmContext;
+use crate::routes::personas::{load_persona, vault_context};
+use crate::state::llm_queue::Priority;
+
+#[derive(Debug, Deserialize)]
+pub struct ChatRequest {
+    pub conversation_id: Option<String>, // Starts a new conversation when omitted
+    pub message: String,
+    pub model: Option<String>,
+    pub persona_id: Option<String>, // Also becomes the conversation's persona for later turns
+    pub priority: Option<Priority>,
+}
+
+const TITLE_MAX_CHARS: usize = 60;
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct EditMessageRequest {
+    pub content: String,
+    pub model: Option<String>,
+    pub priority: Option<Priority>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RegenerateRequest {
+    pub model: Option<String>, // Defaults to the model that wrote the original reply
+    pub priority: Option<Priority>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetActiveRequest {
+    pub message_id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub model: Option<String>,
+    pub mode: Option<String>,  // "chat", "debate", "consensus", ...
+    pub since: Option<String>, // YYYY-MM-DD or RFC 3339, inclusive
+    pub until: Option<String>, // YYYY-MM-DD or RFC 3339; whole day when a date is given
+    pub limit: Option<i64>,
+}
+
+// POST /llm/chat
+pub async fn send_message(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Json(payload): Json<ChatRequest>,
+) -> Result<Response, StatusCode> {
+    let ctx = chat_context(&state, claims, payload.priority).await;
+    let pool = &state.db;
+
+    let requested_persona = match &payload.persona_id {
+        Some(id) => Some(load_persona(pool, id, &ctx.user).await?),
+        None => None,
+    };
+
+    let mut conversation = match &payload.conversation_id {
+        Some(id) => load_conversation(pool, id, &ctx.user).await?,
+        None => {
+            let model = payload.model.as_deref()
+                .or(requested_persona.as_ref().map(|p| p.model.as_str()))
+                .unwrap_or(&ctx.config.llm_model);
+            let title: String = payload.message.chars().take(TITLE_MAX_CHARS).collect();
+            conversations::create(pool, &ctx.user, model, "chat", Some(title.trim())).await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        },
+    };
+
+    // The persona sticks to the conversation until another one is picked
+    let persona = match requested_persona {
+        Some(persona) => {
+            conversations::set_persona(pool, &conversation.id, Some(&persona.id)).await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            conversation.persona_id = Some(persona.id.clone());
+            Some(persona)
+        },
+        None => conversation_persona(pool, &conversation).await,
+    };
+    let model = payload.model.clone()
+        .or_else(|| persona.as_ref().map(|p| p.model.clone()))
+        .unwrap_or_else(|| conversation.model.clone());
+
+    // A user turn left unanswered by a failed reply is retried beside it
+    // rather than answered under, so two user turns never follow each other
+    let messages = load_messages(pool, &conversation.id).await?;
+    let parent_id = match conversation.active_leaf.and_then(|leaf| messages.iter().find(|m| m.id == leaf)) {
+        Some(leaf) if leaf.role == ChatRole::User.as_str() => leaf.parent_id,
+        _ => conversation.active_leaf,
+    };
+    let message = conversations::add_message(
+        pool, &conversation.id, parent_id, ChatRole::User, &payload.message, None,
+    ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let path = load_path(pool, &conversation.id, Some(message.id)).await?;
+    generate_reply(&state, ctx, conversation, path, &model, persona.as_ref()).await
+}
+
+// POST /llm/conversations/:id/messages/:message_id/edit
+// The edited text becomes a sibling of the original, starting a new branch
+pub async fn edit_message(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path((id, message_id)): Path<(String, i64)>,
+    Json(payload): Json<EditMessageRequest>,
+) -> Result<Response, StatusCode> {
+    let ctx = chat_context(&state, claims, payload.priority).await;
+    let pool = &state.db;
+
+    let conversation = load_conversation(pool, &id, &ctx.user).await?;
+    let messages = load_messages(pool, &id).await?;
+    let original = find_message(&messages, message_id)?;
+    if original.role != ChatRole::User.as_str() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let persona = conversation_persona(pool, &conversation).await;
+    let model = payload.model.clone()
+        .or_else(|| persona.as_ref().map(|p| p.model.clone()))
+        .unwrap_or_else(|| conversation.model.clone());
+
+    let edited = conversations::add_message(pool, &id, original.parent_id, ChatRole::User, &payload.content, None).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let path = load_path(pool, &id, Some(edited.id)).await?;
+    generate_reply(&state, ctx, conversation, path, &model, persona.as_ref()).await
+}
+
+// POST /llm/conversations/:id/messages/:message_id/regenerate
+// The new answer becomes a sibling of the original reply
+pub async fn regenerate_message(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path((id, message_id)): Path<(String, i64)>,
+    payload: Option<Json<RegenerateRequest>>,
+) -> Result<Response, StatusCode> {
+    let Json(payload) = payload.unwrap_or_default();
+    let ctx = chat_context(&state, claims, payload.priority).await;
+    let pool = &state.db;
+
+    let conversation = load_conversation(pool, &id, &ctx.user).await?;
+    let messages = load_messages(pool, &id).await?;
+    let original = find_message(&messages, message_id)?;
+    if original.role != ChatRole::Assistant.as_str() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let persona = conversation_persona(pool, &conversation).await;
+    let model = payload.model.clone()
+        .or_else(|| original.model.clone())
+        .unwrap_or_else(|| conversation.model.clone());
+
+    let path = conversations::path_to(&messages, original.parent_id);
+    generate_reply(&state, ctx, conversation, path, &model, persona.as_ref()).await
+}
+
+// GET /llm/conversations/:id
+pub async fn get_conversation(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let conversation = load_conversation(&state.db, &id, &user_id(&claims)).await?;
+    let messages = load_messages(&state.db, &id).await?;
+    let active_path: Vec<i64> = conversations::path_to(&messages, conversation.active_leaf)
+        .iter()
+        .map(|m| m.id)
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "conversation": conversation,
+        "messages": messages,
+        "active_path": active_path,
+    })).into_response())
+}
+
+// GET /llm/conversations/search
+pub async fn search_conversations(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Response, StatusCode> {
+    if query.q.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let filters = SearchFilters {
+        model: query.model.clone(),
+        mode: query.mode.clone(),
+        since: query.since.as_deref().map(|d| parse_date(d, false)).transpose()?,
+        until: query.until.as_deref().map(|d| parse_date(d, true)).transpose()?,
+        limit: query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT),
+    };
+
+    let hits = conversations::search(&state.db, &user_id(&claims), &query.q, &filters).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let results: Vec<_> = hits.into_iter()
+        .map(|hit| serde_json::json!({
+            "link": format!("/llm/conversations/{}?message={}", hit.conversation_id, hit.message_id),
+            "hit": hit,
+        }))
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "query": query.q,
+        "total": results.len(),
+        "results": results,
+    })).into_response())
+}
+
+// Unix seconds; a bare date as `until` covers that whole day
+fn parse_date(value: &str, end_of_day: bool) -> Result<i64, StatusCode> {
+    if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.timestamp());
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let date = if end_of_day { date.succ_opt().ok_or(StatusCode::BAD_REQUEST)? } else { date };
+    Ok(date.and_time(chrono::NaiveTime::MIN).and_utc().timestamp())
+}
+
+// GET /llm/conversations/:id/branches
+pub async fn list_branches(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let conversation = load_conversation(&state.db, &id, &user_id(&claims)).await?;
+    let messages = load_messages(&state.db, &id).await?;
+
+    let branches: Vec<_> = conversations::leaves(&messages)
+        .into_iter()
+        .map(|leaf| {
+            let path: Vec<i64> = conversations::path_to(&messages, Some(leaf.id))
+                .iter()
+                .map(|m| m.id)
+                .collect();
+            serde_json::json!({
+                "leaf_id": leaf.id,
+                "active": conversation.active_leaf == Some(leaf.id),
+                "model": leaf.model,
+                "preview": leaf.content.chars().take(TITLE_MAX_CHARS).collect::<String>(),
+                "created_at": leaf.created_at,
+                "path": path,
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "conversation_id": id,
+        "branches": branches,
+    })).into_response())
+}
+
+// POST /llm/conversations/:id/active
+// Any message may be picked; the branch continues down its newest replies
+pub async fn set_active_branch(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+    Json(payload): Json<SetActiveRequest>,
+) -> Result<Response, StatusCode> {
+    load_conversation(&state.db, &id, &user_id(&claims)).await?;
+    let messages = load_messages(&state.db, &id).await?;
+    find_message(&messages, payload.message_id)?;
+
+    let leaf = conversations::latest_leaf_under(&messages, payload.message_id);
+    conversations::set_active_leaf(&state.db, &id, leaf).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let active_path: Vec<i64> = conversations::path_to(&messages, Some(leaf))
+        .iter()
+        .map(|m| m.id)
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "conversation_id": id,
+        "active_leaf": leaf,
+        "active_path": active_path,
+    })).into_response())
+}
+
+// GET /llm/conversations/:id/summary
+pub async fn get_summary(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let conversation = load_conversation(&state.db, &id, &user_id(&claims)).await?;
+    let path = load_path(&state.db, &id, conversation.active_leaf).await?;
+
+    Ok(Json(summary_json(&conversation, &path)).into_response())
+}
+
+// POST /llm/conversations/:id/summary/regenerate
+// Rebuilds the summary from scratch over everything but the most recent turns
+pub async fn regenerate_summary(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let ctx = chat_context(&state, claims, None).await;
+
+    let mut conversation = load_conversation(&state.db, &id, &ctx.user).await?;
+    let path = load_path(&state.db, &id, conversation.active_leaf).await?;
+
+    if let Err(e) = update_summary(&state.db, &ctx, &mut conversation, &path, true).await {
+        return Ok((StatusCode::BAD_GATEWAY, Json(serde_json::json!({
+            "error": e.to_string(),
+        }))).into_response());
+    }
+
+    Ok(Json(summary_json(&conversation, &path)).into_response())
+}
+
+// Answer the last message on `path`, storing the reply as its child
+async fn generate_reply(
+    state: &AppState,
+    mut ctx: LlmContext,
+    mut conversation: Conversation,
+    path: Vec<StoredMessage>,
+    model: &str,
+    persona: Option<&Persona>,
+) -> Result<Response, StatusCode> {
+    let pool = &state.db;
+
+    // A failed summary only costs context space, so carry on with the full history
+    if let Err(e) = update_summary(pool, &ctx, &mut conversation, &path, false).await {
+        tracing::warn!("Summarising conversation {} failed: {}", conversation.id, e);
+    }
+
+    let history: Vec<ChatMessage> = pending_messages(&conversation, &path)
+        .iter()
+        .map(StoredMessage::to_chat)
+        .collect();
+
+    let mut context = Vec::new();
+    if let Some(persona) = persona {
+        ctx.persona_prompt = persona.system_prompt.clone();
+        if let Some(notes) = vault_context(state, &ctx, persona, latest_text(&path)).await {
+            context.push(notes);
+        }
+    }
+    if let Some(summary) = summary_on_path(&conversation, &path) {
+        context.push(format!("Summary of the earlier conversation:\n{}", summary));
+    }
+    let context = (!context.is_empty()).then(|| context.join("\n\n"));
+
+    let reply = match ctx.call_chat(model, &history, context.as_deref()).await {
+        Ok(reply) => reply,
+        Err(e) => {
+            return Ok((StatusCode::BAD_GATEWAY, Json(serde_json::json!({
+                "error": e.to_string(),
+                "conversation_id": conversation.id,
+            }))).into_response());
+        },
+    };
+
+    let parent_id = path.last().map(|m| m.id);
+    let stored = conversations::add_message(pool, &conversation.id, parent_id, ChatRole::Assistant, &reply, Some(model)).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "conversation_id": conversation.id,
+        "message_id": stored.id,
+        "parent_id": parent_id,
+        "model": model,
+        "persona_id": conversation.persona_id,
+        "response": reply,
+        "summarized_through": summary_on_path(&conversation, &path).and(conversation.summary_through),
+    })).into_response())
+}
+
+fn latest_text(path: &[StoredMessage]) -> &str {
+    path.last().map(|m| m.content.as_str()).unwrap_or_default()
+}
+
+// A deleted persona simply stops applying
+async fn conversation_persona(pool: &SqlitePool, conversation: &Conversation) -> Option<Persona> {
+    let id = conversation.persona_id.as_deref()?;
+    personas::get(pool, id, &conversation.user_id).await.ok().flatten()
+}
+
+async fn chat_context(state: &AppState, claims: Option<Extension<Claims>>, priority: Option<Priority>) -> LlmContext {
+    let config = {
+        let runtime_state = state.runtime_state.read().await;
+        runtime_state.config.clone()
+    };
+    LlmContext::new(state, config, claims, Some(priority.unwrap_or(Priority::Interactive)))
+}
+
+// Other users' conversations look the same as missing ones
+async fn load_conversation(pool: &SqlitePool, id: &str, user: &str) -> Result<Conversation, StatusCode> {
+    match conversations::get(pool, id).await {
+        Ok(Some(conversation)) if conversation.user_id == user => Ok(conversation),
+        Ok(_) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+async fn load_messages(pool: &SqlitePool, id: &str) -> Result<Vec<StoredMessage>, StatusCode> {
+    conversations::messages(pool, id).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn load_path(pool: &SqlitePool, id: &str, leaf: Option<i64>) -> Result<Vec<StoredMessage>, StatusCode> {
+    Ok(conversations::path_to(&load_messages(pool, id).await?, leaf))
+}
+
+fn find_message(messages: &[StoredMessage], id: i64) -> Result<&StoredMessage, StatusCode> {
+    messages.iter().find(|m| m.id == id).ok_or(StatusCode::NOT_FOUND)
+}
+
+fn summary_json(conversation: &Conversation, path: &[StoredMessage]) -> serde_json::Value {
+    let summary = summary_on_path(conversation, path);
+    let pending = pending_messages(conversation, path);
+    serde_json::json!({
+        "conversation_id": conversation.id,
+        "summary": summary,
+        "summarized_through": summary.and(conversation.summary_through),
+        "pending_messages": pending.len(),
+        "estimated_tokens": estimated_context_tokens(summary, pending),
+    })
+}
+
+// The summary only stands in for turns on the branch it was built from
+fn summary_on_path<'a>(conversation: &'a Conversation, path: &[StoredMessage]) -> Option<&'a str> {
+    let through = conversation.summary_through?;
+    if path.iter().any(|m| m.id == through) {
+        conversation.summary.as_deref()
+    } else {
+        None
+    }
+}
+
+// Messages on the branch not covered by its summary; these are sent verbatim
+fn pending_messages<'a>(conversation: &Conversation, path: &'a [StoredMessage]) -> &'a [StoredMessage] {
+    let covered = conversation.summary_through
+        .and_then(|through| path.iter().position(|m| m.id == through));
+    match covered {
+        Some(index) if conversation.summary.is_some() => &path[index + 1..],
+        _ => path,
+    }
+}
+
+// Rough but provider-independent: about four characters per token for English text
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn estimated_context_tokens(summary: Option<&str>, pending: &[StoredMessage]) -> usize {
+    summary.map(estimate_tokens).unwrap_or(0)
+        + pending.iter().map(|m| estimate_tokens(&m.content)).sum::<usize>()
+}
+
+// How many of the pending messages to fold into the summary; zero while under budget
+fn messages_to_fold(summary: Option<&str>, pending: &[StoredMessage], settings: &SummarizationConfig) -> usize {
+    if estimated_context_tokens(summary, pending) <= settings.token_threshold() {
+        return 0;
+    }
+    pending.len().saturating_sub(settings.keep_recent())
+}
+
+// Fold older turns on the branch into the rolling summary. With `force`, the
+// summary is rebuilt from the root regardless of the token threshold. A branch
+// that doesn't contain the stored summary's turns starts a fresh one.
+async fn update_summary(
+    pool: &SqlitePool,
+    ctx: &LlmContext,
+    conversation: &mut Conversation,
+    path: &[StoredMessage],
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let settings = ctx.config.summarization.clone().unwrap_or_default();
+
+    let (previous, pending) = if force {
+        (None, path)
+    } else {
+        (summary_on_path(conversation, path).map(str::to_string), pending_messages(conversation, path))
+    };
+    let fold = if force {
+        pending.len().saturating_sub(settings.keep_recent())
+    } else {
+        messages_to_fold(previous.as_deref(), pending, &settings)
+    };
+
+    if fold == 0 {
+        if force && conversation.summary.is_some() {
+            conversations::set_summary(pool, &conversation.id, None, None).await?;
+            conversation.summary = None;
+            conversation.summary_through = None;
+        }
+        return Ok(());
+    }
+
+    let folded = &pending[..fold];
+    let model = settings.model.as_deref().unwrap_or(&conversation.model);
+    let summary = ctx.call(model, &summary_prompt(previous.as_deref(), folded)).await?;
+    let through = folded.last().map(|m| m.id);
+
+    conversations::set_summary(pool, &conversation.id, Some(&summary), through).await?;
+    conversation.summary = Some(summary);
+    conversation.summary_through = through;
+    Ok(())
+}
+
+fn summary_prompt(previous: Option<&str>, turns: &[StoredMessage]) -> String {
+    let mut prompt = String::from(
+        "Summarise the conversation below so the summary can stand in for the original messages. \
+         Keep names, facts the user shared, decisions made and open questions. \
+         Reply with the summary only.\n\n",
+    );
+    if let Some(previous) = previous {
+        prompt.push_str(&format!("Summary so far:\n{}\n\n", previous));
+    }
+    prompt.push_str("Conversation:\n");
+    for turn in turns {
+        let speaker = if turn.role == ChatRole::Assistant.as_str() { "Assistant" } else { "User" };
+        prompt.push_str(&format!("{}: {}\n", speaker, turn.content));
+    }
+    prompt
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::{get, post};
+
+    axum::Router::new()
+        .route("/chat", post(send_message))
+        .route("/conversations/search", get(search_conversations))
+        .route("/conversations/:id", get(get_conversation))
+        .route("/conversations/:id/branches", get(list_branches))
+        .route("/conversations/:id/active", post(set_active_branch))
+        .route("/conversations/:id/messages/:message_id/edit", post(edit_message))
+        .route("/conversations/:id/messages/:message_id/regenerate", post(regenerate_message))
+        .route("/conversations/:id/summary", get(get_summary))
+        .route("/conversations/:id/summary/regenerate", post(regenerate_summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::Config;
+
+    async fn chat(state: &AppState, conversation_id: Option<&str>, message: &str) -> serde_json::Value {
+        let payload = ChatRequest {
+            conversation_id: conversation_id.map(str::to_string),
+            message: message.to_string(),
+            model: None,
+            persona_id: None,
+            priority: None,
+        };
+        let response = send_message(State(state.clone()), None, Json(payload)).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_long_chat_is_summarised() {
+        let config = Config {
+            llm_model: "mock:echo".to_string(),
+            summarization: Some(SummarizationConfig {
+                model: Some("mock:reply:the story so far".to_string()),
+                token_threshold: Some(20),
+                keep_recent: Some(1),
+            }),
+            ..Config::default()
+        };
+        let state = crate::test_app_state(config).await;
+
+        let first = chat(&state, None, "a first message that is long enough to count").await;
+        let id = first["conversation_id"].as_str().unwrap().to_string();
+        assert!(first["summarized_through"].is_null());
+
+        let second = chat(&state, Some(&id), "and a second one to push past the threshold").await;
+        assert_eq!(second["response"], "and a second one to push past the threshold");
+        assert_eq!(second["summarized_through"], first["message_id"]);
+
+        let conversation = conversations::get(&state.db, &id).await.unwrap().unwrap();
+        assert_eq!(conversation.summary.as_deref(), Some("the story so far"));
+
+        // Forcing a rebuild with nothing old enough to fold clears the summary
+        let settings = SummarizationConfig { keep_recent: Some(10), ..Default::default() };
+        let ctx = LlmContext::new(&state, Config { summarization: Some(settings), ..Config::default() }, None, None);
+        let mut conversation = conversation;
+        let messages = conversations::messages(&state.db, &id).await.unwrap();
+        let path = conversations::path_to(&messages, conversation.active_leaf);
+        update_summary(&state.db, &ctx, &mut conversation, &path, true).await.unwrap();
+        assert!(conversation.summary.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_persona_sticks_to_conversation() {
+        let state = crate::test_app_state(Config { llm_model: "mock:echo".to_string(), ..Config::default() }).await;
+        let fields = personas::PersonaFields {
+            name: "Narrator".to_string(),
+            model: "mock:reply:in character".to_string(),
+            system_prompt: Some("Stay in character".to_string()),
+            voice: Default::default(),
+            vault_folders: vec![],
+        };
+        let persona = personas::create(&state.db, "anonymous", &fields).await.unwrap();
+
+        let payload = ChatRequest {
+            conversation_id: None,
+            message: "hello".to_string(),
+            model: None,
+            persona_id: Some(persona.id.clone()),
+            priority: None,
+        };
+        let response = send_message(State(state.clone()), None, Json(payload)).await.unwrap();
+        let first = body_json(response).await;
+        assert_eq!(first["response"], "in character");
+        assert_eq!(first["persona_id"], persona.id.as_str());
+
+        // Later turns keep answering as the persona without naming it again
+        let id = first["conversation_id"].as_str().unwrap();
+        let second = chat(&state, Some(id), "still there?").await;
+        assert_eq!(second["response"], "in character");
+        let conversation = conversations::get(&state.db, id).await.unwrap().unwrap();
+        assert_eq!(conversation.persona_id.as_deref(), Some(persona.id.as_str()));
+        assert_eq!(conversation.model, "mock:reply:in character");
+
+        // Another user's persona is invisible
+        let other = personas::create(&state.db, "someone-else", &fields).await.unwrap();
+        let payload = ChatRequest {
+            conversation_id: None,
+            message: "hello".to_string(),
+            model: None,
+            persona_id: Some(other.id),
+            priority: None,
+        };
+        let result = send_message(State(state.clone()), None, Json(payload)).await;
+        assert_eq!(result.err(), Some(StatusCode::NOT_FOUND));
+    }
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_edit_and_regenerate_branch() {
+        let state = crate::test_app_state(Config { llm_model: "mock:echo".to_string(), ..Config::default() }).await;
+
+        let first = chat(&state, None, "hello").await;
+        let id = first["conversation_id"].as_str().unwrap().to_string();
+        let question = chat(&state, Some(&id), "what is two plus two").await;
+        let question_id = question["parent_id"].as_i64().unwrap();
+
+        // Editing the question forks a sibling branch that becomes active
+        let edit = EditMessageRequest { content: "what is three plus three".to_string(), model: None, priority: None };
+        let response = edit_message(State(state.clone()), None, Path((id.clone(), question_id)), Json(edit)).await.unwrap();
+        let edited = body_json(response).await;
+        assert_eq!(edited["response"], "what is three plus three");
+
+        // Regenerating the original answer with another model adds a third branch
+        let regenerate = RegenerateRequest { model: Some("mock:reply:four".to_string()), priority: None };
+        let original_answer = question["message_id"].as_i64().unwrap();
+        let response = regenerate_message(State(state.clone()), None, Path((id.clone(), original_answer)), Some(Json(regenerate))).await.unwrap();
+        let regenerated = body_json(response).await;
+        assert_eq!(regenerated["parent_id"], question_id);
+        assert_eq!(regenerated["response"], "four");
+
+        let branches = body_json(list_branches(State(state.clone()), None, Path(id.clone())).await.unwrap()).await;
+        let branches = branches["branches"].as_array().unwrap();
+        assert_eq!(branches.len(), 3);
+        assert_eq!(branches.iter().filter(|b| b["active"] == true).count(), 1);
+
+        // Picking the edited question switches back to the answer under it
+        let pick = SetActiveRequest { message_id: edited["parent_id"].as_i64().unwrap() };
+        let active = body_json(set_active_branch(State(state.clone()), None, Path(id.clone()), Json(pick)).await.unwrap()).await;
+        assert_eq!(active["active_leaf"], edited["message_id"]);
+        assert_eq!(active["active_path"].as_array().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_failed_reply_is_retried_not_stacked() {
+        let state = crate::test_app_state(Config { llm_model: "mock:echo".to_string(), ..Config::default() }).await;
+        let first = chat(&state, None, "hello").await;
+        let id = first["conversation_id"].as_str().unwrap().to_string();
+
+        let failing = ChatRequest {
+            conversation_id: Some(id.clone()),
+            message: "are you there".to_string(),
+            model: Some("mock:error:500".to_string()),
+            persona_id: None,
+            priority: None,
+        };
+        let response = send_message(State(state.clone()), None, Json(failing)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+        let retried = chat(&state, Some(&id), "are you there").await;
+        let path = load_path(&state.db, &id, retried["message_id"].as_i64()).await.unwrap();
+        let roles: Vec<&str> = path.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["user", "assistant", "user", "assistant"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_messages() {
+        let state = crate::test_app_state(Config { llm_model: "mock:echo".to_string(), ..Config::default() }).await;
+        let first = chat(&state, None, "the capital of Australia is Canberra").await;
+        chat(&state, None, "something unrelated").await;
+
+        let search = |q: &str, model: Option<&str>| SearchQuery {
+            q: q.to_string(),
+            model: model.map(str::to_string),
+            mode: Some("chat".to_string()),
+            since: Some("2000-01-01".to_string()),
+            until: None,
+            limit: None,
+        };
+        let response = search_conversations(State(state.clone()), None, Query(search("canberra", None))).await.unwrap();
+        let json = body_json(response).await;
+        // The user message and its echoed reply both match
+        assert_eq!(json["total"], 2);
+        assert_eq!(json["results"][0]["hit"]["conversation_id"], first["conversation_id"]);
+        assert!(json["results"][0]["hit"]["snippet"].as_str().unwrap().contains("<mark>Canberra</mark>"));
+
+        let response = search_conversations(State(state.clone()), None, Query(search("canberra \"", Some("mock:echo")))).await.unwrap();
+        let json = body_json(response).await;
+        assert_eq!(json["total"], 1);
+        assert_eq!(json["results"][0]["hit"]["message_id"], first["message_id"]);
+    }
+
+    #[test]
+    fn test_messages_to_fold() {
+        let message = |id, content: &str| StoredMessage {
+            id,
+            conversation_id: "c".to_string(),
+            parent_id: None,
+            role: "user".to_string(),
+            content: content.to_string(),
+            model: None,
+            created_at: 0,
+        };
+        let pending: Vec<_> = (1..=5).map(|id| message(id, "twelve chars")).collect();
+        let settings = SummarizationConfig { model: None, token_threshold: Some(10), keep_recent: Some(2) };
+
+        assert_eq!(messages_to_fold(None, &pending, &settings), 3);
+        assert_eq!(messages_to_fold(None, &pending[..2], &settings), 0);
+        assert_eq!(messages_to_fold(None, &pending[..1], &SummarizationConfig::default()), 0);
+    }
+}
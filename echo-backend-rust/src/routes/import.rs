@@ -0,0 +1,210 @@
+// src/routes/import.rs
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Query, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use crate::AppState;
+use crate::auth::{user_id, AuthError, Claims};
+use crate::db::conversations;
+use crate::features;
+use crate::models::chat_import::{parse_export, ExportSource, ImportedConversation};
+use crate::routes::llm::vault_file_path;
+use crate::state::vault_state::VaultMetadata;
+use crate::vault::is_public_vault_path;
+
+// Years of history make for a large conversations.json, but it is parsed in memory
+const MAX_IMPORT_BODY_BYTES: usize = 64 * 1024 * 1024;
+const NOTE_TITLE_MAX_CHARS: usize = 60;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub notes: bool,           // also write each conversation into the vault as Markdown
+    pub folder: Option<String>, // vault-relative; defaults to Private/Imports/<service>
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub source: ExportSource,
+    pub imported: usize,
+    pub skipped: usize, // already imported by this user
+    pub conversation_ids: Vec<String>,
+    pub notes: Vec<PathBuf>,
+}
+
+// POST /llm/import
+pub async fn import_conversations(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> Result<Response, StatusCode> {
+    let user = user_id(&claims);
+    let may_write_vault = claims.as_ref()
+        .is_some_and(|Extension(c)| features::has_feature(c, features::VAULT_WRITE));
+    if query.notes && !may_write_vault {
+        return Ok(AuthError::InsufficientPermissions.into_response());
+    }
+    let vault_path = state.vault_state.read().await.vault_path.clone();
+
+    if let Some(folder) = &query.folder {
+        vault_file_path(&vault_path, folder).ok_or(StatusCode::BAD_REQUEST)?;
+    }
+    let vault = query.notes.then_some(vault_path.as_path());
+
+    let report = match import_export(&state.db, &user, &body, vault, query.folder.as_deref()).await {
+        Ok(report) => report,
+        Err(message) => {
+            return Ok((StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message }))).into_response());
+        },
+    };
+
+    // The watcher doesn't feed the indexer yet, so register the new notes directly
+    if !report.notes.is_empty() {
+        if let Some(indexer) = &state.vault_state.read().await.indexer {
+            for path in &report.notes {
+                if let Err(e) = indexer.update_note(path).await {
+                    tracing::warn!("Indexing imported note {} failed: {}", path.display(), e);
+                }
+            }
+        }
+        let mut vault_state = state.vault_state.write().await;
+        for path in &report.notes {
+            if let Ok(content) = tokio::fs::read_to_string(path).await {
+                vault_state.indexed_files.insert(path.clone(), note_metadata(&vault_path, path, content));
+            }
+        }
+    }
+
+    Ok(Json(report).into_response())
+}
+
+/// Store every new conversation in an export and optionally write them into the vault.
+/// Shared by the HTTP route and the `import` command.
+pub(crate) async fn import_export(
+    pool: &SqlitePool,
+    user: &str,
+    data: &[u8],
+    vault: Option<&Path>,
+    folder: Option<&str>,
+) -> Result<ImportReport, String> {
+    let (source, imported) = parse_export(data)?;
+    let notes_dir = vault.map(|vault| match folder {
+        Some(folder) => vault.join(folder),
+        None => vault.join("Private").join("Imports").join(source.label()),
+    });
+
+    let mut report = ImportReport {
+        source,
+        imported: 0,
+        skipped: 0,
+        conversation_ids: Vec::new(),
+        notes: Vec::new(),
+    };
+
+    for conversation in &imported {
+        let existing = conversations::find_by_source(pool, user, source.as_str(), &conversation.source_id).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if existing.is_some() {
+            report.skipped += 1;
+            continue;
+        }
+
+        let stored = conversations::import(pool, user, source.as_str(), conversation).await
+            .map_err(|e| format!("Failed to store conversation {}: {}", conversation.source_id, e))?;
+        report.imported += 1;
+        report.conversation_ids.push(stored.id);
+
+        if let Some(dir) = &notes_dir {
+            let path = dir.join(note_file_name(conversation));
+            tokio::fs::create_dir_all(dir).await
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            tokio::fs::write(&path, conversation.to_markdown(source)).await
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            report.notes.push(path);
+        }
+    }
+
+    Ok(report)
+}
+
+// "2024-03-01 Rust lifetimes (conv-1a2).md": dated, readable and unique per source id
+fn note_file_name(conversation: &ImportedConversation) -> String {
+    let date = chrono::DateTime::from_timestamp(conversation.created_at, 0)
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let title: String = conversation.title.as_deref().unwrap_or("Untitled")
+        .chars()
+        .filter(|c| !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '[' | ']'))
+        .take(NOTE_TITLE_MAX_CHARS)
+        .collect();
+    let id: String = conversation.source_id.chars().filter(char::is_ascii_alphanumeric).take(8).collect();
+    format!("{} {} ({}).md", date, title.trim(), id)
+}
+
+fn note_metadata(vault_path: &Path, path: &Path, content: String) -> VaultMetadata {
+    VaultMetadata {
+        title: path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+        tags: vec!["imported".to_string()],
+        content,
+        last_modified: chrono::Utc::now(),
+        is_public: path.strip_prefix(vault_path).is_ok_and(is_public_vault_path),
+    }
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::post;
+
+    axum::Router::new()
+        .route("/import", post(import_conversations)
+            .layer(DefaultBodyLimit::max(MAX_IMPORT_BODY_BYTES)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_import_deduplicates_and_writes_notes() {
+        let pool = crate::db::test_pool().await;
+        let vault = tempfile::tempdir().unwrap();
+        let export = serde_json::json!([{
+            "uuid": "claude-1",
+            "name": "Garden plans",
+            "created_at": "2024-03-01T10:00:00Z",
+            "updated_at": "2024-03-02T09:00:00Z",
+            "chat_messages": [
+                { "sender": "human", "text": "When should I plant tomatoes?", "created_at": "2024-03-01T10:00:00Z" },
+                { "sender": "assistant", "text": "After the last frost.", "created_at": "2024-03-01T10:00:04Z" }
+            ]
+        }]).to_string();
+
+        let first = import_export(&pool, "alice", export.as_bytes(), Some(vault.path()), None).await.unwrap();
+        assert_eq!((first.imported, first.skipped), (1, 0));
+        assert_eq!(first.notes.len(), 1);
+        assert!(first.notes[0].starts_with(vault.path().join("Private/Imports/Claude")));
+        let note = std::fs::read_to_string(&first.notes[0]).unwrap();
+        assert!(note.contains("After the last frost."));
+
+        let conversation = conversations::get(&pool, &first.conversation_ids[0]).await.unwrap().unwrap();
+        assert_eq!(conversation.source_id.as_deref(), Some("claude-1"));
+        assert_eq!(conversation.updated_at, 1709370000);
+        let messages = conversations::messages(&pool, &conversation.id).await.unwrap();
+        assert_eq!(messages[1].parent_id, Some(messages[0].id));
+        assert_eq!(conversation.active_leaf, Some(messages[1].id));
+        assert_eq!(messages[1].created_at, 1709287204);
+
+        // Importing the same export again adds nothing, but another user gets their own copy
+        let again = import_export(&pool, "alice", export.as_bytes(), None, None).await.unwrap();
+        assert_eq!((again.imported, again.skipped), (0, 1));
+        let other = import_export(&pool, "bob", export.as_bytes(), None, None).await.unwrap();
+        assert_eq!(other.imported, 1);
+    }
+}
@@ -1,12 +1,29 @@
 // src/routes/llm.rs
 use axum::{
-    extract::State,
+    extract::{DefaultBodyLimit, Multipart, State},
+    Extension,
     response::{IntoResponse, Response, Json},
+    response::sse::{Event, KeepAlive, Sse},
     http::StatusCode,
 };
 use serde::{Deserialize, Serialize};
+use futures::StreamExt;
 use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
 use crate::AppState;
+use crate::auth::{user_id, Claims};
+use crate::features;
+use crate::state::llm_queue::{LlmQueue, Priority, QueueRequest};
+use crate::db::conversations;
+use crate::db::personas::Persona;
+use crate::routes::personas::load_persona;
+use crate::db::audit::{self, NewAuditEntry};
+use crate::models::llm::{ChatMessage, ChatRole, GenerationParams, ImageAttachment, ProviderExchange};
+use crate::models::redaction::Redactor;
+use crate::vault::is_public_vault_path;
+use crate::models::mock_llm::{MockBehavior, MockScript};
+use crate::models::ollama::OllamaClient;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelInfo {
@@ -15,6 +32,7 @@ pub struct ModelInfo {
     pub modified: String,
     pub active: bool,
     pub model_type: ModelType,
+    pub vision: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,14 +42,28 @@ pub enum ModelType {
     OpenAI,     // GPT-3.5, GPT-4, etc.
     Claude,     // Anthropic Claude
     Proxy,      // External microservices
+    Mock,       // Scripted responses for offline development and tests
     Custom,     // Future expansion
 }
 
 #[derive(Debug, Deserialize)]
 pub struct SetModelRequest {
     pub model: String,
-    pub model_type: ModelType,
+    pub model_type: Option<ModelType>, // Inferred from the model name when omitted
     pub as_primary: bool,
+    #[serde(default)]
+    pub pull_if_missing: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LocalModelRequest {
+    pub model: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CopyModelRequest {
+    pub source: String,
+    pub destination: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -39,6 +71,22 @@ pub struct ConversationRequest {
     pub prompt: String,
     pub models: Vec<String>,
     pub mode: ConversationMode,
+    pub iterations: Option<u32>,          // critique: revision rounds
+    pub input: Option<String>,            // map_reduce: long text to split
+    pub vault_notes: Option<Vec<String>>, // map_reduce: vault-relative note paths
+    pub chunk_size: Option<usize>,        // map_reduce: max characters per chunk
+    pub synthesis_model: Option<String>,  // map_reduce: reducer model
+    pub priority: Option<Priority>,
+    pub images: Option<Vec<ImageInput>>,  // sent to every model in the conversation
+    pub persona_id: Option<String>,
+}
+
+/// An image attached to a conversation, inline or by reference to a vault file
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ImageInput {
+    Vault { vault_path: String },
+    Inline { data: String }, // base64 or a data: URL
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,16 +96,33 @@ pub enum ConversationMode {
     Consensus,
     Debate,
     Collaborative,
+    Critique,
+    #[serde(rename = "map_reduce")]
+    MapReduce,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ModelResponse {
     pub model: String,
     pub response: String,
     pub timestamp: u64,
     pub thinking_time_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<String>,
 }
 
+const DEFAULT_CRITIQUE_ITERATIONS: u32 = 1;
+const MAX_CRITIQUE_ITERATIONS: u32 = 5;
+const DEFAULT_CHUNK_SIZE: usize = 4000;
+const MIN_CHUNK_SIZE: usize = 200;
+// Each chunk is a provider call; bigger inputs need a larger chunk_size
+const MAX_MAP_CHUNKS: usize = 64;
+const MAP_CONCURRENCY: usize = 4;
+// Base64 inflates images by a third, so conversation bodies get more room than the default 2MB
+const MAX_CONVERSATION_BODY_BYTES: usize = 32 * 1024 * 1024;
+// Local model families that ship with a vision encoder
+const OLLAMA_VISION_HINTS: &[&str] = &["llava", "moondream", "vision", "minicpm-v", "qwen2.5vl", "gemma3"];
+
 // Model routing helper
 #[derive(Debug)]
 enum ModelRouting {
@@ -65,10 +130,13 @@ enum ModelRouting {
     OpenAI(String),
     Anthropic(String),
     Proxy { provider: String, model: String },
+    Mock(String),
 }
 
 impl ModelRouting {
-    fn parse(model_name: &str) -> Self {
+    // Aliases and profiles are resolved to the underlying model first
+    fn parse(model_name: &str, config: &crate::models::config::Config) -> Self {
+        let model_name = config.resolve_model_name(model_name);
         if model_name.starts_with("proxy:") {
             let parts: Vec<&str> = model_name.splitn(3, ':').collect();
             if parts.len() == 3 {
@@ -77,6 +145,8 @@ impl ModelRouting {
                     model: parts[2].to_string(),
                 };
             }
+        } else if let Some(script) = model_name.strip_prefix("mock:").filter(|_| config.mock_enabled()) {
+            return ModelRouting::Mock(script.to_string());
         } else if model_name.starts_with("gpt-") {
             return ModelRouting::OpenAI(model_name.to_string());
         } else if model_name.starts_with("claude-") {
@@ -86,6 +156,81 @@ impl ModelRouting {
         // Default to Ollama for everything else
         ModelRouting::Ollama(model_name.to_string())
     }
+
+    fn model_name(&self) -> &str {
+        match self {
+            ModelRouting::Ollama(model)
+            | ModelRouting::OpenAI(model)
+            | ModelRouting::Anthropic(model)
+            | ModelRouting::Proxy { model, .. }
+            | ModelRouting::Mock(model) => model,
+        }
+    }
+
+    // A model name that parses back to this same routing
+    fn spec(&self) -> String {
+        match self {
+            ModelRouting::Proxy { provider, model } => format!("proxy:{}:{}", provider, model),
+            ModelRouting::Mock(script) => format!("mock:{}", script),
+            other => other.model_name().to_string(),
+        }
+    }
+
+    fn model_type(&self) -> ModelType {
+        match self {
+            ModelRouting::Ollama(_) => ModelType::Local,
+            ModelRouting::OpenAI(_) => ModelType::OpenAI,
+            ModelRouting::Anthropic(_) => ModelType::Claude,
+            ModelRouting::Proxy { .. } => ModelType::Proxy,
+            ModelRouting::Mock(_) => ModelType::Mock,
+        }
+    }
+
+    fn provider_name(&self) -> &'static str {
+        match self {
+            ModelRouting::Ollama(_) => "Ollama",
+            ModelRouting::OpenAI(_) => "OpenAI",
+            ModelRouting::Anthropic(_) => "Anthropic",
+            ModelRouting::Proxy { .. } => "Proxy",
+            ModelRouting::Mock(_) => "Mock",
+        }
+    }
+
+    // Cloud providers can't be called without an API key
+    fn has_credentials(&self, config: &crate::models::config::Config) -> bool {
+        match self {
+            ModelRouting::OpenAI(_) => config.openai_key.is_some(),
+            ModelRouting::Anthropic(_) => config.anthropic_key.is_some(),
+            _ => true,
+        }
+    }
+
+    // Guessed from well-known model names; `vision_models` in the config covers the rest
+    fn supports_images(&self, config: &crate::models::config::Config) -> bool {
+        if config.vision_models.iter().flatten().any(|m| m == self.model_name()) {
+            return true;
+        }
+        
+        match self {
+            ModelRouting::Ollama(model) => {
+                let family = model.split(':').next().unwrap_or(model);
+                OLLAMA_VISION_HINTS.iter().any(|hint| family.contains(hint))
+            },
+            ModelRouting::OpenAI(model) => {
+                model.starts_with("gpt-4o") || model.starts_with("gpt-4.1")
+                    || model.starts_with("gpt-4-turbo") || model.contains("vision")
+            },
+            ModelRouting::Anthropic(model) => {
+                !model.starts_with("claude-2") && !model.starts_with("claude-instant")
+            },
+            ModelRouting::Proxy { provider, .. } => {
+                config.proxy_providers.iter()
+                    .flatten()
+                    .any(|p| &p.name == provider && p.supports_images.unwrap_or(false))
+            },
+            ModelRouting::Mock(_) => true,
+        }
+    }
 }
 
 // GET /llm/models
@@ -111,6 +256,7 @@ pub async fn get_models(
                     size: parts[parts.len() - 2].to_string(),
                     modified: parts[parts.len() - 1].to_string(),
                     active: false,
+                    vision: false,
                     model_type: ModelType::Local,
                 });
             }
@@ -131,6 +277,7 @@ pub async fn get_models(
                 size: "API".to_string(),
                 modified: "latest".to_string(),
                 active: false,
+                vision: false,
                 model_type: ModelType::OpenAI,
             },
             ModelInfo {
@@ -138,6 +285,15 @@ pub async fn get_models(
                 size: "API".to_string(),
                 modified: "latest".to_string(),
                 active: false,
+                vision: false,
+                model_type: ModelType::OpenAI,
+            },
+            ModelInfo {
+                name: "gpt-4o".to_string(),
+                size: "API".to_string(),
+                modified: "latest".to_string(),
+                active: false,
+                vision: false,
                 model_type: ModelType::OpenAI,
             },
             ModelInfo {
@@ -145,6 +301,7 @@ pub async fn get_models(
                 size: "API".to_string(),
                 modified: "latest".to_string(),
                 active: false,
+                vision: false,
                 model_type: ModelType::OpenAI,
             },
         ]);
@@ -157,6 +314,7 @@ pub async fn get_models(
                 size: "API".to_string(),
                 modified: "latest".to_string(),
                 active: false,
+                vision: false,
                 model_type: ModelType::Claude,
             },
             ModelInfo {
@@ -164,6 +322,7 @@ pub async fn get_models(
                 size: "API".to_string(),
                 modified: "latest".to_string(),
                 active: false,
+                vision: false,
                 model_type: ModelType::Claude,
             },
             ModelInfo {
@@ -171,6 +330,7 @@ pub async fn get_models(
                 size: "API".to_string(),
                 modified: "latest".to_string(),
                 active: false,
+                vision: false,
                 model_type: ModelType::Claude,
             },
         ]);
@@ -184,60 +344,126 @@ pub async fn get_models(
                 size: "Proxy".to_string(),
                 modified: "external".to_string(),
                 active: false,
+                vision: false,
                 model_type: ModelType::Proxy,
             });
         }
     }
     
-    // Mark active model
+    // Add mock models when the provider is enabled and a fixtures directory is configured
+    if let Some(fixtures_dir) = config.mock_fixtures_dir.as_ref().filter(|_| config.mock_enabled()) {
+        let mut mock_models = vec!["mock:echo".to_string()];
+        if let Ok(mut entries) = tokio::fs::read_dir(fixtures_dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                mock_models.push(format!("mock:fixture:{}", entry.file_name().to_string_lossy()));
+            }
+        }
+        
+        for name in mock_models {
+            all_models.push(ModelInfo {
+                name,
+                size: "Mock".to_string(),
+                modified: "scripted".to_string(),
+                active: false,
+                vision: false,
+                model_type: ModelType::Mock,
+            });
+        }
+    }
+    
+    // Mark active model and which models accept images
     let current_model = config.llm_model.clone();
+    let resolved_model = config.resolve_model_name(&current_model);
     for model in &mut all_models {
-        if model.name == current_model {
+        if model.name == current_model || model.name == resolved_model {
             model.active = true;
         }
+        model.vision = ModelRouting::parse(&model.name, &config).supports_images(&config);
     }
     
+    // Aliases and profiles, with the concrete model each one resolves to
+    let aliases: HashMap<_, _> = config.model_aliases.iter()
+        .flatten()
+        .map(|(name, target)| (name.clone(), serde_json::json!({
+            "target": target,
+            "resolves_to": config.resolve_model_name(name),
+            "vision": ModelRouting::parse(name, &config).supports_images(&config),
+        })))
+        .collect();
+    let profiles: HashMap<_, _> = config.model_profiles.iter()
+        .flatten()
+        .map(|(name, profile)| (name.clone(), serde_json::json!({
+            "profile": profile,
+            "resolves_to": config.resolve_model_name(name),
+            "vision": ModelRouting::parse(name, &config).supports_images(&config),
+        })))
+        .collect();
+    
     Ok(Json(serde_json::json!({
         "models": all_models,
         "primary": current_model,
+        "aliases": aliases,
+        "profiles": profiles,
     })).into_response())
 }
 
 // POST /llm/use
+//
+// Pulling a missing local model downloads gigabytes, so it needs `llm.admin`
+// like POST /llm/local/pull.
 pub async fn set_model(
     State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
     Json(payload): Json<SetModelRequest>,
-) -> Result<StatusCode, StatusCode> {
-    match payload.model_type {
+) -> Result<Response, StatusCode> {
+    let may_pull = claims.as_ref()
+        .is_some_and(|Extension(c)| features::has_feature(c, features::LLM_ADMIN));
+    if payload.pull_if_missing && !may_pull {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let config = {
+        let runtime_state = state.runtime_state.read().await;
+        runtime_state.config.clone()
+    };
+    
+    // Aliases and profiles are validated against the model they resolve to
+    let resolved = config.resolve_model_name(&payload.model).to_string();
+    let model_type = payload.model_type.clone()
+        .unwrap_or_else(|| ModelRouting::parse(&payload.model, &config).model_type());
+    
+    match model_type {
         ModelType::Local => {
-            let check = tokio::process::Command::new("ollama")
-                .arg("show")
-                .arg(&payload.model)
-                .output()
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let ollama = OllamaClient::new(&config);
+            let installed = ollama.has_model(&resolved).await
+                .map_err(|_| StatusCode::BAD_GATEWAY)?;
             
-            if !check.status.success() {
-                return Err(StatusCode::NOT_FOUND);
+            if !installed {
+                if !payload.pull_if_missing {
+                    return Ok((StatusCode::NOT_FOUND, Json(serde_json::json!({
+                        "error": format!("Model '{}' is not installed", resolved),
+                        "pull_available": true,
+                        "hint": "Retry with \"pull_if_missing\": true, or stream the download from POST /llm/local/pull",
+                    }))).into_response());
+                }
+                
+                ollama.pull(&resolved).await
+                    .map_err(|_| StatusCode::BAD_GATEWAY)?;
             }
         },
         ModelType::OpenAI => {
-            let runtime_state = state.runtime_state.read().await;
-            if runtime_state.config.openai_key.is_none() {
+            if config.openai_key.is_none() {
                 return Err(StatusCode::UNAUTHORIZED);
             }
         },
         ModelType::Claude => {
-            let runtime_state = state.runtime_state.read().await;
-            if runtime_state.config.anthropic_key.is_none() {
+            if config.anthropic_key.is_none() {
                 return Err(StatusCode::UNAUTHORIZED);
             }
         },
         ModelType::Proxy => {
             // Verify proxy provider exists
-            let runtime_state = state.runtime_state.read().await;
-            if let Some(providers) = &runtime_state.config.proxy_providers {
-                let provider_name = payload.model.split(':').nth(1).unwrap_or("");
+            if let Some(providers) = &config.proxy_providers {
+                let provider_name = resolved.split(':').nth(1).unwrap_or("");
                 if !providers.iter().any(|p| p.name == provider_name) {
                     return Err(StatusCode::NOT_FOUND);
                 }
@@ -245,10 +471,17 @@ pub async fn set_model(
                 return Err(StatusCode::NOT_FOUND);
             }
         },
+        ModelType::Mock => {
+            // Saved anyway, a mock model would be routed to Ollama while the provider is off
+            let script = resolved.strip_prefix("mock:").unwrap_or(&resolved);
+            if !config.mock_enabled() || MockScript::parse(script).is_err() {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+        },
         _ => return Err(StatusCode::NOT_IMPLEMENTED),
     }
     
-    // Update config
+    // Update config; the alias or profile name is kept so retargeting it takes effect
     {
         let mut runtime_state = state.runtime_state.write().await;
         if payload.as_primary {
@@ -259,161 +492,692 @@ pub async fn set_model(
     state.save_config().await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    Ok(StatusCode::OK)
+    Ok(StatusCode::OK.into_response())
+}
+
+// POST /llm/local/pull - streams layer progress as SSE
+pub async fn pull_local_model(
+    State(state): State<AppState>,
+    Json(payload): Json<LocalModelRequest>,
+) -> Result<Response, StatusCode> {
+    let ollama = {
+        let runtime_state = state.runtime_state.read().await;
+        OllamaClient::new(&runtime_state.config)
+    };
+    let progress = ollama.pull_stream(&payload.model).await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?;
+    
+    let stream = async_stream::stream! {
+        let mut progress = Box::pin(progress);
+        while let Some(update) = progress.next().await {
+            match update {
+                Ok(update) => {
+                    if let Some(error) = update.error {
+                        yield Ok::<_, Infallible>(Event::default().event("error").data(error));
+                        return;
+                    }
+                    let data = serde_json::to_string(&update).unwrap_or_default();
+                    yield Ok(Event::default().event("progress").data(data));
+                }
+                Err(e) => {
+                    yield Ok(Event::default().event("error").data(e.to_string()));
+                    return;
+                }
+            }
+        }
+        yield Ok(Event::default().event("done").data("complete"));
+    };
+    
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()).into_response())
+}
+
+// POST /llm/local/delete
+pub async fn delete_local_model(
+    State(state): State<AppState>,
+    Json(payload): Json<LocalModelRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let ollama = {
+        let runtime_state = state.runtime_state.read().await;
+        OllamaClient::new(&runtime_state.config)
+    };
+    
+    match ollama.delete(&payload.model).await {
+        Ok(true) => Ok(StatusCode::OK),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::BAD_GATEWAY),
+    }
+}
+
+// POST /llm/local/copy
+pub async fn copy_local_model(
+    State(state): State<AppState>,
+    Json(payload): Json<CopyModelRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let ollama = {
+        let runtime_state = state.runtime_state.read().await;
+        OllamaClient::new(&runtime_state.config)
+    };
+    
+    match ollama.copy(&payload.source, &payload.destination).await {
+        Ok(true) => Ok(StatusCode::OK),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::BAD_GATEWAY),
+    }
 }
 
 // POST /llm/conversation
 pub async fn multi_model_conversation(
     State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
     Json(payload): Json<ConversationRequest>,
 ) -> Result<Response, StatusCode> {
-    let mut responses = Vec::new();
+    run_conversation(state, claims, payload, Vec::new()).await
+}
+
+// POST /llm/conversation/upload
+// Multipart form: a `request` field with the ConversationRequest JSON plus any number of `image` files
+pub async fn multi_model_conversation_upload(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    mut multipart: Multipart,
+) -> Result<Response, StatusCode> {
+    let mut payload = None;
+    let mut uploads = Vec::new();
+    
+    while let Some(field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+        match field.name() {
+            Some("request") => {
+                let text = field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?;
+                payload = Some(serde_json::from_str::<ConversationRequest>(&text)
+                    .map_err(|_| StatusCode::BAD_REQUEST)?);
+            },
+            Some("image") | Some("images") => {
+                let file_name = field.file_name().unwrap_or("upload").to_string();
+                let bytes = field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?;
+                match ImageAttachment::from_bytes(&bytes) {
+                    Some(image) => uploads.push(image),
+                    None => return Ok(image_error(format!("'{}' is not a PNG, JPEG, GIF or WebP image", file_name))),
+                }
+            },
+            _ => {},
+        }
+    }
+    
+    let payload = payload.ok_or(StatusCode::BAD_REQUEST)?;
+    run_conversation(state, claims, payload, uploads).await
+}
+
+async fn run_conversation(
+    state: AppState,
+    claims: Option<Extension<Claims>>,
+    mut payload: ConversationRequest,
+    mut images: Vec<ImageAttachment>,
+) -> Result<Response, StatusCode> {
+    let started = std::time::Instant::now();
     
     // Clone config to avoid holding lock during async operations
     let config = {
         let runtime_state = state.runtime_state.read().await;
         runtime_state.config.clone()
     };
+    let vault_path = state.vault_state.read().await.vault_path.clone();
+    let mut ctx = LlmContext::new(&state, config, claims, payload.priority);
     
-    // Process each model
-    for model_name in &payload.models {
-        let start_time = std::time::Instant::now();
-        
-        let response_text = match ModelRouting::parse(model_name) {
-            ModelRouting::Ollama(model) => {
-                call_ollama_model(&model, &payload.prompt).await
-                    .unwrap_or_else(|e| format!("Ollama error: {}", e))
-            },
-            ModelRouting::OpenAI(model) => {
-                if let Some(key) = &config.openai_key {
-                    call_openai_api(&model, &payload.prompt, key).await
-                        .unwrap_or_else(|e| format!("OpenAI error: {}", e))
-                } else {
-                    "OpenAI API key not configured".to_string()
-                }
-            },
-            ModelRouting::Anthropic(model) => {
-                if let Some(key) = &config.anthropic_key {
-                    call_anthropic_api(&model, &payload.prompt, key).await
-                        .unwrap_or_else(|e| format!("Anthropic error: {}", e))
-                } else {
-                    "Anthropic API key not configured".to_string()
-                }
-            },
-            ModelRouting::Proxy { provider, model } => {
-                call_proxy_model(&provider, &model, &payload.prompt, &config).await
-                    .unwrap_or_else(|e| format!("Proxy error: {}", e))
-            },
-        };
-        
-        let elapsed = start_time.elapsed();
-        
-        responses.push(ModelResponse {
-            model: model_name.clone(),
-            response: response_text,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            thinking_time_ms: elapsed.as_millis() as u64,
-        });
+    // A persona supplies the model when none are named, briefs every call and
+    // limits which vault notes and images may be pulled in
+    let persona = match &payload.persona_id {
+        Some(persona_id) => Some(load_persona(&state.db, persona_id, &ctx.user).await?),
+        None => None,
+    };
+    if let Some(persona) = &persona {
+        if payload.models.is_empty() {
+            payload.models.push(persona.model.clone());
+        }
+        let notes_allowed = payload.vault_notes.iter()
+            .flatten()
+            .all(|note| persona.allows_vault_path(std::path::Path::new(note)));
+        if !notes_allowed {
+            return Err(StatusCode::FORBIDDEN);
+        }
+        ctx.persona_prompt = persona.system_prompt.clone();
+    }
+    
+    if let Some(inputs) = &payload.images {
+        match resolve_images(inputs, &vault_path, &ctx, persona.as_ref()).await {
+            Ok(resolved) => images.extend(resolved),
+            Err((status, message)) => return Ok((status, Json(serde_json::json!({ "error": message }))).into_response()),
+        }
+    }
+    ctx.images = images.into();
+    
+    // Refuse up front rather than silently dropping the images for text-only models
+    if !ctx.images.is_empty() {
+        let text_only: Vec<_> = payload.models.iter()
+            .chain(payload.synthesis_model.iter())
+            .filter(|model| !ctx.accepts_images(model))
+            .cloned()
+            .collect();
+        if !text_only.is_empty() {
+            return Ok((StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("Images were attached but these models only accept text: {}", text_only.join(", ")),
+                "text_only_models": text_only,
+            }))).into_response());
+        }
     }
     
-    // Process conversation mode
     let final_response = match payload.mode {
-        ConversationMode::Sequential => responses,
-        ConversationMode::Debate => {
-            process_debate_mode(responses, &payload.models, &config).await
+        // These modes drive their own model calls instead of fanning out the prompt
+        ConversationMode::Critique => {
+            if payload.models.is_empty() {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            process_critique_mode(&payload, &ctx).await
         },
-        ConversationMode::Collaborative => {
-            process_collaborative_mode(responses, &payload.models, &config).await
+        ConversationMode::MapReduce => {
+            if payload.models.is_empty() {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            process_map_reduce_mode(&payload, &vault_path, &ctx).await?
+        },
+        _ => {
+            // Process each model
+            let mut responses = Vec::new();
+            for model_name in &payload.models {
+                responses.push(run_model_step(model_name, &payload.prompt, &ctx, None).await);
+            }
+            
+            // Process conversation mode
+            match payload.mode {
+                ConversationMode::Debate => {
+                    process_debate_mode(responses, &payload.models, &ctx).await
+                },
+                ConversationMode::Collaborative => {
+                    process_collaborative_mode(responses, &payload.models, &ctx).await
+                },
+                ConversationMode::Consensus => {
+                    process_consensus_mode(responses, &payload.models, &ctx).await
+                },
+                _ => responses,
+            }
         },
-        ConversationMode::Consensus => {
-            process_consensus_mode(responses, &payload.models, &config).await
+    };
+    
+    // Keep the run searchable; losing the history shouldn't fail the request
+    let conversation_id = match save_run(&state.db, &ctx.user, &payload, &final_response).await {
+        Ok(id) => Some(id),
+        Err(e) => {
+            tracing::warn!("Failed to store conversation run: {}", e);
+            None
         },
     };
     
     Ok(Json(serde_json::json!({
+        "conversation_id": conversation_id,
         "mode": payload.mode,
         "responses": final_response,
         "total_models": payload.models.len(),
+        "total_time_ms": started.elapsed().as_millis() as u64,
     })).into_response())
 }
 
-// Helper functions
-async fn call_ollama_model(model: &str, prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let output = tokio::process::Command::new("ollama")
-        .arg("run")
-        .arg(model)
-        .arg(prompt)
-        .output()
-        .await?;
+// Store the prompt with every response as a reply to it
+async fn save_run(
+    pool: &sqlx::SqlitePool,
+    user: &str,
+    payload: &ConversationRequest,
+    responses: &[ModelResponse],
+) -> Result<String, sqlx::Error> {
+    let mode = serde_json::to_value(&payload.mode).ok()
+        .and_then(|mode| mode.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let model = payload.models.first().map(String::as_str).unwrap_or_default();
+    let title: String = payload.prompt.chars().take(60).collect();
+    
+    let conversation = conversations::create(pool, user, model, &mode, Some(title.trim())).await?;
+    let prompt = conversations::add_message(pool, &conversation.id, None, ChatRole::User, &payload.prompt, None).await?;
+    for response in responses {
+        conversations::add_message(
+            pool, &conversation.id, Some(prompt.id), ChatRole::Assistant, &response.response, Some(&response.model),
+        ).await?;
+    }
+    Ok(conversation.id)
+}
+
+fn image_error(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+// Vault images follow the same scope rule as vault notes in map_reduce
+async fn resolve_images(
+    inputs: &[ImageInput],
+    vault_path: &std::path::Path,
+    ctx: &LlmContext,
+    persona: Option<&Persona>,
+) -> Result<Vec<ImageAttachment>, (StatusCode, String)> {
+    let invalid = |message: String| (StatusCode::BAD_REQUEST, message);
+    let mut images = Vec::new();
+    for input in inputs {
+        let image = match input {
+            ImageInput::Vault { vault_path: note } => {
+                let path = vault_file_path(vault_path, note)
+                    .ok_or_else(|| invalid(format!("Invalid vault path '{}'", note)))?;
+                if !is_public_vault_path(std::path::Path::new(note)) && !ctx.read_private {
+                    return Err((StatusCode::FORBIDDEN, format!("Vault attachment '{}' is private", note)));
+                }
+                if persona.is_some_and(|persona| !persona.allows_vault_path(std::path::Path::new(note))) {
+                    return Err((StatusCode::FORBIDDEN, format!("Vault attachment '{}' is outside the persona's folders", note)));
+                }
+                let bytes = tokio::fs::read(&path).await
+                    .map_err(|_| invalid(format!("Vault attachment '{}' not found", note)))?;
+                ImageAttachment::from_bytes(&bytes)
+                    .ok_or_else(|| invalid(format!("'{}' is not a PNG, JPEG, GIF or WebP image", note)))?
+            },
+            ImageInput::Inline { data } => {
+                ImageAttachment::from_base64(data)
+                    .ok_or_else(|| invalid("Inline image is not base64-encoded PNG, JPEG, GIF or WebP data".to_string()))?
+            },
+        };
+        images.push(image);
+    }
+    Ok(images)
+}
+
+// Vault-relative paths only; absolute paths and `..` could escape the vault
+pub(crate) fn vault_file_path(vault_path: &std::path::Path, relative: &str) -> Option<std::path::PathBuf> {
+    let relative = std::path::Path::new(relative);
+    if relative.is_absolute()
+        || relative.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        return None;
+    }
+    Some(vault_path.join(relative))
+}
+
+// Per-request settings shared by every model call in a conversation
+#[derive(Clone)]
+pub(crate) struct LlmContext {
+    pub(crate) config: crate::models::config::Config,
+    queue: Arc<LlmQueue>,
+    pub(crate) user: String,
+    priority: Priority,
+    images: Arc<[ImageAttachment]>, // attached to every call in the conversation
+    pub(crate) persona_prompt: Option<String>,
+    pub(crate) overrides: GenerationParams, // take precedence over the model's profile
+    db: sqlx::SqlitePool,
+    private_texts: Arc<std::sync::Mutex<Vec<String>>>, // masked in the audit log
+    pub(crate) read_private: bool, // Private-scope notes may go into prompts
+}
+
+impl LlmContext {
+    pub(crate) fn new(
+        state: &AppState,
+        config: crate::models::config::Config,
+        claims: Option<Extension<Claims>>,
+        priority: Option<Priority>,
+    ) -> Self {
+        let read_private = claims.as_ref()
+            .is_some_and(|Extension(c)| features::has_feature(c, features::VAULT_READ_PRIVATE));
+        Self {
+            config,
+            queue: state.llm_queue.clone(),
+            user: user_id(&claims),
+            priority: priority.unwrap_or_default(),
+            images: Arc::from([]),
+            persona_prompt: None,
+            overrides: GenerationParams::default(),
+            db: state.db.clone(),
+            private_texts: Arc::default(),
+            read_private,
+        }
+    }
+    
+    /// Note text from a Private-scope vault note that is about to go into a prompt
+    pub(crate) fn mark_private(&self, text: impl Into<String>) {
+        self.private_texts.lock().unwrap().push(text.into());
+    }
+    
+    // The model itself followed by its profile's fallbacks
+    fn candidates<'a>(&'a self, model_name: &'a str) -> Vec<&'a str> {
+        let mut candidates = vec![model_name];
+        if let Some(fallbacks) = self.config.resolve_profile(model_name).and_then(|p| p.fallbacks.as_ref()) {
+            candidates.extend(fallbacks.iter().map(String::as_str));
+        }
+        candidates
+    }
+    
+    fn accepts_images(&self, model_name: &str) -> bool {
+        self.candidates(model_name).into_iter()
+            .any(|candidate| ModelRouting::parse(candidate, &self.config).supports_images(&self.config))
+    }
     
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Ollama failed: {}", stderr).into());
+    pub(crate) async fn call(&self, model_name: &str, prompt: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        self.call_chat(model_name, &[ChatMessage::user(prompt)], None).await
     }
     
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    // Resolve aliases and profiles, then try the model and its fallback chain in order.
+    // `context` is extra system-prompt text, such as a summary of earlier turns.
+    pub(crate) async fn call_chat(
+        &self,
+        model_name: &str,
+        messages: &[ChatMessage],
+        context: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let mut params = self.config.resolve_profile(model_name)
+            .map(GenerationParams::from)
+            .unwrap_or_default();
+        params.system_prompt = self.overrides.system_prompt.clone().or(params.system_prompt);
+        params.temperature = self.overrides.temperature.or(params.temperature);
+        params.max_tokens = self.overrides.max_tokens.or(params.max_tokens);
+        let system: Vec<&str> = [params.system_prompt.as_deref(), self.persona_prompt.as_deref(), context]
+            .into_iter()
+            .flatten()
+            .collect();
+        params.system_prompt = (!system.is_empty()).then(|| system.join("\n\n"));
+        
+        let mut last_error = None;
+        for candidate in self.candidates(model_name) {
+            let routing = ModelRouting::parse(candidate, &self.config);
+            if !routing.has_credentials(&self.config) {
+                last_error = Some(format!("{} API key not configured", routing.provider_name()).into());
+                continue;
+            }
+            if !self.images.is_empty() && !routing.supports_images(&self.config) {
+                last_error = Some(format!("'{}' does not accept images", candidate).into());
+                continue;
+            }
+            
+            match self.call_routed(&routing, messages, &params).await {
+                Ok(text) => return Ok(text),
+                Err(e) => {
+                    tracing::warn!("Model '{}' failed: {}", candidate, e);
+                    last_error = Some(e);
+                },
+            }
+        }
+        
+        Err(last_error.unwrap_or_else(|| "No model to call".into()))
+    }
+    
+    // Wait for a slot under the concurrency limits, then call the provider
+    async fn call_routed(
+        &self,
+        routing: &ModelRouting,
+        messages: &[ChatMessage],
+        params: &GenerationParams,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let limits = self.config.concurrency.clone().unwrap_or_default();
+        let provider = routing.provider_name().to_lowercase();
+        let model = routing.model_name().to_string();
+        
+        let _permit = self.queue.acquire(QueueRequest {
+            user: self.user.clone(),
+            priority: self.priority,
+            provider_limit: limits.provider_limit(&provider),
+            model_limit: limits.model_limit(&model),
+            provider,
+            model,
+        }).await;
+        
+        let start = std::time::Instant::now();
+        let mut exchange = ProviderExchange::default();
+        let result = call_model(routing, messages, params, &self.images, &self.config, self.read_private, &mut exchange).await;
+        self.audit(routing, messages, params, exchange, &result, start.elapsed()).await;
+        result
+    }
+    
+    // Write the call to the audit log; a logging failure never fails the call itself
+    async fn audit(
+        &self,
+        routing: &ModelRouting,
+        messages: &[ChatMessage],
+        params: &GenerationParams,
+        exchange: ProviderExchange,
+        result: &Result<String, Box<dyn std::error::Error + Send + Sync>>,
+        latency: std::time::Duration,
+    ) {
+        if !self.config.audit.as_ref().is_none_or(|audit| audit.enabled()) {
+            return;
+        }
+        let private_texts = self.private_texts.lock().unwrap().clone();
+        let redactor = Redactor::new(&self.config, private_texts);
+        
+        let mut request = serde_json::json!({
+            "messages": messages,
+            "params": params,
+            "images": self.images.len(),
+        });
+        let mut payload = exchange.payload;
+        let mut response = exchange.response;
+        let mut error = result.as_ref().err().map(|e| e.to_string());
+        
+        let mut redacted = redactor.redact(&mut request);
+        redacted |= redactor.redact(&mut payload);
+        if let Some(response) = &mut response {
+            redacted |= redactor.redact(response);
+        }
+        if let Some(error) = &mut error {
+            let (masked, changed) = redactor.redact_str(error);
+            *error = masked;
+            redacted |= changed;
+        }
+        
+        let entry = NewAuditEntry {
+            user_id: self.user.clone(),
+            provider: routing.provider_name().to_string(),
+            model: routing.spec(),
+            request,
+            payload,
+            response,
+            error,
+            latency_ms: latency.as_millis() as i64,
+            input_tokens: exchange.input_tokens,
+            output_tokens: exchange.output_tokens,
+            redacted,
+        };
+        if let Err(e) = audit::record(&self.db, &entry).await {
+            tracing::warn!("Failed to write audit log entry: {}", e);
+        }
+    }
+}
+
+// Helper functions
+
+// Call one model and record how long the step took; errors become the response text
+async fn run_model_step(
+    model_name: &str,
+    prompt: &str,
+    ctx: &LlmContext,
+    step: Option<String>,
+) -> ModelResponse {
+    let start_time = std::time::Instant::now();
+    
+    let routing = ModelRouting::parse(model_name, &ctx.config);
+    let response_text = ctx.call(model_name, prompt).await
+        .unwrap_or_else(|e| format!("{} error: {}", routing.provider_name(), e));
+    
+    ModelResponse {
+        model: model_name.to_string(),
+        response: response_text,
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        thinking_time_ms: start_time.elapsed().as_millis() as u64,
+        step,
+    }
+}
+
+async fn call_model(
+    routing: &ModelRouting,
+    messages: &[ChatMessage],
+    params: &GenerationParams,
+    images: &[ImageAttachment],
+    config: &crate::models::config::Config,
+    read_private: bool,
+    exchange: &mut ProviderExchange,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    match routing {
+        ModelRouting::Ollama(model) => {
+            Ok(OllamaClient::new(config).chat(model, messages, params, images, exchange).await?)
+        },
+        ModelRouting::OpenAI(model) => {
+            let key = config.openai_key.as_ref().ok_or("OpenAI API key not configured")?;
+            call_openai_api(model, messages, params, images, key, exchange).await
+        },
+        ModelRouting::Anthropic(model) => {
+            let key = config.anthropic_key.as_ref().ok_or("Anthropic API key not configured")?;
+            call_anthropic_api(model, messages, params, images, key, exchange).await
+        },
+        ModelRouting::Proxy { provider, model } => {
+            call_proxy_model(provider, model, messages, params, images, config, exchange).await
+        },
+        ModelRouting::Mock(script) => {
+            let prompt = latest_prompt(messages);
+            exchange.payload = serde_json::json!({ "script": script, "prompt": prompt, "system": params.system_prompt });
+            let text = call_mock_model(script, prompt, config, read_private).await?;
+            exchange.response = Some(serde_json::json!(text));
+            Ok(text)
+        },
+    }
 }
 
-async fn call_openai_api(model: &str, prompt: &str, api_key: &str) -> Result<String, Box<dyn std::error::Error>> {
+// Text of the most recent turn, for providers that take a single prompt
+fn latest_prompt(messages: &[ChatMessage]) -> &str {
+    messages.last().map(|m| m.content.as_str()).unwrap_or_default()
+}
+
+async fn call_openai_api(
+    model: &str,
+    chat: &[ChatMessage],
+    params: &GenerationParams,
+    images: &[ImageAttachment],
+    api_key: &str,
+    exchange: &mut ProviderExchange,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let mut messages = Vec::new();
+    if let Some(system) = &params.system_prompt {
+        messages.push(serde_json::json!({"role": "system", "content": system}));
+    }
+    
+    for (i, message) in chat.iter().enumerate() {
+        // Images belong to the latest turn
+        let content = if i + 1 < chat.len() || images.is_empty() {
+            serde_json::json!(message.content)
+        } else {
+            let mut parts = vec![serde_json::json!({"type": "text", "text": message.content})];
+            parts.extend(images.iter().map(|image| serde_json::json!({
+                "type": "image_url",
+                "image_url": {"url": image.data_url()},
+            })));
+            serde_json::json!(parts)
+        };
+        messages.push(serde_json::json!({"role": message.role, "content": content}));
+    }
+    
+    let mut body = serde_json::json!({
+        "model": model,
+        "messages": messages,
+        "temperature": params.temperature.unwrap_or(0.7),
+    });
+    if let Some(max_tokens) = params.max_tokens {
+        body["max_tokens"] = serde_json::json!(max_tokens);
+    }
+    exchange.payload = body.clone();
+    
     let client = reqwest::Client::new();
     let response = client
         .post("https://api.openai.com/v1/chat/completions")
         .header("Authorization", format!("Bearer {}", api_key))
-        .json(&serde_json::json!({
-            "model": model,
-            "messages": [{"role": "user", "content": prompt}],
-            "temperature": 0.7,
-        }))
+        .json(&body)
         .send()
         .await?;
     
     if !response.status().is_success() {
         let error_text = response.text().await?;
+        exchange.response = Some(serde_json::json!(error_text));
         return Err(format!("OpenAI API error: {}", error_text).into());
     }
     
     let json: serde_json::Value = response.json().await?;
-    Ok(json["choices"][0]["message"]["content"]
+    exchange.input_tokens = json["usage"]["prompt_tokens"].as_i64();
+    exchange.output_tokens = json["usage"]["completion_tokens"].as_i64();
+    let text = json["choices"][0]["message"]["content"]
         .as_str()
         .unwrap_or("No response")
-        .to_string())
+        .to_string();
+    exchange.response = Some(json);
+    Ok(text)
 }
 
-async fn call_anthropic_api(model: &str, prompt: &str, api_key: &str) -> Result<String, Box<dyn std::error::Error>> {
+async fn call_anthropic_api(
+    model: &str,
+    chat: &[ChatMessage],
+    params: &GenerationParams,
+    images: &[ImageAttachment],
+    api_key: &str,
+    exchange: &mut ProviderExchange,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let mut messages = Vec::new();
+    for (i, message) in chat.iter().enumerate() {
+        // Images belong to the latest turn, placed before the text as Anthropic recommends
+        let mut content = Vec::new();
+        if i + 1 == chat.len() {
+            content.extend(images.iter().map(|image| serde_json::json!({
+                "type": "image",
+                "source": {"type": "base64", "media_type": image.media_type, "data": image.data},
+            })));
+        }
+        content.push(serde_json::json!({"type": "text", "text": message.content}));
+        messages.push(serde_json::json!({"role": message.role, "content": content}));
+    }
+    
+    let mut body = serde_json::json!({
+        "model": model,
+        "messages": messages,
+        "max_tokens": params.max_tokens.unwrap_or(1000),
+    });
+    if let Some(system) = &params.system_prompt {
+        body["system"] = serde_json::json!(system);
+    }
+    if let Some(temperature) = params.temperature {
+        body["temperature"] = serde_json::json!(temperature);
+    }
+    exchange.payload = body.clone();
+    
     let client = reqwest::Client::new();
     let response = client
         .post("https://api.anthropic.com/v1/messages")
         .header("x-api-key", api_key)
         .header("anthropic-version", "2023-06-01")
-        .json(&serde_json::json!({
-            "model": model,
-            "messages": [{"role": "user", "content": prompt}],
-            "max_tokens": 1000,
-        }))
+        .json(&body)
         .send()
         .await?;
     
     if !response.status().is_success() {
         let error_text = response.text().await?;
+        exchange.response = Some(serde_json::json!(error_text));
         return Err(format!("Anthropic API error: {}", error_text).into());
     }
     
     let json: serde_json::Value = response.json().await?;
-    Ok(json["content"][0]["text"]
+    exchange.input_tokens = json["usage"]["input_tokens"].as_i64();
+    exchange.output_tokens = json["usage"]["output_tokens"].as_i64();
+    let text = json["content"][0]["text"]
         .as_str()
         .unwrap_or("No response")
-        .to_string())
+        .to_string();
+    exchange.response = Some(json);
+    Ok(text)
 }
 
 async fn call_proxy_model(
     provider_name: &str,
     model: &str,
-    prompt: &str,
-    config: &crate::models::config::Config
-) -> Result<String, Box<dyn std::error::Error>> {
+    messages: &[ChatMessage],
+    params: &GenerationParams,
+    images: &[ImageAttachment],
+    config: &crate::models::config::Config,
+    exchange: &mut ProviderExchange,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
     let providers = config.proxy_providers.as_ref()
         .ok_or("No proxy providers configured")?;
     
@@ -421,14 +1185,29 @@ async fn call_proxy_model(
         .find(|p| p.name == provider_name)
         .ok_or(format!("Provider '{}' not found", provider_name))?;
     
+    let mut body = serde_json::json!({
+        "model": model,
+        "prompt": latest_prompt(messages),
+        "messages": messages,
+    });
+    if let Some(system) = &params.system_prompt {
+        body["system"] = serde_json::json!(system);
+    }
+    if let Some(temperature) = params.temperature {
+        body["temperature"] = serde_json::json!(temperature);
+    }
+    if let Some(max_tokens) = params.max_tokens {
+        body["max_tokens"] = serde_json::json!(max_tokens);
+    }
+    if !images.is_empty() {
+        body["images"] = serde_json::json!(images);
+    }
+    exchange.payload = body.clone();
+    
     let client = reqwest::Client::new();
     let mut request = client
         .post(&provider.endpoint)
-        .json(&serde_json::json!({
-            "model": model,
-            "prompt": prompt,
-            "messages": [{"role": "user", "content": prompt}],
-        }));
+        .json(&body);
     
     // Add API key if configured
     if let Some(api_key) = &provider.api_key {
@@ -446,10 +1225,14 @@ async fn call_proxy_model(
     
     if !response.status().is_success() {
         let error_text = response.text().await?;
+        exchange.response = Some(serde_json::json!(error_text));
         return Err(format!("Proxy API error: {}", error_text).into());
     }
     
     let json: serde_json::Value = response.json().await?;
+    exchange.response = Some(json.clone());
+    exchange.input_tokens = json["usage"]["prompt_tokens"].as_i64();
+    exchange.output_tokens = json["usage"]["completion_tokens"].as_i64();
     
     // Try to extract response from common paths
     let text = json["response"].as_str()
@@ -475,11 +1258,39 @@ async fn call_proxy_model(
         .ok_or_else(|| "No response found in proxy response".into())
 }
 
+async fn call_mock_model(
+    script: &str,
+    prompt: &str,
+    config: &crate::models::config::Config,
+    read_private: bool,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let fixtures_dir = std::path::Path::new(config.mock_fixtures_dir.as_deref().unwrap_or("fixtures/mock"));
+    let mock = MockScript::parse(script)?;
+    if let MockBehavior::Fixture(name) = &mock.behavior {
+        if !read_private && is_private_vault_file(&fixtures_dir.join(name), config).await {
+            return Err(format!("Fixture '{}' is in the private vault", name).into());
+        }
+    }
+    Ok(mock.respond(prompt, fixtures_dir).await?)
+}
+
+// Files outside the vault have no scope; inside it, the vault-relative path decides
+async fn is_private_vault_file(path: &std::path::Path, config: &crate::models::config::Config) -> bool {
+    let Some(vault_path) = config.vault_path.as_deref() else {
+        return false;
+    };
+    let (Ok(path), Ok(vault)) = (tokio::fs::canonicalize(path).await, tokio::fs::canonicalize(vault_path).await) else {
+        return false;
+    };
+    path.strip_prefix(&vault)
+        .is_ok_and(|relative| !is_public_vault_path(relative))
+}
+
 // Conversation mode processors
 async fn process_debate_mode(
     initial_responses: Vec<ModelResponse>,
     models: &[String],
-    config: &crate::models::config::Config,
+    ctx: &LlmContext,
 ) -> Vec<ModelResponse> {
     let mut all_responses = initial_responses;
     
@@ -492,32 +1303,13 @@ async fn process_debate_mode(
                 round, other_response
             );
             
-            let response_text = match ModelRouting::parse(model_name) {
-                ModelRouting::Ollama(model) => {
-                    call_ollama_model(&model, &debate_prompt).await
-                        .unwrap_or_else(|_| "Error in debate".to_string())
-                },
-                ModelRouting::OpenAI(model) => {
-                    if let Some(key) = &config.openai_key {
-                        call_openai_api(&model, &debate_prompt, key).await
-                            .unwrap_or_else(|_| "Error in debate".to_string())
-                    } else {
-                        continue;
-                    }
-                },
-                ModelRouting::Anthropic(model) => {
-                    if let Some(key) = &config.anthropic_key {
-                        call_anthropic_api(&model, &debate_prompt, key).await
-                            .unwrap_or_else(|_| "Error in debate".to_string())
-                    } else {
-                        continue;
-                    }
-                },
-                ModelRouting::Proxy { provider, model } => {
-                    call_proxy_model(&provider, &model, &debate_prompt, config).await
-                        .unwrap_or_else(|_| "Error in debate".to_string())
-                },
-            };
+            let routing = ModelRouting::parse(model_name, &ctx.config);
+            if !routing.has_credentials(&ctx.config) {
+                continue;
+            }
+            
+            let response_text = ctx.call(model_name, &debate_prompt).await
+                .unwrap_or_else(|_| "Error in debate".to_string());
             
             all_responses.push(ModelResponse {
                 model: model_name.clone(),
@@ -527,6 +1319,7 @@ async fn process_debate_mode(
                     .unwrap()
                     .as_secs(),
                 thinking_time_ms: 0,
+                step: None,
             });
         }
     }
@@ -537,7 +1330,7 @@ async fn process_debate_mode(
 async fn process_collaborative_mode(
     initial_responses: Vec<ModelResponse>,
     models: &[String],
-    config: &crate::models::config::Config,
+    ctx: &LlmContext,
 ) -> Vec<ModelResponse> {
     let mut all_responses = initial_responses;
     
@@ -548,32 +1341,13 @@ async fn process_collaborative_mode(
             previous
         );
         
-        let response_text = match ModelRouting::parse(model_name) {
-            ModelRouting::Ollama(model) => {
-                call_ollama_model(&model, &collab_prompt).await
-                    .unwrap_or_else(|_| "Error in collaboration".to_string())
-            },
-            ModelRouting::OpenAI(model) => {
-                if let Some(key) = &config.openai_key {
-                    call_openai_api(&model, &collab_prompt, key).await
-                        .unwrap_or_else(|_| "Error in collaboration".to_string())
-                } else {
-                    continue;
-                }
-            },
-            ModelRouting::Anthropic(model) => {
-                if let Some(key) = &config.anthropic_key {
-                    call_anthropic_api(&model, &collab_prompt, key).await
-                        .unwrap_or_else(|_| "Error in collaboration".to_string())
-                } else {
-                    continue;
-                }
-            },
-            ModelRouting::Proxy { provider, model } => {
-                call_proxy_model(&provider, &model, &collab_prompt, config).await
-                    .unwrap_or_else(|_| "Error in collaboration".to_string())
-            },
-        };
+        let routing = ModelRouting::parse(model_name, &ctx.config);
+        if !routing.has_credentials(&ctx.config) {
+            continue;
+        }
+        
+        let response_text = ctx.call(model_name, &collab_prompt).await
+            .unwrap_or_else(|_| "Error in collaboration".to_string());
         
         all_responses.push(ModelResponse {
             model: model_name.clone(),
@@ -583,6 +1357,7 @@ async fn process_collaborative_mode(
                 .unwrap()
                 .as_secs(),
             thinking_time_ms: 0,
+            step: None,
         });
     }
     
@@ -592,7 +1367,7 @@ async fn process_collaborative_mode(
 async fn process_consensus_mode(
     initial_responses: Vec<ModelResponse>,
     models: &[String],
-    config: &crate::models::config::Config,
+    ctx: &LlmContext,
 ) -> Vec<ModelResponse> {
     let mut all_responses = initial_responses;
     
@@ -605,31 +1380,12 @@ async fn process_consensus_mode(
     );
     
     if let Some(first_model) = models.first() {
-        let response_text = match ModelRouting::parse(first_model) {
-            ModelRouting::Ollama(model) => {
-                call_ollama_model(&model, &synthesis_prompt).await
-                    .unwrap_or_else(|_| "Error in consensus".to_string())
-            },
-            ModelRouting::OpenAI(model) => {
-                if let Some(key) = &config.openai_key {
-                    call_openai_api(&model, &synthesis_prompt, key).await
-                        .unwrap_or_else(|_| "Error in consensus".to_string())
-                } else {
-                    "Cannot synthesize without API key".to_string()
-                }
-            },
-            ModelRouting::Anthropic(model) => {
-                if let Some(key) = &config.anthropic_key {
-                    call_anthropic_api(&model, &synthesis_prompt, key).await
-                        .unwrap_or_else(|_| "Error in consensus".to_string())
-                } else {
-                    "Cannot synthesize without API key".to_string()
-                }
-            },
-            ModelRouting::Proxy { provider, model } => {
-                call_proxy_model(&provider, &model, &synthesis_prompt, config).await
-                    .unwrap_or_else(|_| "Error in consensus".to_string())
-            },
+        let routing = ModelRouting::parse(first_model, &ctx.config);
+        let response_text = if routing.has_credentials(&ctx.config) {
+            ctx.call(first_model, &synthesis_prompt).await
+                .unwrap_or_else(|_| "Error in consensus".to_string())
+        } else {
+            "Cannot synthesize without API key".to_string()
         };
         
         all_responses.push(ModelResponse {
@@ -640,12 +1396,157 @@ async fn process_consensus_mode(
                 .unwrap()
                 .as_secs(),
             thinking_time_ms: 0,
+            step: None,
         });
     }
     
     all_responses
 }
 
+// The first model drafts, the others critique, and the author revises each round
+async fn process_critique_mode(
+    payload: &ConversationRequest,
+    ctx: &LlmContext,
+) -> Vec<ModelResponse> {
+    let author = &payload.models[0];
+    // A lone model critiques its own drafts
+    let critics = if payload.models.len() > 1 {
+        &payload.models[1..]
+    } else {
+        &payload.models[..]
+    };
+    let iterations = payload.iterations
+        .unwrap_or(DEFAULT_CRITIQUE_ITERATIONS)
+        .clamp(1, MAX_CRITIQUE_ITERATIONS);
+    
+    let mut all_responses = Vec::new();
+    let draft = run_model_step(author, &payload.prompt, ctx, Some("draft 1".to_string())).await;
+    let mut current_draft = draft.response.clone();
+    all_responses.push(draft);
+    
+    for round in 1..=iterations {
+        let mut critiques = Vec::new();
+        for critic in critics {
+            let critique_prompt = format!(
+                "Task: '{}'\n\nDraft answer: '{}'\n\nCritique this draft. List concrete errors, omissions and improvements.",
+                payload.prompt, current_draft
+            );
+            let critique = run_model_step(critic, &critique_prompt, ctx, Some(format!("critique {}", round))).await;
+            critiques.push(format!("- {}: {}", critique.model, critique.response));
+            all_responses.push(critique);
+        }
+        
+        let revise_prompt = format!(
+            "Task: '{}'\n\nYour previous draft: '{}'\n\nCritiques:\n{}\n\nRevise the draft to address the critiques. Reply with the improved answer only.",
+            payload.prompt, current_draft, critiques.join("\n")
+        );
+        let revision = run_model_step(author, &revise_prompt, ctx, Some(format!("draft {}", round + 1))).await;
+        current_draft = revision.response.clone();
+        all_responses.push(revision);
+    }
+    
+    all_responses
+}
+
+// Split the input (or vault notes) across models, then reduce with a synthesis model
+async fn process_map_reduce_mode(
+    payload: &ConversationRequest,
+    vault_path: &std::path::Path,
+    ctx: &LlmContext,
+) -> Result<Vec<ModelResponse>, StatusCode> {
+    let chunk_size = payload.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE).max(MIN_CHUNK_SIZE);
+    
+    let mut chunks = Vec::new();
+    if let Some(notes) = &payload.vault_notes {
+        for note in notes {
+            let path = vault_file_path(vault_path, note).ok_or(StatusCode::BAD_REQUEST)?;
+            let private = !is_public_vault_path(std::path::Path::new(note));
+            if private && !ctx.read_private {
+                return Err(StatusCode::FORBIDDEN);
+            }
+            let content = tokio::fs::read_to_string(path).await
+                .map_err(|_| StatusCode::NOT_FOUND)?;
+            let note_chunks = split_into_chunks(&content, chunk_size);
+            if private {
+                for chunk in &note_chunks {
+                    ctx.mark_private(chunk.clone());
+                }
+            }
+            chunks.extend(note_chunks);
+        }
+    }
+    if let Some(input) = &payload.input {
+        chunks.extend(split_into_chunks(input, chunk_size));
+    }
+    if chunks.is_empty() {
+        chunks = split_into_chunks(&payload.prompt, chunk_size);
+    }
+    if chunks.len() > MAX_MAP_CHUNKS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    
+    let total = chunks.len();
+    let slots = tokio::sync::Semaphore::new(MAP_CONCURRENCY);
+    let slots = &slots;
+    let map_steps = chunks.iter().enumerate().map(|(i, chunk)| {
+        let model_name = &payload.models[i % payload.models.len()];
+        let map_prompt = format!(
+            "{}\n\nThis is part {} of {} of the input. Work only from this part:\n\n{}",
+            payload.prompt, i + 1, total, chunk
+        );
+        async move {
+            let _slot = slots.acquire().await;
+            run_model_step(model_name, &map_prompt, ctx, Some(format!("map {}/{}", i + 1, total))).await
+        }
+    });
+    let mut all_responses = futures::future::join_all(map_steps).await;
+    
+    let synthesis_model = payload.synthesis_model.as_ref().unwrap_or(&payload.models[0]);
+    let reduce_prompt = format!(
+        "{}\n\nThe input was processed in {} parts. Combine these partial results into one complete answer:\n\n{}",
+        payload.prompt,
+        total,
+        all_responses.iter()
+            .enumerate()
+            .map(|(i, r)| format!("Part {}: {}", i + 1, r.response))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    );
+    all_responses.push(run_model_step(synthesis_model, &reduce_prompt, ctx, Some("reduce".to_string())).await);
+    
+    Ok(all_responses)
+}
+
+// Split text on paragraph boundaries into chunks of at most `max_chars` characters
+fn split_into_chunks(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if !current.is_empty() && current.chars().count() + paragraph.chars().count() + 2 > max_chars {
+            chunks.push(std::mem::take(&mut current));
+        }
+        
+        if paragraph.chars().count() > max_chars {
+            let chars: Vec<char> = paragraph.chars().collect();
+            for piece in chars.chunks(max_chars) {
+                chunks.push(piece.iter().collect());
+            }
+            continue;
+        }
+        
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(paragraph);
+    }
+    
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
 // GET /llm/status
 pub async fn model_status(
     State(state): State<AppState>,
@@ -656,15 +1557,10 @@ pub async fn model_status(
     };
     
     let primary_model = config.llm_model.clone();
-    let is_loaded = match ModelRouting::parse(&primary_model) {
+    let is_loaded = match ModelRouting::parse(&primary_model, &config) {
         ModelRouting::Ollama(model) => {
-            let check = tokio::process::Command::new("ollama")
-                .arg("show")
-                .arg(&model)
-                .output()
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-            check.status.success()
+            OllamaClient::new(&config).has_model(&model).await
+                .unwrap_or(false)
         },
         ModelRouting::OpenAI(_) => config.openai_key.is_some(),
         ModelRouting::Anthropic(_) => config.anthropic_key.is_some(),
@@ -674,18 +1570,22 @@ pub async fn model_status(
                 .map(|providers| providers.iter().any(|p| p.name == provider))
                 .unwrap_or(false)
         },
+        ModelRouting::Mock(script) => MockScript::parse(&script).is_ok(),
     };
     
     Ok(Json(serde_json::json!({
         "primary_model": primary_model,
         "loaded": is_loaded,
+        "queue": state.llm_queue.stats(),
         "capabilities": {
             "local_ai": true,
             "openai": config.openai_key.is_some(),
             "anthropic": config.anthropic_key.is_some(),
             "proxy": config.proxy_providers.is_some(),
+            "mock": config.mock_enabled(),
             "multi_model": true,
-            "conversation_modes": ["sequential", "debate", "collaborative", "consensus"],
+            "conversation_modes": ["sequential", "debate", "collaborative", "consensus", "critique", "map_reduce"],
+            "image_input": true,
         }
     })).into_response())
 }
@@ -696,7 +1596,266 @@ pub fn routes() -> axum::Router<AppState> {
     
     axum::Router::new()
         .route("/models", get(get_models))
-        .route("/use", post(set_model))
-        .route("/conversation", post(multi_model_conversation))
+        .route("/conversation", post(multi_model_conversation)
+            .layer(DefaultBodyLimit::max(MAX_CONVERSATION_BODY_BYTES)))
+        .route("/conversation/upload", post(multi_model_conversation_upload)
+            .layer(DefaultBodyLimit::max(MAX_CONVERSATION_BODY_BYTES)))
         .route("/status", get(model_status))
+        .merge(features::require(features::LLM_USE, axum::Router::new()
+            .route("/use", post(set_model))))
+        .merge(features::require(features::LLM_ADMIN, axum::Router::new()
+            .route("/local/pull", post(pull_local_model))
+            .route("/local/delete", post(delete_local_model))
+            .route("/local/copy", post(copy_local_model))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::Config;
+
+    async fn run_conversation(request: serde_json::Value) -> serde_json::Value {
+        let state = crate::test_app_state(Config::default()).await;
+        let payload: ConversationRequest = serde_json::from_value(request).unwrap();
+        let response = multi_model_conversation(State(state), None, Json(payload)).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn test_mock_routing() {
+        let config = Config::default();
+        assert!(matches!(ModelRouting::parse("mock:echo", &config), ModelRouting::Mock(s) if s == "echo"));
+        assert!(matches!(ModelRouting::parse("gpt-4", &config), ModelRouting::OpenAI(_)));
+        assert!(matches!(ModelRouting::parse("llama3:8b", &config), ModelRouting::Ollama(_)));
+    }
+
+    #[tokio::test]
+    async fn test_pulling_on_use_needs_llm_admin() {
+        let state = crate::test_app_state(Config::default()).await;
+        let user = Claims { features: vec![features::CHAT.to_string(), features::LLM_USE.to_string()], ..Claims::default() };
+        let request = SetModelRequest {
+            model: "llama3:70b".to_string(),
+            model_type: None,
+            as_primary: true,
+            pull_if_missing: true,
+        };
+        let refused = set_model(State(state.clone()), Some(Extension(user)), Json(request)).await;
+        assert_eq!(refused.unwrap_err(), StatusCode::FORBIDDEN);
+        assert_eq!(state.runtime_state.read().await.config.llm_model, Config::default().llm_model);
+    }
+
+    #[tokio::test]
+    async fn test_profile_fallback_chain() {
+        let config = Config {
+            model_aliases: Some(HashMap::from([("backup".to_string(), "mock:reply:from backup".to_string())])),
+            model_profiles: Some(HashMap::from([(
+                "flaky".to_string(),
+                crate::models::config::ModelProfile {
+                    model: "mock:error:503".to_string(),
+                    system_prompt: None,
+                    temperature: None,
+                    max_tokens: None,
+                    fallbacks: Some(vec!["mock:error:429".to_string(), "backup".to_string()]),
+                },
+            )])),
+            ..Config::default()
+        };
+
+        let state = crate::test_app_state(config).await;
+        let payload: ConversationRequest = serde_json::from_value(serde_json::json!({
+            "prompt": "hello",
+            "models": ["flaky"],
+            "mode": "sequential",
+        })).unwrap();
+        let response = multi_model_conversation(State(state), None, Json(payload)).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["responses"][0]["model"], "flaky");
+        assert_eq!(json["responses"][0]["response"], "from backup");
+    }
+
+    #[tokio::test]
+    async fn test_sequential_conversation_with_mocks() {
+        let json = run_conversation(serde_json::json!({
+            "prompt": "ping",
+            "models": ["mock:echo", "mock:reply:pong", "mock:error:429"],
+            "mode": "sequential",
+        })).await;
+
+        let responses = json["responses"].as_array().unwrap();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["response"], "ping");
+        assert_eq!(responses[1]["response"], "pong");
+        assert_eq!(responses[2]["response"], "Mock error: 429 Too Many Requests (mock)");
+    }
+
+    #[tokio::test]
+    async fn test_critique_returns_every_draft() {
+        let json = run_conversation(serde_json::json!({
+            "prompt": "write a haiku",
+            "models": ["mock:reply:draft", "mock:reply:needs more syllables"],
+            "mode": "critique",
+            "iterations": 2,
+        })).await;
+
+        let steps: Vec<_> = json["responses"].as_array().unwrap()
+            .iter()
+            .map(|r| r["step"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(steps, ["draft 1", "critique 1", "draft 2", "critique 2", "draft 3"]);
+    }
+
+    #[tokio::test]
+    async fn test_map_reduce_splits_input() {
+        let paragraph = "word ".repeat(30);
+        let json = run_conversation(serde_json::json!({
+            "prompt": "summarise",
+            "input": format!("{p}\n\n{p}\n\n{p}", p = paragraph),
+            "chunk_size": 1, // raised to the minimum, which fits one paragraph
+            "models": ["mock:reply:part"],
+            "synthesis_model": "mock:reply:summary",
+            "mode": "map_reduce",
+        })).await;
+
+        let responses = json["responses"].as_array().unwrap();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0]["step"], "map 1/3");
+        assert_eq!(responses[3]["step"], "reduce");
+        assert_eq!(responses[3]["response"], "summary");
+
+        let too_many: ConversationRequest = serde_json::from_value(serde_json::json!({
+            "prompt": "summarise",
+            "input": "x".repeat(MIN_CHUNK_SIZE * (MAX_MAP_CHUNKS + 1)),
+            "chunk_size": MIN_CHUNK_SIZE,
+            "models": ["mock:echo"],
+            "mode": "map_reduce",
+        })).unwrap();
+        let state = crate::test_app_state(Config::default()).await;
+        let refused = multi_model_conversation(State(state), None, Json(too_many)).await;
+        assert_eq!(refused.unwrap_err(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_map_reduce_private_notes_need_private_access() {
+        let vault = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(vault.path().join("Private")).unwrap();
+        std::fs::write(vault.path().join("Private/public-speaking.md"), "nervous before talks").unwrap();
+        let payload: ConversationRequest = serde_json::from_value(serde_json::json!({
+            "prompt": "summarise",
+            "vault_notes": ["Private/public-speaking.md"],
+            "models": ["mock:echo"],
+            "mode": "map_reduce",
+        })).unwrap();
+
+        let state = crate::test_app_state(Config::default()).await;
+        let mut ctx = LlmContext::new(&state, Config::default(), None, None);
+        let refused = process_map_reduce_mode(&payload, vault.path(), &ctx).await;
+        assert_eq!(refused.unwrap_err(), StatusCode::FORBIDDEN);
+        ctx.read_private = true;
+        assert!(process_map_reduce_mode(&payload, vault.path(), &ctx).await.is_ok());
+        assert!(ctx.private_texts.lock().unwrap().iter().any(|text| text.contains("nervous")));
+    }
+
+    #[tokio::test]
+    async fn test_images_rejected_for_text_only_models() {
+        use base64::Engine;
+        let png = base64::engine::general_purpose::STANDARD.encode(b"\x89PNG\r\n\x1a\n....");
+
+        let state = crate::test_app_state(Config::default()).await;
+        let payload: ConversationRequest = serde_json::from_value(serde_json::json!({
+            "prompt": "what is this?",
+            "models": ["mock:echo", "llama2"],
+            "mode": "sequential",
+            "images": [{"data": format!("data:image/png;base64,{}", png)}],
+        })).unwrap();
+        let response = multi_model_conversation(State(state), None, Json(payload)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["text_only_models"], serde_json::json!(["llama2"]));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_vault_images() {
+        let vault = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(vault.path().join("Public")).unwrap();
+        std::fs::create_dir_all(vault.path().join("Private")).unwrap();
+        std::fs::write(vault.path().join("Public/photo.jpg"), [0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+        std::fs::write(vault.path().join("Private/photo.jpg"), [0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+        std::fs::write(vault.path().join("Private/public-photo.jpg"), [0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+        std::fs::write(vault.path().join("Public/note.md"), "not an image").unwrap();
+
+        let state = crate::test_app_state(Config::default()).await;
+        let reader = Claims { sub: "reader".to_string(), features: vec!["chat".to_string()], ..Claims::default() };
+        let mut ctx = LlmContext::new(&state, Config::default(), Some(Extension(reader)), None);
+        assert!(!ctx.read_private);
+
+        let vault_image = |path: &str| ImageInput::Vault { vault_path: path.to_string() };
+        let images = resolve_images(&[vault_image("Public/photo.jpg")], vault.path(), &ctx, None).await.unwrap();
+        assert_eq!(images[0].media_type, "image/jpeg");
+
+        assert!(resolve_images(&[vault_image("Public/note.md")], vault.path(), &ctx, None).await.is_err());
+        assert!(resolve_images(&[vault_image("../photo.jpg")], vault.path(), &ctx, None).await.is_err());
+        let refused = resolve_images(&[vault_image("Private/photo.jpg")], vault.path(), &ctx, None).await.unwrap_err();
+        assert_eq!(refused.0, StatusCode::FORBIDDEN);
+        let refused = resolve_images(&[vault_image("Private/public-photo.jpg")], vault.path(), &ctx, None).await.unwrap_err();
+        assert_eq!(refused.0, StatusCode::FORBIDDEN);
+        ctx.read_private = true;
+        assert!(resolve_images(&[vault_image("Private/photo.jpg")], vault.path(), &ctx, None).await.is_ok());
+
+        let fields = crate::db::personas::PersonaFields {
+            name: "Publicist".to_string(),
+            model: "llava:13b".to_string(),
+            system_prompt: None,
+            voice: Default::default(),
+            vault_folders: vec!["Public".to_string()],
+        };
+        let persona = crate::db::personas::create(&state.db, "reader", &fields).await.unwrap();
+        let refused = resolve_images(&[vault_image("Private/photo.jpg")], vault.path(), &ctx, Some(&persona)).await.unwrap_err();
+        assert_eq!(refused.0, StatusCode::FORBIDDEN);
+        assert!(resolve_images(&[vault_image("Public/photo.jpg")], vault.path(), &ctx, Some(&persona)).await.is_ok());
+        assert!(ModelRouting::parse("llava:13b", &Config::default()).supports_images(&Config::default()));
+    }
+
+    #[tokio::test]
+    async fn test_mock_fixtures_in_private_vault_need_private_access() {
+        let vault = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(vault.path().join("Private/public-fixtures")).unwrap();
+        std::fs::write(vault.path().join("Private/public-fixtures/diary.txt"), "dear diary").unwrap();
+        let config = Config {
+            vault_path: Some(vault.path().to_string_lossy().into_owned()),
+            mock_fixtures_dir: Some(vault.path().join("Private/public-fixtures").to_string_lossy().into_owned()),
+            ..Config::default()
+        };
+
+        assert!(call_mock_model("fixture:diary.txt", "", &config, false).await.is_err());
+        assert_eq!(call_mock_model("fixture:diary.txt", "", &config, true).await.unwrap(), "dear diary");
+        let outside = Config { vault_path: Some("/nonexistent-vault".to_string()), ..config };
+        assert_eq!(call_mock_model("fixture:diary.txt", "", &outside, false).await.unwrap(), "dear diary");
+    }
+
+    #[test]
+    fn test_split_into_chunks() {
+        assert_eq!(split_into_chunks("a\n\nb\n\nc", 4), ["a\n\nb", "c"]);
+        assert_eq!(split_into_chunks("abcdef", 4), ["abcd", "ef"]);
+        assert!(split_into_chunks("  ", 4).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_consensus_conversation_with_mocks() {
+        let json = run_conversation(serde_json::json!({
+            "prompt": "ping",
+            "models": ["mock:reply:agreed", "mock:echo"],
+            "mode": "consensus",
+        })).await;
+
+        let responses = json["responses"].as_array().unwrap();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[2]["model"], "mock:reply:agreed (Consensus)");
+        assert_eq!(responses[2]["response"], "agreed");
+        assert!(json["conversation_id"].is_string());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,284 @@
+// src/routes/lockouts.rs
+use axum::{
+    extract::{Path, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::{HeaderValue, StatusCode},
+};
+use sqlx::SqlitePool;
+use std::net::IpAddr;
+use crate::AppState;
+use crate::auth::Claims;
+use crate::features::require_admin;
+use crate::db::login_failures::{self, LoginFailure};
+
+// Failures before each further attempt has to wait, doubling from one second
+const DELAY_AFTER_FAILURES: i64 = 3;
+const MAX_DELAY_SECS: i64 = 60;
+const LOCKOUT_AFTER_FAILURES: i64 = 10;
+const LOCKOUT_SECS: i64 = 15 * 60;
+// Failures older than this no longer count
+const FAILURE_WINDOW_SECS: i64 = 60 * 60;
+const RECENT_EVENTS: i64 = 100;
+
+/// Keys that failed logins are counted under: the account being guessed
+/// and the address guessing it
+pub(crate) fn attempt_keys(account: &str, ip: Option<IpAddr>) -> Vec<String> {
+    keys_for(format!("account:{}", account.to_lowercase()), ip)
+}
+
+/// Keys for failed passphrase unlocks. There is only one passphrase, so a
+/// shared key would let anyone lock the owner out; failures count against
+/// the guessing address alone, apart from its login failures. Without an
+/// address they fall back to one shared key.
+pub(crate) fn passphrase_keys(ip: Option<IpAddr>) -> Vec<String> {
+    match ip {
+        Some(ip) => vec![format!("passphrase-ip:{}", ip)],
+        None => vec!["passphrase".to_string()],
+    }
+}
+
+fn keys_for(subject: String, ip: Option<IpAddr>) -> Vec<String> {
+    let mut keys = vec![subject];
+    if let Some(ip) = ip {
+        keys.push(format!("ip:{}", ip));
+    }
+    keys
+}
+
+/// Seconds until the next attempt is allowed, if it has to wait
+pub fn wait_secs(failure: &LoginFailure, now: i64) -> Option<i64> {
+    if let Some(locked_until) = failure.locked_until.filter(|until| *until > now) {
+        return Some(locked_until - now);
+    }
+    if now - failure.last_failure_at > FAILURE_WINDOW_SECS || failure.failures < DELAY_AFTER_FAILURES {
+        return None;
+    }
+    let delay = 1_i64.checked_shl((failure.failures - DELAY_AFTER_FAILURES) as u32)
+        .unwrap_or(MAX_DELAY_SECS)
+        .min(MAX_DELAY_SECS);
+    Some(failure.last_failure_at + delay - now).filter(|wait| *wait > 0)
+}
+
+/// Counts a failure against `key` and locks it once there are too many.
+/// Returns the new count and whether this failure started the lockout.
+pub(crate) async fn count_failure(pool: &SqlitePool, key: &str, now: i64) -> Result<(LoginFailure, bool), sqlx::Error> {
+    let mut failure = login_failures::increment(pool, key, now, FAILURE_WINDOW_SECS).await?;
+    let mut locked = false;
+    if failure.failures >= LOCKOUT_AFTER_FAILURES && failure.locked_until.is_none_or(|until| until <= now) {
+        locked = login_failures::lock(pool, key, now, now + LOCKOUT_SECS).await?;
+        if locked {
+            failure.locked_until = Some(now + LOCKOUT_SECS);
+        }
+    }
+    Ok((failure, locked))
+}
+
+/// Turns the attempt away with 429 and `Retry-After` while any key has to wait
+pub(crate) async fn check(pool: &SqlitePool, keys: &[String]) -> Result<(), Response> {
+    let now = chrono::Utc::now().timestamp();
+    let mut wait = None;
+    for key in keys {
+        let failure = login_failures::get(pool, key)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+        wait = wait.max(failure.and_then(|failure| wait_secs(&failure, now)));
+    }
+
+    match wait {
+        Some(secs) => {
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(serde_json::json!({
+                "error": "Too many failed attempts, try again later",
+                "retry_after": secs,
+            }))).into_response();
+            response.headers_mut().insert("retry-after", HeaderValue::from(secs));
+            Err(response)
+        }
+        None => Ok(()),
+    }
+}
+
+pub(crate) async fn record_failure(pool: &SqlitePool, keys: &[String], ip: Option<IpAddr>) {
+    let now = chrono::Utc::now().timestamp();
+    let ip = ip.map(|ip| ip.to_string());
+    for key in keys {
+        let result = async {
+            let (failure, locked) = count_failure(pool, key, now).await?;
+            if locked {
+                tracing::warn!("Locked out {} after {} failed attempts", key, failure.failures);
+                let detail = format!("{} failed attempts", failure.failures);
+                login_failures::record_event(pool, "lockout", key, ip.as_deref(), Some(&detail)).await?;
+            }
+            Ok::<_, sqlx::Error>(())
+        }.await;
+        if let Err(e) = result {
+            tracing::error!("Failed to record login failure for {}: {}", key, e);
+        }
+    }
+}
+
+/// A successful attempt wipes the first key's count: the account's, or for
+/// the passphrase the address's own
+pub(crate) async fn record_success(pool: &SqlitePool, keys: &[String]) {
+    if let Some(account) = keys.first() {
+        if let Err(e) = login_failures::clear(pool, account).await {
+            tracing::error!("Failed to clear login failures for {}: {}", account, e);
+        }
+    }
+}
+
+// GET /admin/lockouts
+pub async fn list_lockouts(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let now = chrono::Utc::now().timestamp();
+    let lockouts = login_failures::active_lockouts(&state.db, now).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let events = login_failures::recent_events(&state.db, RECENT_EVENTS).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(serde_json::json!({
+        "lockouts": lockouts,
+        "events": events,
+    })).into_response())
+}
+
+// DELETE /admin/lockouts/:key
+//
+// Clears the failure count too, so the key starts over without delays.
+pub async fn clear_lockout(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(key): Path<String>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let cleared = login_failures::clear(&state.db, &key).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !cleared {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let detail = format!("cleared by {}", claims.sub);
+    login_failures::record_event(&state.db, "lockout_cleared", &key, None, Some(&detail)).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::{delete, get};
+
+    axum::Router::new()
+        .route("/admin/lockouts", get(list_lockouts))
+        .route("/admin/lockouts/:key", delete(clear_lockout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::Config;
+
+    #[tokio::test]
+    async fn test_delays_grow_then_lock_out() {
+        let pool = crate::db::test_pool().await;
+        let mut now = 1_000_000;
+        let mut failure = None;
+        for _ in 0..DELAY_AFTER_FAILURES - 1 {
+            failure = Some(count_failure(&pool, "account:eve", now).await.unwrap().0);
+        }
+        assert_eq!(wait_secs(failure.as_ref().unwrap(), now), None);
+
+        let (failure, _) = count_failure(&pool, "account:eve", now).await.unwrap();
+        assert_eq!(wait_secs(&failure, now), Some(1));
+        let (failure, _) = count_failure(&pool, "account:eve", now).await.unwrap();
+        assert_eq!(wait_secs(&failure, now), Some(2));
+        assert_eq!(wait_secs(&failure, now + 2), None);
+
+        let mut locked = failure;
+        let mut lockouts = 0;
+        while locked.failures < LOCKOUT_AFTER_FAILURES {
+            now += MAX_DELAY_SECS;
+            let (failure, started) = count_failure(&pool, "account:eve", now).await.unwrap();
+            lockouts += started as i32;
+            locked = failure;
+        }
+        assert_eq!(lockouts, 1);
+        assert_eq!(locked.locked_until, Some(now + LOCKOUT_SECS));
+        assert_eq!(wait_secs(&locked, now + 60), Some(LOCKOUT_SECS - 60));
+        // A failure during the lockout neither extends nor restarts it
+        let (during, started) = count_failure(&pool, "account:eve", now + 60).await.unwrap();
+        assert!(!started && during.locked_until == locked.locked_until);
+
+        // Long after the lockout the slate is clean again
+        let later = now + FAILURE_WINDOW_SECS + LOCKOUT_SECS;
+        assert_eq!(wait_secs(&during, later), None);
+        let (fresh, _) = count_failure(&pool, "account:eve", later).await.unwrap();
+        assert_eq!((fresh.failures, fresh.locked_until), (1, None));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_failures_are_all_counted() {
+        let pool = crate::db::test_pool().await;
+        let failures = (0..LOCKOUT_AFTER_FAILURES).map(|_| count_failure(&pool, "ip:10.0.0.7", 1_000_000));
+        let started: Vec<bool> = futures::future::join_all(failures).await
+            .into_iter()
+            .map(|result| result.unwrap().1)
+            .collect();
+        assert_eq!(started.iter().filter(|s| **s).count(), 1);
+        let stored = login_failures::get(&pool, "ip:10.0.0.7").await.unwrap().unwrap();
+        assert_eq!(stored.failures, LOCKOUT_AFTER_FAILURES);
+
+    }
+
+    #[tokio::test]
+    async fn test_passphrase_and_login_counts_stay_apart() {
+        let pool = crate::db::test_pool().await;
+        let ip: IpAddr = "10.0.0.7".parse().unwrap();
+        let login = attempt_keys("eve", Some(ip));
+        let passphrase = passphrase_keys(Some(ip));
+        assert_eq!(passphrase, vec!["passphrase-ip:10.0.0.7".to_string()]);
+
+        for _ in 0..LOCKOUT_AFTER_FAILURES {
+            record_failure(&pool, &login, Some(ip)).await;
+        }
+        // Failed logins don't hold up the passphrase, and unlocking doesn't
+        // wipe the address's login failures
+        assert!(check(&pool, &passphrase).await.is_ok());
+        record_success(&pool, &passphrase).await;
+        assert!(check(&pool, &login[1..]).await.is_err());
+
+        for _ in 0..LOCKOUT_AFTER_FAILURES {
+            record_failure(&pool, &passphrase, Some(ip)).await;
+        }
+        assert!(check(&pool, &attempt_keys("mallory", Some("10.0.0.8".parse().unwrap()))).await.is_ok());
+        assert!(check(&pool, &passphrase).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_lockouts_are_recorded_and_cleared_by_admins() {
+        let state = crate::test_app_state(Config::default()).await;
+        let keys = attempt_keys("Eve", Some("10.0.0.9".parse().unwrap()));
+        assert_eq!(keys, vec!["account:eve".to_string(), "ip:10.0.0.9".to_string()]);
+
+        for _ in 0..LOCKOUT_AFTER_FAILURES {
+            record_failure(&state.db, &keys, Some("10.0.0.9".parse().unwrap())).await;
+        }
+        let refused = check(&state.db, &keys).await.unwrap_err();
+        assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(refused.headers().contains_key("retry-after"));
+
+        let admin = Claims { sub: "root".to_string(), features: vec!["admin".to_string()], ..Claims::default() };
+        let response = list_lockouts(State(state.clone()), Extension(admin.clone())).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let listed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listed["lockouts"].as_array().unwrap().len(), 2);
+        assert_eq!(listed["events"][0]["kind"], "lockout");
+
+        for key in &keys {
+            clear_lockout(State(state.clone()), Extension(admin.clone()), Path(key.clone())).await.unwrap();
+        }
+        assert!(check(&state.db, &keys).await.is_ok());
+        let events = login_failures::recent_events(&state.db, 10).await.unwrap();
+        assert_eq!(events[0].kind, "lockout_cleared");
+    }
+}
@@ -1,5 +1,17 @@
 // src/routes/mod.rs
 pub mod llm;
+pub mod chat;
+pub mod personas;
+pub mod votes;
+pub mod import;
+pub mod batches;
+pub mod templates;
+pub mod audit;
+pub mod sessions;
+pub mod admin;
+pub mod access_tokens;
+pub mod lockouts;
+pub mod secrets;
 pub mod voice;
 pub mod vault;
 pub mod auth;
@@ -0,0 +1,159 @@
+// src/routes/personas.rs
+use axum::{
+    extract::{Path, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::StatusCode,
+};
+use sqlx::SqlitePool;
+use crate::AppState;
+use crate::auth::{user_id, Claims};
+use crate::db::personas::{self, Persona, PersonaFields};
+use crate::routes::llm::LlmContext;
+use crate::vault::is_public_vault_path;
+
+const VAULT_CONTEXT_NOTES: usize = 3;
+const VAULT_EXCERPT_CHARS: usize = 600;
+
+// GET /llm/personas
+pub async fn list_personas(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+) -> Result<Response, StatusCode> {
+    let personas = personas::list(&state.db, &user_id(&claims)).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(serde_json::json!({ "personas": personas })).into_response())
+}
+
+// POST /llm/personas
+pub async fn create_persona(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Json(fields): Json<PersonaFields>,
+) -> Result<Response, StatusCode> {
+    validate(&fields)?;
+    let persona = personas::create(&state.db, &user_id(&claims), &fields).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::CREATED, Json(persona)).into_response())
+}
+
+// GET /llm/personas/:id
+pub async fn get_persona(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let persona = load_persona(&state.db, &id, &user_id(&claims)).await?;
+    Ok(Json(persona).into_response())
+}
+
+// PUT /llm/personas/:id
+pub async fn update_persona(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+    Json(fields): Json<PersonaFields>,
+) -> Result<Response, StatusCode> {
+    validate(&fields)?;
+    match personas::update(&state.db, &id, &user_id(&claims), &fields).await {
+        Ok(Some(persona)) => Ok(Json(persona).into_response()),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// DELETE /llm/personas/:id
+pub async fn delete_persona(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    match personas::delete(&state.db, &id, &user_id(&claims)).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+fn validate(fields: &PersonaFields) -> Result<(), StatusCode> {
+    let folders_ok = fields.vault_folders.iter().all(|folder| {
+        let folder = std::path::Path::new(folder);
+        !folder.is_absolute()
+            && !folder.components().any(|c| matches!(c, std::path::Component::ParentDir))
+    });
+    if fields.name.trim().is_empty() || fields.model.trim().is_empty() || !folders_ok {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(())
+}
+
+/// Look up one of the caller's personas for use by another endpoint
+pub(crate) async fn load_persona(pool: &SqlitePool, id: &str, user: &str) -> Result<Persona, StatusCode> {
+    match personas::get(pool, id, user).await {
+        Ok(Some(persona)) => Ok(persona),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Excerpts from the indexed vault notes inside the persona's folders that share
+/// the most words with `query`, formatted for a system prompt. Private-scope
+/// notes are left out unless the caller may read them, and are registered with
+/// `ctx` so the audit log masks them.
+pub(crate) async fn vault_context(
+    state: &AppState,
+    ctx: &LlmContext,
+    persona: &Persona,
+    query: &str,
+) -> Option<String> {
+    let terms: Vec<String> = query.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 4)
+        .map(str::to_lowercase)
+        .collect();
+    if terms.is_empty() {
+        return None;
+    }
+
+    let vault_state = state.vault_state.read().await;
+    let mut scored: Vec<_> = vault_state.indexed_files.iter()
+        .filter_map(|(path, metadata)| {
+            let relative = path.strip_prefix(&vault_state.vault_path).ok()?;
+            if !persona.allows_vault_path(relative) {
+                return None;
+            }
+            if !ctx.read_private && !is_public_vault_path(relative) {
+                return None;
+            }
+            let content = metadata.content.to_lowercase();
+            let score = terms.iter().filter(|term| content.contains(term.as_str())).count();
+            (score > 0).then_some((score, relative.to_path_buf(), metadata))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    let notes: Vec<String> = scored.into_iter()
+        .take(VAULT_CONTEXT_NOTES)
+        .map(|(_, path, metadata)| {
+            let excerpt: String = metadata.content.chars().take(VAULT_EXCERPT_CHARS).collect();
+            if !is_public_vault_path(&path) {
+                ctx.mark_private(excerpt.clone());
+            }
+            format!("## {} ({})\n{}", metadata.title, path.display(), excerpt)
+        })
+        .collect();
+
+    if notes.is_empty() {
+        None
+    } else {
+        Some(format!("Relevant notes from the vault:\n\n{}", notes.join("\n\n")))
+    }
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::get;
+
+    axum::Router::new()
+        .route("/personas", get(list_personas).post(create_persona))
+        .route("/personas/:id", get(get_persona).put(update_persona).delete(delete_persona))
+}
@@ -0,0 +1,239 @@
+// src/routes/secrets.rs
+use axum::{
+    extract::{Path, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use crate::AppState;
+use crate::auth::Claims;
+use crate::features::require_admin;
+use crate::db::secrets::{self, SecretError, SecretInfo, SecretKey};
+
+#[derive(Debug, Deserialize)]
+pub struct SetSecretRequest {
+    pub value: String,
+    #[serde(default)]
+    pub use_for: Vec<String>, // key fields to point at this secret, e.g. "openai_key"
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RotateSecretRequest {
+    pub value: String,
+}
+
+/// Derives the secrets key from a passphrase that has just been verified and
+/// resolves the config's secret references. Unlocking still succeeds if this
+/// fails; the affected keys just stay unset.
+pub(crate) async fn unlock_store(state: &AppState, passphrase: &str) {
+    let loaded = match secrets::unlock(&state.db, passphrase).await {
+        Ok(key) => load(state, key).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = loaded {
+        tracing::warn!("Secrets stay locked: {}", e);
+    }
+}
+
+/// Keeps `key` for later changes and applies every secret to the running config
+pub(crate) async fn load(state: &AppState, key: SecretKey) -> Result<(), SecretError> {
+    let values = secrets::values(&state.db, &key).await?;
+    state.runtime_state.write().await.apply_secrets(&values);
+    *state.secrets_key.write().unwrap() = Some(key);
+    Ok(())
+}
+
+// GET /admin/secrets
+//
+// Names and timestamps only; values are never returned.
+pub async fn list_secrets(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let stored = secrets::list(&state.db).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let references = state.runtime_state.read().await.secret_refs().clone();
+    let locked = state.secrets_key.read().unwrap().is_none();
+
+    let secrets: Vec<_> = stored
+        .iter()
+        .map(|info| secret_json(info, used_by(&references, &info.name)))
+        .collect();
+    let missing: Vec<_> = references
+        .iter()
+        .filter(|(_, name)| !stored.iter().any(|info| &info.name == *name))
+        .map(|(slot, name)| serde_json::json!({ "slot": slot, "name": name }))
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "locked": locked,
+        "secrets": secrets,
+        "missing": missing,
+    })).into_response())
+}
+
+// PUT /admin/secrets/:name
+//
+// Creates the secret or replaces its value, and optionally points config key
+// fields at it. config.json only ever gets the `secret:<name>` reference.
+pub async fn set_secret(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+    Json(payload): Json<SetSecretRequest>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    if !secrets::is_valid_name(&name) || payload.value.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let slots = state.runtime_state.read().await.config.secret_slots();
+    if payload.use_for.iter().any(|slot| !slots.contains(slot)) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let key = current_key(&state)?;
+
+    let info = secrets::put(&state.db, &key, &name, &payload.value).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !payload.use_for.is_empty() {
+        let mut runtime_state = state.runtime_state.write().await;
+        for slot in &payload.use_for {
+            runtime_state.refer_to_secret(slot, &name);
+        }
+    }
+    load(&state, key).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !payload.use_for.is_empty() {
+        state.save_config().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    tracing::info!("Secret {} set by {}", name, claims.sub);
+    let references = state.runtime_state.read().await.secret_refs().clone();
+    Ok(Json(secret_json(&info, used_by(&references, &name))).into_response())
+}
+
+// POST /admin/secrets/:name/rotate
+//
+// Replaces the value of an existing secret; everything referring to it picks
+// up the new value straight away.
+pub async fn rotate_secret(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+    Json(payload): Json<RotateSecretRequest>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    if payload.value.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let key = current_key(&state)?;
+    secrets::find(&state.db, &name).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let info = secrets::put(&state.db, &key, &name, &payload.value).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    load(&state, key).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tracing::info!("Secret {} rotated by {}", name, claims.sub);
+    let references = state.runtime_state.read().await.secret_refs().clone();
+    Ok(Json(secret_json(&info, used_by(&references, &name))).into_response())
+}
+
+// DELETE /admin/secrets/:name
+//
+// Refused while a config key field still refers to the secret.
+pub async fn delete_secret(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+) -> Result<Response, StatusCode> {
+    require_admin(&claims)?;
+    let in_use = state.runtime_state.read().await.secret_refs().values().any(|n| n == &name);
+    if in_use {
+        return Err(StatusCode::CONFLICT);
+    }
+    let deleted = secrets::delete(&state.db, &name).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !deleted {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    tracing::info!("Secret {} deleted by {}", name, claims.sub);
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// Changing secrets needs the key, which only exists once the vault passphrase has been entered
+fn current_key(state: &AppState) -> Result<SecretKey, StatusCode> {
+    state.secrets_key.read().unwrap().clone().ok_or(StatusCode::LOCKED)
+}
+
+fn used_by(references: &std::collections::HashMap<String, String>, name: &str) -> Vec<String> {
+    let mut slots: Vec<String> = references
+        .iter()
+        .filter(|(_, n)| n.as_str() == name)
+        .map(|(slot, _)| slot.clone())
+        .collect();
+    slots.sort();
+    slots
+}
+
+fn secret_json(info: &SecretInfo, used_by: Vec<String>) -> serde_json::Value {
+    serde_json::json!({
+        "name": info.name,
+        "created_at": info.created_at,
+        "updated_at": info.updated_at,
+        "used_by": used_by,
+    })
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::{get, post, put};
+
+    axum::Router::new()
+        .route("/admin/secrets", get(list_secrets))
+        .route("/admin/secrets/:name", put(set_secret).delete(delete_secret))
+        .route("/admin/secrets/:name/rotate", post(rotate_secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::Config;
+
+    #[tokio::test]
+    async fn test_secrets_resolve_into_config_without_being_returned() {
+        let config = Config { openai_key: Some("secret:openai".to_string()), ..Config::default() };
+        let state = crate::test_app_state(config).await;
+        let admin = Claims { sub: "root".to_string(), features: vec!["admin".to_string()], ..Claims::default() };
+        let set = |value: &str| SetSecretRequest { value: value.to_string(), use_for: Vec::new() };
+
+        let locked = set_secret(State(state.clone()), Extension(admin.clone()), Path("openai".to_string()), Json(set("sk-one"))).await;
+        assert_eq!(locked.unwrap_err(), StatusCode::LOCKED);
+        assert_eq!(state.runtime_state.read().await.config.openai_key, None);
+
+        unlock_store(&state, "vault passphrase").await;
+        set_secret(State(state.clone()), Extension(admin.clone()), Path("openai".to_string()), Json(set("sk-one"))).await.unwrap();
+        assert_eq!(state.runtime_state.read().await.config.openai_key.as_deref(), Some("sk-one"));
+
+        let rotate = RotateSecretRequest { value: "sk-two".to_string() };
+        let response = rotate_secret(State(state.clone()), Extension(admin.clone()), Path("openai".to_string()), Json(rotate)).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let rotated: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(rotated["used_by"], serde_json::json!(["openai_key"]));
+        assert_eq!(state.runtime_state.read().await.config.openai_key.as_deref(), Some("sk-two"));
+
+        let response = list_secrets(State(state.clone()), Extension(admin.clone())).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(!String::from_utf8_lossy(&body).contains("sk-two"));
+        let listed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listed["locked"], false);
+        assert_eq!(listed["secrets"][0]["name"], "openai");
+
+        let in_use = delete_secret(State(state.clone()), Extension(admin.clone()), Path("openai".to_string())).await;
+        assert_eq!(in_use.unwrap_err(), StatusCode::CONFLICT);
+        let rotate = RotateSecretRequest { value: "x".to_string() };
+        let missing = rotate_secret(State(state.clone()), Extension(admin), Path("anthropic".to_string()), Json(rotate)).await;
+        assert_eq!(missing.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,193 @@
+// src/routes/sessions.rs
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use crate::auth::{decode_token, extract_token, generate_token, Claims, ACCESS_TOKEN_TTL_MINUTES};
+use crate::db::tokens::{self, Rotation};
+use crate::db::users::{self, User};
+use crate::{AppState, AuthResponse};
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
+}
+
+/// Issues an access token and a new refresh token for the user
+pub(crate) async fn issue_tokens(state: &AppState, user: &User) -> Result<AuthResponse, StatusCode> {
+    let refresh_token = tokens::create_refresh(&state.db, &user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    access_response(state, user, refresh_token)
+}
+
+fn access_response(state: &AppState, user: &User, refresh_token: String) -> Result<AuthResponse, StatusCode> {
+    let claims = Claims::new(user.username.clone(), user.is_dev, user.features.0.clone());
+    let token = generate_token(&claims, &state.jwt_secret)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(AuthResponse {
+        token,
+        refresh_token,
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+        username: user.username.clone(),
+    })
+}
+
+// POST /auth/refresh
+//
+// Spends the refresh token and returns a new pair. Features are read from
+// the user record, so changes made by an admin take effect on refresh.
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Response, StatusCode> {
+    let rotation = tokens::rotate_refresh(&state.db, &payload.refresh_token)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let (user_id, refresh_token) = match rotation {
+        Rotation::Rotated { user_id, token } => (user_id, token),
+        Rotation::Reused { user_id } => {
+            tracing::warn!("Refresh token reuse for user {}; all sessions revoked", user_id);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Rotation::Invalid => return Err(StatusCode::UNAUTHORIZED),
+    };
+
+    let user = users::get(&state.db, &user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .filter(|user| !user.is_disabled())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    Ok(Json(access_response(&state, &user, refresh_token)?).into_response())
+}
+
+// POST /auth/logout
+//
+// Revokes the refresh token in the body and, if the request carries a
+// still-valid access token, puts that token on the denylist.
+pub async fn logout(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Json<LogoutRequest>>,
+) -> Result<Response, StatusCode> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+
+    let mut refresh_revoked = false;
+    if let Some(refresh_token) = &payload.refresh_token {
+        refresh_revoked = tokens::revoke_refresh(&state.db, refresh_token)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let claims = extract_token(&headers)
+        .ok()
+        .and_then(|token| decode_token(&token, &state.jwt_secret).ok());
+    let access_revoked = match claims {
+        Some(claims) if !claims.jti.is_empty() => {
+            tokens::deny_access_token(&state.db, &claims.jti, claims.exp as i64)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            true
+        }
+        _ => false,
+    };
+
+    Ok(Json(serde_json::json!({
+        "refresh_token_revoked": refresh_revoked,
+        "access_token_revoked": access_revoked,
+    })).into_response())
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::post;
+
+    axum::Router::new()
+        .route("/auth/refresh", post(refresh))
+        .route("/auth/logout", post(logout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Path, Extension};
+    use crate::models::config::Config;
+    use crate::routes::admin::revoke_user_sessions;
+
+    async fn refresh_with(state: &AppState, token: &str) -> Result<AuthResponse, StatusCode> {
+        let response = refresh(State(state.clone()), Json(RefreshRequest { refresh_token: token.to_string() })).await?;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        Ok(AuthResponse {
+            token: value["token"].as_str().unwrap().to_string(),
+            refresh_token: value["refresh_token"].as_str().unwrap().to_string(),
+            expires_in: value["expires_in"].as_i64().unwrap(),
+            username: value["username"].as_str().unwrap().to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_refresh_tokens_rotate_and_reuse_revokes_everything() {
+        let state = crate::test_app_state(Config::default()).await;
+        let user = users::create(&state.db, "alice", "pw", &["chat".to_string()], false).await.unwrap();
+        let first = issue_tokens(&state, &user).await.unwrap();
+
+        let second = refresh_with(&state, &first.refresh_token).await.unwrap();
+        assert_ne!(second.refresh_token, first.refresh_token);
+        let claims = decode_token(&second.token, &state.jwt_secret).unwrap();
+        assert_eq!(claims.features, vec!["chat".to_string()]);
+
+        // Replaying the spent token kills the live one too
+        assert_eq!(refresh_with(&state, &first.refresh_token).await.unwrap_err(), StatusCode::UNAUTHORIZED);
+        assert_eq!(refresh_with(&state, &second.refresh_token).await.unwrap_err(), StatusCode::UNAUTHORIZED);
+        assert_eq!(refresh_with(&state, "not-a-token").await.unwrap_err(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_logout_and_admin_revoke_deny_access_tokens() {
+        let state = crate::test_app_state(Config::default()).await;
+        let user = users::create(&state.db, "bob", "pw", &["chat".to_string()], false).await.unwrap();
+        let revoked = |claims: Claims| {
+            let db = state.db.clone();
+            async move { tokens::is_access_revoked(&db, &claims.jti, &claims.sub, claims.issued_at_micros()).await.unwrap() }
+        };
+
+        let session = issue_tokens(&state, &user).await.unwrap();
+        let elsewhere = issue_tokens(&state, &user).await.unwrap();
+        let claims = decode_token(&session.token, &state.jwt_secret).unwrap();
+        assert!(!revoked(claims.clone()).await);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {}", session.token).parse().unwrap());
+        let logout_request = LogoutRequest { refresh_token: Some(session.refresh_token.clone()) };
+        logout(State(state.clone()), headers, Some(Json(logout_request))).await.unwrap();
+        assert!(revoked(claims).await);
+        assert!(refresh_with(&state, &session.refresh_token).await.is_err());
+        // Refreshing with a logged-out token isn't reuse, so other devices stay signed in
+        assert!(refresh_with(&state, &elsewhere.refresh_token).await.is_ok());
+
+        let other = issue_tokens(&state, &user).await.unwrap();
+        let other_claims = decode_token(&other.token, &state.jwt_secret).unwrap();
+        let not_admin = Claims { features: vec!["chat".to_string()], ..Claims::default() };
+        let forbidden = revoke_user_sessions(State(state.clone()), Extension(not_admin), Path("bob".to_string())).await;
+        assert_eq!(forbidden.unwrap_err(), StatusCode::FORBIDDEN);
+
+        let admin = Claims { features: vec!["admin".to_string()], ..Claims::default() };
+        revoke_user_sessions(State(state.clone()), Extension(admin), Path("bob".to_string())).await.unwrap();
+        assert!(revoked(other_claims).await);
+        assert!(refresh_with(&state, &other.refresh_token).await.is_err());
+
+        // Signing in again straight away works, even within the same second
+        let fresh = issue_tokens(&state, &user).await.unwrap();
+        assert!(!revoked(decode_token(&fresh.token, &state.jwt_secret).unwrap()).await);
+    }
+}
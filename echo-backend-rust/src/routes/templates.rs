@@ -0,0 +1,111 @@
+// src/routes/templates.rs
+use axum::{
+    extract::{Path, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use crate::AppState;
+use crate::auth::Claims;
+use crate::features;
+use crate::routes::llm::LlmContext;
+use crate::state::llm_queue::Priority;
+use crate::vault::prompt_templates::PromptTemplate;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RunTemplateRequest {
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    pub model: Option<String>,
+    pub priority: Option<Priority>,
+}
+
+// GET /llm/templates
+//
+// Templates in Private-scope notes only show up for callers who may read them.
+pub async fn list_templates(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+) -> Result<Response, StatusCode> {
+    let vault_path = state.vault_state.read().await.vault_path.clone();
+    let templates = state.templates.list(&vault_path, read_private(&claims)).await;
+    Ok(Json(serde_json::json!({ "templates": templates })).into_response())
+}
+
+// GET /llm/templates/:id
+pub async fn get_template(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let template = load_template(&state, &id, read_private(&claims)).await?;
+    Ok(Json(template).into_response())
+}
+
+// POST /llm/templates/:id/run
+pub async fn run_template(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Path(id): Path<String>,
+    Json(payload): Json<RunTemplateRequest>,
+) -> Result<Response, StatusCode> {
+    let template = load_template(&state, &id, read_private(&claims)).await?;
+    let prompt = match template.render(&payload.variables) {
+        Ok(prompt) => prompt,
+        Err(message) => {
+            return Ok((StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message }))).into_response());
+        },
+    };
+
+    let config = {
+        let runtime_state = state.runtime_state.read().await;
+        runtime_state.config.clone()
+    };
+    let model = payload.model.clone()
+        .or_else(|| template.model.clone())
+        .unwrap_or_else(|| config.llm_model.clone());
+    let mut ctx = LlmContext::new(&state, config, claims, payload.priority);
+    ctx.overrides.system_prompt = template.system.clone();
+    if template.is_private() {
+        ctx.mark_private(prompt.clone());
+        if let Some(system) = &template.system {
+            ctx.mark_private(system.clone());
+        }
+    }
+
+    match ctx.call(&model, &prompt).await {
+        Ok(response) => Ok(Json(serde_json::json!({
+            "template_id": template.id,
+            "model": model,
+            "prompt": prompt,
+            "response": response,
+        })).into_response()),
+        Err(e) => {
+            tracing::error!("Template '{}' failed on {}: {}", template.id, model, e);
+            Ok((StatusCode::BAD_GATEWAY, Json(serde_json::json!({ "error": e.to_string() }))).into_response())
+        },
+    }
+}
+
+// Private templates look the same as missing ones to callers who can't read them
+async fn load_template(state: &AppState, id: &str, read_private: bool) -> Result<PromptTemplate, StatusCode> {
+    let vault_path = state.vault_state.read().await.vault_path.clone();
+    state.templates.get(&vault_path, id, read_private).await.ok_or(StatusCode::NOT_FOUND)
+}
+
+fn read_private(claims: &Option<Extension<Claims>>) -> bool {
+    claims.as_ref()
+        .is_some_and(|Extension(c)| features::has_feature(c, features::VAULT_READ_PRIVATE))
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::{get, post};
+
+    axum::Router::new()
+        .route("/templates", get(list_templates))
+        .route("/templates/:id", get(get_template))
+        .route("/templates/:id/run", post(run_template))
+}
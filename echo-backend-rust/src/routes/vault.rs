@@ -1,13 +1,15 @@
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
-    Router,
+    Extension, Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use crate::AppState;  // Import from crate root
+use crate::auth::{AuthError, Claims};
+use crate::features;
 
 #[derive(Debug, Serialize)]
 struct VaultInfo {
@@ -87,9 +89,12 @@ pub async fn get_vault_info(
 
 pub async fn search_vault(
     State(state): State<AppState>,  // Changed from State<Arc<AppState>>
+    Extension(claims): Extension<Claims>,
     Query(params): Query<SearchQuery>,
 ) -> Result<Json<SearchResults>, StatusCode> {
     let vault_state = state.vault_state.read().await;
+    // Callers without access to Private notes only ever see Public ones
+    let public_only = params.public_only || !features::has_feature(&claims, features::VAULT_READ_PRIVATE);
     
     // Simple search implementation - in production, you'd want to use a proper search index
     let mut results = Vec::new();
@@ -97,7 +102,7 @@ pub async fn search_vault(
     
     for (path, metadata) in &vault_state.indexed_files {
         // Skip private files if public_only is true
-        if params.public_only && !metadata.is_public {
+        if public_only && !metadata.is_public {
             continue;
         }
         
@@ -138,14 +143,19 @@ pub async fn search_vault(
 
 pub async fn get_vault_entry(
     State(state): State<AppState>,  // Changed from State<Arc<AppState>>
+    Extension(claims): Extension<Claims>,
     Path(entry_path): Path<String>,
-) -> Result<Json<VaultEntry>, StatusCode> {
+) -> Result<Response, StatusCode> {
     let vault_state = state.vault_state.read().await;
     
     // Reconstruct the full path
     let full_path = vault_state.vault_path.join(&entry_path);
     
     if let Some(metadata) = vault_state.indexed_files.get(&full_path) {
+        if !metadata.is_public && !features::has_feature(&claims, features::VAULT_READ_PRIVATE) {
+            return Ok(AuthError::InsufficientPermissions.into_response());
+        }
+
         let excerpt = if metadata.content.len() > 500 {
             format!("{}...", &metadata.content[..500])
         } else {
@@ -159,7 +169,7 @@ pub async fn get_vault_entry(
             tags: metadata.tags.clone(),
             last_modified: metadata.last_modified.to_rfc3339(),
             is_public: metadata.is_public,
-        }))
+        }).into_response())
     } else {
         Err(StatusCode::NOT_FOUND)
     }
@@ -250,6 +260,7 @@ pub fn routes() -> axum::Router<AppState> {  // Changed from Router<Arc<AppState
         .route("/", get(get_vault_info))
         .route("/query", get(search_vault))  // Fixed route path
         .route("/entry/*path", get(get_vault_entry))
-        .route("/refresh", post(refresh_vault_index))
         .route("/index/progress", get(get_index_progress))
+        .merge(features::require(features::VAULT_WRITE, Router::new()
+            .route("/refresh", post(refresh_vault_index))))
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use axum::{
-    extract::{Multipart, State},
+    extract::{Multipart, Query, State},
+    Extension,
     http::StatusCode,
     response::{Json, Response, IntoResponse},
     routing::post,
@@ -15,8 +16,10 @@ use serde::{Deserialize, Serialize};
 use tracing::{info, error};
 
 use crate::{
+    auth::{user_id, Claims},
     models::config::Config,
-    models::{stt::STTModule, tts::TTSModule},
+    models::{stt::STTModule, tts::{TTSModule, VoiceSettings}},
+    routes::personas::load_persona,
     AppState,
 };
 
@@ -35,9 +38,16 @@ struct TTSRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     voice_id: Option<String>,
     #[serde(default)]
+    persona_id: Option<String>,
+    #[serde(default)]
     stream: bool,
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct VoicePersonaQuery {
+    persona_id: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 struct TTSResponse {
     message: String,
@@ -92,10 +102,17 @@ pub async fn transcribe_audio(
 
 pub async fn synthesize_speech(
     State(state): State<AppState>,  // Changed from State<Arc<AppState>>
+    claims: Option<Extension<Claims>>,
     Json(request): Json<TTSRequest>,
 ) -> Result<Response, StatusCode> {
     info!("TTS request: {:?}", request);
 
+    // An explicit voice_id overrides the persona's voice
+    let mut settings = persona_voice(&state, &claims, request.persona_id.as_deref()).await?;
+    if request.voice_id.is_some() {
+        settings.voice_id = request.voice_id.clone();
+    }
+
     let runtime_state = state.runtime_state.read().await;
     let config = Arc::new(runtime_state.config.clone());
     drop(runtime_state);
@@ -103,7 +120,7 @@ pub async fn synthesize_speech(
     let tts = TTSModule::new(config);
 
     if request.stream {
-        match tts.stream_synthesize(request.text, request.voice_id).await {
+        match tts.stream_synthesize(request.text, settings).await {
             Ok(mut audio_rx) => {
                 let stream = async_stream::stream! {
                     while let Some(chunk_result) = audio_rx.recv().await {
@@ -145,7 +162,7 @@ pub async fn synthesize_speech(
             }
         }
     } else {
-        match tts.synthesize(request.text, request.voice_id).await {
+        match tts.synthesize(request.text, &settings).await {
             Ok(audio_data) => {
                 info!("TTS synthesis successful, size: {} bytes", audio_data.len());
                 
@@ -280,7 +297,7 @@ pub async fn test_voice_pipeline(
     let transcription = stt.transcribe(audio_data).await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let audio_response = tts.synthesize(transcription.text.clone(), None).await
+    let audio_response = tts.synthesize(transcription.text.clone(), &VoiceSettings::default()).await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Response::builder()
@@ -313,8 +330,12 @@ pub async fn voice_stream(
 // Voice-to-voice conversion endpoint
 pub async fn voice_to_voice(
     State(state): State<AppState>,  // Changed from State<Arc<AppState>>
+    claims: Option<Extension<Claims>>,
+    Query(query): Query<VoicePersonaQuery>,
     body: Bytes,
 ) -> Result<impl IntoResponse, StatusCode> {
+    let settings = persona_voice(&state, &claims, query.persona_id.as_deref()).await?;
+
     // Get STT module
     let runtime_state = state.runtime_state.read().await;
     let config = Arc::new(runtime_state.config.clone());
@@ -328,7 +349,7 @@ pub async fn voice_to_voice(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
     // Synthesize the response
-    let audio_response = tts.synthesize(transcription.text.clone(), None).await
+    let audio_response = tts.synthesize(transcription.text.clone(), &settings).await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
     // Return audio with transcription in header
@@ -340,6 +361,20 @@ pub async fn voice_to_voice(
         .unwrap())
 }
 
+// The voice settings of the caller's persona, or the defaults when none is given
+async fn persona_voice(
+    state: &AppState,
+    claims: &Option<Extension<Claims>>,
+    persona_id: Option<&str>,
+) -> Result<VoiceSettings, StatusCode> {
+    let Some(persona_id) = persona_id else {
+        return Ok(VoiceSettings::default());
+    };
+    let user = user_id(claims);
+    let persona = load_persona(&state.db, persona_id, &user).await?;
+    Ok(persona.voice.0)
+}
+
 pub fn routes() -> Router<AppState> {  // Changed from Router<Arc<AppState>>
     Router::new()
         .route("/voice/transcribe", post(transcribe_audio))
@@ -0,0 +1,190 @@
+// src/routes/votes.rs
+use axum::{
+    extract::{Query, State},
+    Extension,
+    response::{IntoResponse, Response, Json},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use crate::AppState;
+use crate::auth::{user_id, Claims};
+use crate::db::conversations;
+use crate::features;
+use crate::db::votes::{self, NewVote};
+use crate::models::ratings::{elo_ratings, VoteOutcome};
+use crate::routes::llm::ModelResponse;
+
+const DEFAULT_TEAM: &str = "default";
+const DEFAULT_CATEGORY: &str = "general";
+
+#[derive(Debug, Deserialize)]
+pub struct VoteRequest {
+    pub response_a: ModelResponse,
+    pub response_b: ModelResponse,
+    pub outcome: VoteOutcome,
+    pub prompt: Option<String>,
+    pub conversation_id: Option<String>,
+    pub category: Option<String>,
+    pub team: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardQuery {
+    pub team: Option<String>,
+    pub category: Option<String>,
+}
+
+// POST /llm/votes
+//
+// Everyone votes into the default team; any other team's leaderboard only
+// takes votes from its members.
+pub async fn record_vote(
+    State(state): State<AppState>,
+    claims: Option<Extension<Claims>>,
+    Json(payload): Json<VoteRequest>,
+) -> Result<Response, StatusCode> {
+    if payload.response_a.model == payload.response_b.model {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let user = user_id(&claims);
+    let team = normalize(payload.team.as_deref()).unwrap_or_else(|| DEFAULT_TEAM.to_string());
+    let member = team == DEFAULT_TEAM
+        || claims.as_ref().is_some_and(|Extension(c)| features::in_team(c, &team));
+    if !member {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // Votes may point back at the stored run they judged, but only the caller's own
+    if let Some(id) = &payload.conversation_id {
+        match conversations::get(&state.db, id).await {
+            Ok(Some(conversation)) if conversation.user_id == user => {},
+            Ok(_) => return Err(StatusCode::NOT_FOUND),
+            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+
+    let vote = NewVote {
+        team,
+        category: normalize(payload.category.as_deref()).unwrap_or_else(|| DEFAULT_CATEGORY.to_string()),
+        conversation_id: payload.conversation_id,
+        prompt: payload.prompt,
+        model_a: payload.response_a.model,
+        response_a: payload.response_a.response,
+        model_b: payload.response_b.model,
+        response_b: payload.response_b.response,
+        outcome: payload.outcome,
+        tags: payload.tags.iter().filter_map(|tag| normalize(Some(tag))).collect(),
+    };
+    let vote = votes::create(&state.db, &user, &vote).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(vote)).into_response())
+}
+
+// GET /llm/leaderboard
+pub async fn leaderboard(
+    State(state): State<AppState>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<Response, StatusCode> {
+    let team = normalize(query.team.as_deref()).unwrap_or_else(|| DEFAULT_TEAM.to_string());
+    let category = normalize(query.category.as_deref());
+
+    let team_votes = votes::for_team(&state.db, &team, category.as_deref()).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let categories = votes::categories(&state.db, &team).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let ratings = elo_ratings(team_votes.iter().filter_map(|vote| {
+        Some((vote.model_a.as_str(), vote.model_b.as_str(), vote.outcome()?))
+    }));
+
+    Ok(Json(serde_json::json!({
+        "team": team,
+        "category": category,
+        "votes": team_votes.len(),
+        "ratings": ratings,
+        "categories": categories.into_iter()
+            .map(|(category, votes)| serde_json::json!({ "category": category, "votes": votes }))
+            .collect::<Vec<_>>(),
+    })).into_response())
+}
+
+// Teams, categories and tags compare case-insensitively
+fn normalize(value: Option<&str>) -> Option<String> {
+    value.map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty())
+}
+
+// Route registration
+pub fn routes() -> axum::Router<AppState> {
+    use axum::routing::{get, post};
+
+    axum::Router::new()
+        .route("/votes", post(record_vote))
+        .route("/leaderboard", get(leaderboard))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::Config;
+
+    fn response(model: &str) -> ModelResponse {
+        ModelResponse {
+            model: model.to_string(),
+            response: format!("answer from {}", model),
+            timestamp: 0,
+            thinking_time_ms: 0,
+            step: None,
+        }
+    }
+
+    async fn vote(state: &AppState, a: &str, b: &str, outcome: VoteOutcome, category: &str) -> StatusCode {
+        let researcher = Claims { sub: "rita".to_string(), features: vec!["team:research".to_string()], ..Claims::default() };
+        vote_as(state, Some(researcher), a, b, outcome, category).await
+    }
+
+    async fn vote_as(state: &AppState, claims: Option<Claims>, a: &str, b: &str, outcome: VoteOutcome, category: &str) -> StatusCode {
+        let payload = VoteRequest {
+            response_a: response(a),
+            response_b: response(b),
+            outcome,
+            prompt: Some("which is better?".to_string()),
+            conversation_id: None,
+            category: Some(category.to_string()),
+            team: Some("Research".to_string()),
+            tags: vec![" Concise ".to_string()],
+        };
+        match record_vote(State(state.clone()), claims.map(Extension), Json(payload)).await {
+            Ok(response) => response.status(),
+            Err(status) => status,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_votes_feed_leaderboard() {
+        let state = crate::test_app_state(Config::default()).await;
+
+        assert_eq!(vote(&state, "gpt-4", "llama3", VoteOutcome::A, "code").await, StatusCode::CREATED);
+        assert_eq!(vote(&state, "llama3", "mistral", VoteOutcome::B, "Writing").await, StatusCode::CREATED);
+        assert_eq!(vote(&state, "gpt-4", "gpt-4", VoteOutcome::Tie, "code").await, StatusCode::BAD_REQUEST);
+        // Outsiders can't skew the team's leaderboard
+        let outsider = Claims { sub: "otto".to_string(), features: vec!["chat".to_string()], ..Claims::default() };
+        assert_eq!(vote_as(&state, Some(outsider), "llama3", "gpt-4", VoteOutcome::A, "code").await, StatusCode::FORBIDDEN);
+        assert_eq!(vote_as(&state, None, "llama3", "gpt-4", VoteOutcome::A, "code").await, StatusCode::FORBIDDEN);
+
+        let query = LeaderboardQuery { team: Some("research".to_string()), category: Some("code".to_string()) };
+        let response = leaderboard(State(state.clone()), Query(query)).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let board: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(board["votes"], 1);
+        assert_eq!(board["ratings"][0]["model"], "gpt-4");
+        assert_eq!(board["ratings"][1]["model"], "llama3");
+        assert_eq!(board["categories"].as_array().unwrap().len(), 2);
+
+        let stored = votes::for_team(&state.db, "research", Some("writing")).await.unwrap();
+        assert_eq!(stored[0].tags.0, vec!["concise".to_string()]);
+    }
+}
@@ -0,0 +1,295 @@
+// src/state/llm_queue.rs
+//
+// Admission queue for LLM provider calls. Each call waits for a slot under
+// its provider and model concurrency limits. When a slot frees up, the
+// highest priority waiter that fits goes next; ties go to the user with the
+// fewest calls in flight, then to whoever was served least recently, so one
+// user's burst can't starve everyone else.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Batch,
+    #[default]
+    Normal,
+    Interactive,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueueRequest {
+    pub user: String,
+    pub priority: Priority,
+    pub provider: String,
+    pub model: String,
+    pub provider_limit: usize,
+    pub model_limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStats {
+    pub queue_depth: usize,
+    pub depth_by_provider: HashMap<String, usize>,
+    pub in_flight_by_provider: HashMap<String, usize>,
+    pub in_flight_by_model: HashMap<String, usize>,
+    pub oldest_wait_ms: u64,
+    pub average_wait_ms: f64,
+    pub total_served: u64,
+}
+
+struct Waiter {
+    id: u64,
+    request: QueueRequest,
+    enqueued_at: Instant,
+    tx: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct QueueInner {
+    next_id: u64,
+    waiting: Vec<Waiter>,
+    provider_in_flight: HashMap<String, usize>,
+    model_in_flight: HashMap<String, usize>,
+    user_in_flight: HashMap<String, usize>,
+    user_last_served: HashMap<String, u64>,
+    total_served: u64,
+    total_wait_ms: u64,
+}
+
+#[derive(Default)]
+pub struct LlmQueue {
+    inner: Mutex<QueueInner>,
+}
+
+/// Held for the duration of a provider call; releasing it admits the next waiter
+pub struct QueuePermit {
+    queue: Arc<LlmQueue>,
+    request: QueueRequest,
+}
+
+// Cleans up if the acquiring future is dropped before it sees its slot
+struct WaitGuard<'a> {
+    queue: &'a LlmQueue,
+    id: u64,
+    request: &'a QueueRequest,
+    admitted: bool,
+}
+
+impl LlmQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn acquire(self: &Arc<Self>, request: QueueRequest) -> QueuePermit {
+        let (tx, rx) = oneshot::channel();
+        let id = {
+            let mut inner = self.inner.lock().unwrap();
+            let id = inner.next_id;
+            inner.next_id += 1;
+            inner.waiting.push(Waiter {
+                id,
+                request: request.clone(),
+                enqueued_at: Instant::now(),
+                tx,
+            });
+            inner.dispatch();
+            id
+        };
+
+        let mut guard = WaitGuard {
+            queue: self,
+            id,
+            request: &request,
+            admitted: false,
+        };
+        // The sender is only consumed once the slot has been granted
+        let _ = rx.await;
+        guard.admitted = true;
+        drop(guard);
+
+        QueuePermit {
+            queue: Arc::clone(self),
+            request,
+        }
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let inner = self.inner.lock().unwrap();
+        let mut depth_by_provider = HashMap::new();
+        for waiter in &inner.waiting {
+            *depth_by_provider.entry(waiter.request.provider.clone()).or_insert(0) += 1;
+        }
+
+        QueueStats {
+            queue_depth: inner.waiting.len(),
+            depth_by_provider,
+            in_flight_by_provider: inner.provider_in_flight.clone(),
+            in_flight_by_model: inner.model_in_flight.clone(),
+            oldest_wait_ms: inner.waiting.iter()
+                .map(|w| w.enqueued_at.elapsed().as_millis() as u64)
+                .max()
+                .unwrap_or(0),
+            average_wait_ms: if inner.total_served > 0 {
+                inner.total_wait_ms as f64 / inner.total_served as f64
+            } else {
+                0.0
+            },
+            total_served: inner.total_served,
+        }
+    }
+}
+
+impl QueueInner {
+    fn has_capacity(&self, request: &QueueRequest) -> bool {
+        self.provider_in_flight.get(&request.provider).copied().unwrap_or(0) < request.provider_limit
+            && self.model_in_flight.get(&request.model).copied().unwrap_or(0) < request.model_limit
+    }
+
+    // Grant slots to as many waiters as the limits allow
+    fn dispatch(&mut self) {
+        loop {
+            let next = self.waiting.iter()
+                .enumerate()
+                .filter(|(_, w)| self.has_capacity(&w.request))
+                .min_by_key(|(_, w)| {
+                    (
+                        std::cmp::Reverse(w.request.priority),
+                        self.user_in_flight.get(&w.request.user).copied().unwrap_or(0),
+                        self.user_last_served.get(&w.request.user).copied().unwrap_or(0),
+                        w.id,
+                    )
+                })
+                .map(|(i, _)| i);
+
+            let Some(index) = next else { break };
+            let waiter = self.waiting.remove(index);
+            if waiter.tx.send(()).is_err() {
+                continue;
+            }
+
+            let request = waiter.request;
+            *self.provider_in_flight.entry(request.provider).or_insert(0) += 1;
+            *self.model_in_flight.entry(request.model).or_insert(0) += 1;
+            *self.user_in_flight.entry(request.user.clone()).or_insert(0) += 1;
+            self.total_served += 1;
+            self.total_wait_ms += waiter.enqueued_at.elapsed().as_millis() as u64;
+            self.user_last_served.insert(request.user, self.total_served);
+        }
+    }
+
+    fn release(&mut self, request: &QueueRequest) {
+        for (map, key) in [
+            (&mut self.provider_in_flight, &request.provider),
+            (&mut self.model_in_flight, &request.model),
+            (&mut self.user_in_flight, &request.user),
+        ] {
+            if let Some(count) = map.get_mut(key) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    map.remove(key);
+                }
+            }
+        }
+        self.dispatch();
+    }
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        let mut inner = self.queue.inner.lock().unwrap();
+        inner.release(&self.request);
+    }
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        if self.admitted {
+            return;
+        }
+        
+        let mut inner = self.queue.inner.lock().unwrap();
+        let waiting = inner.waiting.len();
+        inner.waiting.retain(|w| w.id != self.id);
+        if inner.waiting.len() == waiting {
+            // Granted a slot nobody will use; hand it straight back
+            inner.release(self.request);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn request(user: &str, priority: Priority) -> QueueRequest {
+        QueueRequest {
+            user: user.to_string(),
+            priority,
+            provider: "ollama".to_string(),
+            model: "llama3".to_string(),
+            provider_limit: 1,
+            model_limit: usize::MAX,
+        }
+    }
+
+    // Queue requests behind a held permit, then record the order they run in
+    async fn admission_order(requests: Vec<QueueRequest>) -> Vec<String> {
+        let queue = Arc::new(LlmQueue::new());
+        let blocker = queue.acquire(request("blocker", Priority::Normal)).await;
+        let order = Arc::new(tokio::sync::Mutex::new(Vec::new()));
+
+        let mut handles = Vec::new();
+        for request in requests {
+            let queue = Arc::clone(&queue);
+            let order = Arc::clone(&order);
+            handles.push(tokio::spawn(async move {
+                let _permit = queue.acquire(request.clone()).await;
+                order.lock().await.push(request.user);
+            }));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        assert_eq!(queue.stats().queue_depth, handles.len());
+        drop(blocker);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        let order = order.lock().await.clone();
+        order
+    }
+
+    #[tokio::test]
+    async fn test_interactive_jumps_ahead_of_batch() {
+        let order = admission_order(vec![
+            request("batch-job", Priority::Batch),
+            request("chat", Priority::Interactive),
+        ]).await;
+        assert_eq!(order, ["chat", "batch-job"]);
+    }
+
+    #[tokio::test]
+    async fn test_users_are_served_round_robin() {
+        let order = admission_order(vec![
+            request("alice", Priority::Normal),
+            request("alice", Priority::Normal),
+            request("alice", Priority::Normal),
+            request("bob", Priority::Normal),
+        ]).await;
+        assert_eq!(order, ["alice", "bob", "alice", "alice"]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_leaves_queue() {
+        let queue = Arc::new(LlmQueue::new());
+        let _held = queue.acquire(request("a", Priority::Normal)).await;
+
+        let waiting = tokio::time::timeout(Duration::from_millis(10), queue.acquire(request("b", Priority::Normal))).await;
+        assert!(waiting.is_err());
+        assert_eq!(queue.stats().queue_depth, 0);
+    }
+}
@@ -1,4 +1,5 @@
 // src/state/mod.rs
+pub mod llm_queue;
 pub mod runtime;
 pub mod vault_state;
 
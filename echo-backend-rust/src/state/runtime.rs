@@ -35,14 +35,19 @@ pub struct RuntimeState {
     pub conversations: RwLock<HashMap<String, ConversationState>>,
     pub system_prompts: RwLock<HashMap<String, String>>,
     pub vault_path: Option<PathBuf>,
+    // Key fields that refer to the secrets store, so they are saved as references
+    secret_refs: HashMap<String, String>,
 }
 
 impl RuntimeState {
-    pub fn new(config: Config) -> Self {
+    pub fn new(mut config: Config) -> Self {
         let vault_path = config.vault_path.as_ref().map(PathBuf::from);
+        // Secrets stay unset until the vault passphrase is entered
+        let secret_refs = config.resolve_secrets(&HashMap::new());
         
         Self {
             config,
+            secret_refs,
             models: RwLock::new(HashMap::new()),
             conversations: RwLock::new(HashMap::new()),
             system_prompts: RwLock::new(HashMap::new()),
@@ -50,6 +55,27 @@ impl RuntimeState {
         }
     }
 
+    /// Re-resolves the config's secret references against the secrets store's values
+    pub fn apply_secrets(&mut self, values: &HashMap<String, String>) {
+        self.config.restore_secret_refs(&self.secret_refs);
+        self.secret_refs = self.config.resolve_secrets(values);
+    }
+
+    /// Points a key field at a secret, taking effect on the next `apply_secrets`.
+    /// False if there is no such field.
+    pub fn refer_to_secret(&mut self, slot: &str, name: &str) -> bool {
+        if !self.config.secret_slots().iter().any(|s| s == slot) {
+            return false;
+        }
+        self.secret_refs.insert(slot.to_string(), name.to_string());
+        true
+    }
+
+    /// Which key field refers to which secret
+    pub fn secret_refs(&self) -> &HashMap<String, String> {
+        &self.secret_refs
+    }
+
     pub async fn initialize(&self) -> Result<()> {
         // Initialize model states
         let mut models = self.models.write().await;
@@ -203,7 +229,9 @@ impl RuntimeState {
 
     // FIXED: Added save_config method
     pub async fn save_config(&self) -> Result<()> {
-        let config_str = serde_json::to_string_pretty(&self.config)?;
+        let mut config = self.config.clone();
+        config.restore_secret_refs(&self.secret_refs);
+        let config_str = serde_json::to_string_pretty(&config)?;
         tokio::fs::write("config.json", config_str).await?;
         Ok(())
     }
@@ -2,7 +2,8 @@
 pub mod vault_watcher;
 pub mod vault_indexer;
 pub mod vault_access;
+pub mod prompt_templates;
 
 pub use vault_watcher::VaultWatcher;
 pub use vault_indexer::VaultIndexer;
-pub use vault_access::{AccessScope, determine_access_scope};
\ No newline at end of file
+pub use vault_access::{AccessScope, determine_access_scope, is_public_vault_path};
\ No newline at end of file
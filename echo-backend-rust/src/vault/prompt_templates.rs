@@ -0,0 +1,325 @@
+// src/vault/prompt_templates.rs
+//
+// Prompt templates kept as vault notes. A note whose frontmatter says
+// `type: prompt` is a template: its body holds `{{variable}}` placeholders
+// and the frontmatter declares the inputs. The vault is rescanned at most
+// every few seconds and notes are re-read when their modification time
+// changes, so edits in Obsidian apply shortly after without a restart.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::RwLock;
+use walkdir::WalkDir;
+
+use crate::vault::vault_indexer::extract_frontmatter;
+use crate::vault::is_public_vault_path;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemplateInput {
+    pub name: String,
+    pub description: Option<String>,
+    pub default: Option<String>,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptTemplate {
+    pub id: String,           // frontmatter `id`, or a slug of the file name
+    pub path: PathBuf,        // vault-relative
+    pub title: String,
+    pub description: Option<String>,
+    pub model: Option<String>, // suggested model when the caller doesn't pick one
+    pub system: Option<String>,
+    pub inputs: Vec<TemplateInput>,
+    pub body: String,
+}
+
+impl PromptTemplate {
+    /// Templates outside the Public folder are private notes like any other
+    pub fn is_private(&self) -> bool {
+        !is_public_vault_path(&self.path)
+    }
+
+    /// Parse a note; returns None unless its frontmatter marks it as a prompt
+    pub fn parse(relative: &Path, content: &str) -> Option<Self> {
+        let (frontmatter, body) = extract_frontmatter(content);
+        if frontmatter.get("type").and_then(Value::as_str) != Some("prompt") {
+            return None;
+        }
+        let text = |key: &str| frontmatter.get(key).and_then(Value::as_str).map(str::to_string);
+        let stem = relative.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+        // Placeholders nobody declared are still inputs, just undocumented ones
+        let mut inputs = frontmatter.get("inputs").map(parse_inputs).unwrap_or_default();
+        for name in placeholders(&body) {
+            if !inputs.iter().any(|input| input.name == name) {
+                inputs.push(TemplateInput { name, description: None, default: None, required: true });
+            }
+        }
+
+        Some(Self {
+            id: text("id").unwrap_or_else(|| slug(&stem)),
+            path: relative.to_path_buf(),
+            title: text("title").unwrap_or(stem),
+            description: text("description"),
+            model: text("model"),
+            system: text("system"),
+            inputs,
+            body: body.trim().to_string(),
+        })
+    }
+
+    /// Fill in the placeholders, falling back to declared defaults
+    pub fn render(&self, variables: &HashMap<String, String>) -> Result<String, String> {
+        let missing: Vec<&str> = self.inputs.iter()
+            .filter(|input| input.required && input.default.is_none() && !variables.contains_key(&input.name))
+            .map(|input| input.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("Missing template variables: {}", missing.join(", ")));
+        }
+
+        let rendered = placeholder_regex().replace_all(&self.body, |captures: &regex::Captures| {
+            let name = &captures[1];
+            variables.get(name)
+                .cloned()
+                .or_else(|| self.inputs.iter().find(|i| i.name == name).and_then(|i| i.default.clone()))
+                .unwrap_or_default()
+        });
+        Ok(rendered.into_owned())
+    }
+}
+
+// How long a scan of the vault is trusted before it is walked again
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Templates found in the vault, re-parsed only when a note's mtime changes
+pub struct TemplateLibrary {
+    scan: RwLock<Scan>,
+    rescan_interval: Duration,
+}
+
+#[derive(Default)]
+struct Scan {
+    vault_path: PathBuf,
+    scanned_at: Option<Instant>,
+    notes: HashMap<PathBuf, (SystemTime, Option<PromptTemplate>)>,
+    by_id: BTreeMap<String, Vec<PromptTemplate>>, // sorted by path, so the first visible one wins
+}
+
+impl Default for TemplateLibrary {
+    fn default() -> Self {
+        Self { scan: RwLock::default(), rescan_interval: RESCAN_INTERVAL }
+    }
+}
+
+impl TemplateLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every template in the vault, sorted by id. Private templates are left
+    /// out unless `read_private` is set.
+    pub async fn list(&self, vault_path: &Path, read_private: bool) -> Vec<PromptTemplate> {
+        self.refresh(vault_path).await;
+        self.scan.read().await.by_id.values()
+            .filter_map(|templates| visible(templates, read_private).cloned())
+            .collect()
+    }
+
+    pub async fn get(&self, vault_path: &Path, id: &str, read_private: bool) -> Option<PromptTemplate> {
+        self.refresh(vault_path).await;
+        self.scan.read().await.by_id.get(id)
+            .and_then(|templates| visible(templates, read_private).cloned())
+    }
+
+    // Walks the vault once the last scan is too old. The walk and the reads
+    // happen outside the write lock, so requests only wait on the swap.
+    async fn refresh(&self, vault_path: &Path) {
+        let cached = {
+            let scan = self.scan.read().await;
+            let fresh = scan.vault_path == vault_path
+                && scan.scanned_at.is_some_and(|at| at.elapsed() < self.rescan_interval);
+            if fresh {
+                return;
+            }
+            if scan.vault_path == vault_path {
+                scan.notes.iter().map(|(path, (modified, _))| (path.clone(), *modified)).collect()
+            } else {
+                HashMap::new()
+            }
+        };
+
+        let root = vault_path.to_path_buf();
+        let files = tokio::task::spawn_blocking(move || markdown_files(&root))
+            .await
+            .unwrap_or_default();
+        let mut parsed = Vec::new();
+        for (path, relative, modified) in &files {
+            if cached.get(path) != Some(modified) {
+                let template = tokio::fs::read_to_string(path).await.ok()
+                    .and_then(|content| PromptTemplate::parse(relative, &content));
+                parsed.push((path.clone(), *modified, template));
+            }
+        }
+
+        let mut scan = self.scan.write().await;
+        if scan.vault_path != vault_path {
+            *scan = Scan { vault_path: vault_path.to_path_buf(), ..Scan::default() };
+        }
+        let present: HashSet<&PathBuf> = files.iter().map(|(path, _, _)| path).collect();
+        scan.notes.retain(|path, _| present.contains(path));
+        for (path, modified, template) in parsed {
+            scan.notes.insert(path, (modified, template));
+        }
+
+        let mut by_id: BTreeMap<String, Vec<PromptTemplate>> = BTreeMap::new();
+        for template in scan.notes.values().filter_map(|(_, template)| template.clone()) {
+            by_id.entry(template.id.clone()).or_default().push(template);
+        }
+        for templates in by_id.values_mut() {
+            templates.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        scan.by_id = by_id;
+        scan.scanned_at = Some(Instant::now());
+    }
+}
+
+fn visible(templates: &[PromptTemplate], read_private: bool) -> Option<&PromptTemplate> {
+    templates.iter().find(|template| read_private || !template.is_private())
+}
+
+// (absolute path, vault-relative path, mtime) for each note, skipping dot folders
+fn markdown_files(vault_path: &Path) -> Vec<(PathBuf, PathBuf, SystemTime)> {
+    WalkDir::new(vault_path)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|e| {
+            let modified = e.metadata().ok()?.modified().ok()?;
+            let relative = e.path().strip_prefix(vault_path).ok()?.to_path_buf();
+            Some((e.path().to_path_buf(), relative, modified))
+        })
+        .collect()
+}
+
+// `inputs` may be a plain list of names or a list of {name, description, default, required}
+fn parse_inputs(value: &Value) -> Vec<TemplateInput> {
+    value.as_array().map(|items| {
+        items.iter().filter_map(|item| match item {
+            Value::String(name) => Some(TemplateInput {
+                name: name.clone(),
+                description: None,
+                default: None,
+                required: true,
+            }),
+            Value::Object(fields) => {
+                let default = fields.get("default").map(|v| match v {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                });
+                Some(TemplateInput {
+                    name: fields.get("name")?.as_str()?.to_string(),
+                    description: fields.get("description").and_then(Value::as_str).map(str::to_string),
+                    required: fields.get("required").and_then(Value::as_bool).unwrap_or(default.is_none()),
+                    default,
+                })
+            },
+            _ => None,
+        }).collect()
+    }).unwrap_or_default()
+}
+
+fn placeholder_regex() -> Regex {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap()
+}
+
+fn placeholders(body: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for captures in placeholder_regex().captures_iter(body) {
+        if !names.iter().any(|name| name == &captures[1]) {
+            names.push(captures[1].to_string());
+        }
+    }
+    names
+}
+
+fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "---\ntype: prompt\ndescription: Summarise a text\nmodel: mock:echo\ninputs:\n  - name: text\n    description: What to summarise\n  - name: tone\n    default: neutral\n---\nSummarise in a {{ tone }} tone for {{audience}}:\n\n{{text}}\n";
+
+    #[test]
+    fn test_parse_and_render_template() {
+        let template = PromptTemplate::parse(Path::new("Prompts/Quick Summary.md"), NOTE).unwrap();
+        assert_eq!(template.id, "quick-summary");
+        assert_eq!(template.model.as_deref(), Some("mock:echo"));
+        let names: Vec<_> = template.inputs.iter().map(|i| (i.name.as_str(), i.required)).collect();
+        assert_eq!(names, vec![("text", true), ("tone", false), ("audience", true)]);
+
+        let mut variables = HashMap::from([("text".to_string(), "Long report".to_string())]);
+        assert_eq!(template.render(&variables).unwrap_err(), "Missing template variables: audience");
+        variables.insert("audience".to_string(), "managers".to_string());
+        assert_eq!(template.render(&variables).unwrap(), "Summarise in a neutral tone for managers:\n\nLong report");
+
+        assert!(PromptTemplate::parse(Path::new("note.md"), "---\ntype: note\n---\nHello").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_library_picks_up_edits() {
+        let vault = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(vault.path().join("Prompts")).unwrap();
+        let path = vault.path().join("Prompts/Quick Summary.md");
+        std::fs::write(&path, NOTE).unwrap();
+
+        let cached = TemplateLibrary::new();
+        assert_eq!(cached.list(vault.path(), true).await.len(), 1);
+        std::fs::write(vault.path().join("Prompts/Another.md"), NOTE).unwrap();
+        assert_eq!(cached.list(vault.path(), true).await.len(), 1);
+
+        let library = TemplateLibrary { rescan_interval: Duration::ZERO, ..TemplateLibrary::new() };
+        assert_eq!(library.list(vault.path(), true).await.len(), 2);
+
+        // Push the mtime forward so the change is visible on coarse-grained filesystems
+        std::fs::write(&path, NOTE.replace("Summarise in", "Condense in")).unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+        let template = library.get(vault.path(), "quick-summary", true).await.unwrap();
+        assert!(template.body.starts_with("Condense in"));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(library.list(vault.path(), true).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_private_templates_need_private_access() {
+        let vault = tempfile::tempdir().unwrap();
+        for folder in ["Public", "Private"] {
+            std::fs::create_dir_all(vault.path().join(folder)).unwrap();
+        }
+        std::fs::write(vault.path().join("Public/Shared.md"), NOTE).unwrap();
+        std::fs::write(vault.path().join("Private/Diary Prompt.md"), NOTE).unwrap();
+        std::fs::write(vault.path().join("Private/Public Speaking.md"), NOTE).unwrap();
+
+        let library = TemplateLibrary::new();
+        let ids = |templates: Vec<PromptTemplate>| templates.into_iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(ids(library.list(vault.path(), false).await), vec!["shared"]);
+        assert_eq!(ids(library.list(vault.path(), true).await), vec!["diary-prompt", "public-speaking", "shared"]);
+        assert!(library.get(vault.path(), "diary-prompt", false).await.is_none());
+        assert!(library.get(vault.path(), "public-speaking", false).await.is_none());
+    }
+}
@@ -1,5 +1,5 @@
 // src/vault/vault_access.rs
-use std::path::Path;
+use std::path::{Component, Path};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AccessScope {
@@ -23,6 +23,16 @@ pub fn determine_access_scope(path: &Path) -> AccessScope {
     }
 }
 
+/// Whether a vault-relative path is in the Public folder. Only the first
+/// component counts, so `Private/public-speaking.md` stays private; anything
+/// outside Public is private.
+pub fn is_public_vault_path(relative: &Path) -> bool {
+    matches!(
+        relative.components().next(),
+        Some(Component::Normal(first)) if first.eq_ignore_ascii_case("public")
+    )
+}
+
 pub fn is_accessible(path: &Path, required_scope: AccessScope) -> bool {
     let file_scope = determine_access_scope(path);
     
@@ -55,4 +65,14 @@ mod tests {
             AccessScope::System
         );
     }
+
+    #[test]
+    fn test_public_vault_path_uses_the_top_folder() {
+        assert!(is_public_vault_path(Path::new("Public/note.md")));
+        assert!(is_public_vault_path(Path::new("public/Drafts/note.md")));
+        assert!(!is_public_vault_path(Path::new("Private/public-speaking.md")));
+        assert!(!is_public_vault_path(Path::new("Private/Publications/x.md")));
+        assert!(!is_public_vault_path(Path::new("Notes/public.md")));
+        assert!(!is_public_vault_path(Path::new("/Public/note.md")));
+    }
 }
\ No newline at end of file
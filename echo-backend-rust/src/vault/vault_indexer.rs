@@ -52,8 +52,14 @@ impl VaultIndexer {
     pub async fn new(vault_path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
         let vault_path = vault_path.as_ref().to_path_buf();
         
-        // Initialize MeiliSearch client with correct API key
-        let client = Client::new("http://localhost:7700", Some("HfcjPnJMXnoXpF3iFk24_M-phz95JesleVwcyOqCaY"));
+        // The indexer starts before the secrets store can be unlocked, so the
+        // master key comes from the environment, the same variable Meilisearch
+        // itself reads (see meilisearch.toml)
+        let master_key = std::env::var("MEILI_MASTER_KEY").ok().filter(|key| !key.is_empty());
+        if master_key.is_none() {
+            tracing::warn!("MEILI_MASTER_KEY is not set; Meilisearch requests will be unauthenticated");
+        }
+        let client = Client::new("http://localhost:7700", master_key);
         
         // Create or get the vault-notes index
         let uid = "vault-notes";
@@ -154,7 +160,7 @@ impl VaultIndexer {
         let metadata = fs::metadata(path)?;
         
         // Parse frontmatter
-        let (frontmatter, body) = self.extract_frontmatter(&content);
+        let (frontmatter, body) = extract_frontmatter(&content);
         
         // Extract title from frontmatter or first heading
         let title = frontmatter.get("title")
@@ -248,22 +254,6 @@ impl VaultIndexer {
     
     // Helper methods
     
-    fn extract_frontmatter(&self, content: &str) -> (HashMap<String, serde_json::Value>, String) {
-        let re = Regex::new(r"^---\n(.*?)\n---\n(.*)").unwrap();
-        
-        if let Some(captures) = re.captures(content) {
-            let yaml_str = captures.get(1).map_or("", |m| m.as_str());
-            let body = captures.get(2).map_or("", |m| m.as_str());
-            
-            match serde_yaml::from_str::<HashMap<String, serde_json::Value>>(yaml_str) {
-                Ok(frontmatter) => (frontmatter, body.to_string()),
-                Err(_) => (HashMap::new(), content.to_string()),
-            }
-        } else {
-            (HashMap::new(), content.to_string())
-        }
-    }
-    
     fn extract_first_heading(&self, content: &str) -> Option<String> {
         let re = Regex::new(r"^#+\s+(.+)$").unwrap();
         content.lines()
@@ -324,6 +314,25 @@ impl VaultIndexer {
     }
 }
 
+/// Split a note into its YAML frontmatter and body. Notes without valid
+/// frontmatter come back unchanged with an empty map.
+pub fn extract_frontmatter(content: &str) -> (HashMap<String, serde_json::Value>, String) {
+    // (?s) so the frontmatter block can span lines; Windows line endings are accepted too
+    let re = Regex::new(r"(?s)^---\r?\n(.*?)\r?\n---\r?\n(.*)").unwrap();
+    
+    if let Some(captures) = re.captures(content) {
+        let yaml_str = captures.get(1).map_or("", |m| m.as_str());
+        let body = captures.get(2).map_or("", |m| m.as_str());
+        
+        match serde_yaml::from_str::<HashMap<String, serde_json::Value>>(yaml_str) {
+            Ok(frontmatter) => (frontmatter, body.to_string()),
+            Err(_) => (HashMap::new(), content.to_string()),
+        }
+    } else {
+        (HashMap::new(), content.to_string())
+    }
+}
+
 // Standalone function for scanning vault files
 pub async fn scan_vault_files(
     vault_path: &Path, 